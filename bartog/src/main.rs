@@ -12,8 +12,82 @@ mod wasm {
     }
 }
 
+#[cfg(target_arch = "wasm32")]
 fn run() {
     let params = platform::get_state_params();
     let state = game::BartogState::new(params);
     platform::run(state);
-}
\ No newline at end of file
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run() {
+    let params = platform::get_state_params();
+
+    match network_mode(std::env::args().skip(1)) {
+        Some(NetworkMode::Host(address, human_count, bots)) => {
+            if let Err(error) = game::host(params, address.as_deref(), human_count, &bots) {
+                match address {
+                    Some(address) => eprintln!("could not host on {}: {}", address, error),
+                    None => eprintln!("could not start the bots: {}", error),
+                }
+            }
+        }
+        Some(NetworkMode::Join(address)) => match game::RemoteState::new(params, &address) {
+            Ok(state) => platform::run(state),
+            Err(error) => eprintln!("could not join {}: {}", address, error),
+        },
+        None => {
+            let state = game::BartogState::new(params);
+            platform::run(state);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, PartialEq, Eq)]
+enum NetworkMode {
+    /// The address to listen on, if anyone will join, how many people will join, and the
+    /// bot commands.
+    Host(Option<String>, u8, Vec<String>),
+    Join(String),
+}
+
+/// Finds `--host [address] [--players n]` or `--join address`, if either was passed.
+/// Each `--bot command` adds a bot to the hosted game, and hosts one if `--host` was not
+/// passed, which no one else joins unless `--players` says so. A game only for bots does
+/// not listen for anyone at all.
+#[cfg(not(target_arch = "wasm32"))]
+fn network_mode(args: impl Iterator<Item = String>) -> Option<NetworkMode> {
+    let args: Vec<String> = args.collect();
+    let value_after = |flag: &str| {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| args.get(i + 1))
+            .filter(|value| !value.starts_with("--"))
+            .cloned()
+    };
+
+    let bots: Vec<String> = args
+        .windows(2)
+        .filter(|pair| pair[0] == "--bot")
+        .map(|pair| pair[1].clone())
+        .collect();
+    let hosting = args.iter().any(|arg| arg == "--host");
+
+    if hosting || !bots.is_empty() {
+        let address = value_after("--host")
+            .unwrap_or_else(|| if hosting { "0.0.0.0" } else { "127.0.0.1" }.to_owned());
+        let human_count = value_after("--players")
+            .and_then(|count| count.parse().ok())
+            .unwrap_or(if bots.is_empty() { 2 } else { 0 });
+        let listening = hosting || human_count > 0;
+
+        Some(NetworkMode::Host(
+            listening.then_some(address),
+            human_count,
+            bots,
+        ))
+    } else {
+        value_after("--join").map(NetworkMode::Join)
+    }
+}
@@ -2,7 +2,7 @@ use choices::choose_suit;
 use common::{xs::Xs, *};
 use game_state::{
     event_push,
-    in_game::{self, CardMovement, Change, RelativeHand, RelativePlayer},
+    in_game::{self, CardMovement, Change, RelativeHand, RelativePlayer, StalemateResolution},
     optionally_event_push, EventLog, GameState, Rules,
 };
 use platform_types::{Speaker, SFX};
@@ -48,10 +48,10 @@ impl ApplyToState for CardMovement {
             );
         }
 
-        let players = self.affected.absolute_players(state.current_player);
+        let players = self.affected.absolute_players(state, state.current_player);
 
         for player in players {
-            let source_str = self.source.apply(player).to_string();
+            let source_str = self.source.apply(state, player).name(state.human_seats);
             let card = {
                 if self.source == RelativeHand::Deck {
                     let source: &Hand = state.get_relative_hand_mut(self.source, player);
@@ -74,7 +74,7 @@ impl ApplyToState for CardMovement {
             if let Some(card) = card {
                 optionally_event_push!(
                     event_log,
-                    player_name(player).as_bytes(),
+                    player_name(state.human_seats, player).as_bytes(),
                     b" moves ",
                     self.selection.to_string().as_bytes(),
                     b" from ",
@@ -110,12 +110,12 @@ impl ApplyToState for CardMovement {
                     card,
                     x,
                     y,
-                    get_move_action(self.target, player),
+                    get_move_action(state, self.target, player),
                 ));
             } else {
                 optionally_event_push!(
                     event_log,
-                    player_name(player).as_bytes(),
+                    player_name(state.human_seats, player).as_bytes(),
                     b" tries to move ",
                     self.selection.to_string().as_bytes(),
                     b" from ",
@@ -131,11 +131,11 @@ impl ApplyToState for CardMovement {
     }
 }
 
-fn get_move_action(hand: RelativeHand, player: PlayerID) -> Action {
+fn get_move_action(state: &in_game::State, hand: RelativeHand, player: PlayerID) -> Action {
     match hand {
         RelativeHand::Deck => Action::MoveToDeck,
         RelativeHand::Discard => Action::MoveToDiscard,
-        RelativeHand::Player(p) => Action::MoveToHand(p.apply(player)),
+        RelativeHand::Player(p) => Action::MoveToHand(state.resolve(p, player)),
     }
 }
 
@@ -146,7 +146,7 @@ impl ApplyToState for RelativePlayer {
         _rng: &mut Xs,
         event_log: &mut Option<&mut EventLog>,
     ) {
-        let new_player = self.apply(state.current_player);
+        let new_player = state.resolve(*self, state.current_player);
         let new_player_str = new_player.to_string();
 
         optionally_event_push!(
@@ -158,7 +158,7 @@ impl ApplyToState for RelativePlayer {
 
         state.current_player =
                     //apply Previous to undo the autonatic incrementation that will happen later
-                        RelativePlayer::Previous.apply(new_player);
+                        state.resolve(RelativePlayer::Previous, new_player);
     }
 }
 
@@ -166,7 +166,7 @@ pub fn play_to_discard(state: &mut GameState, card: Card) {
     play_to_discard_parts(
         &mut state.in_game,
         &state.rules,
-        &mut state.rng,
+        &mut state.rngs.reshuffle,
         &mut Some(&mut state.event_log),
         card,
     )
@@ -183,25 +183,53 @@ pub fn play_to_discard_parts(
     }
 
     in_game.discard.push(card);
-    for change in rules.when_played.get_card_changes(card) {
-        if let Some(event_log) = event_log {
-            event_push!(
-                event_log,
-                b"change" as &[u8],
-                format!("{:?}", change).as_bytes() as &[u8]
-            );
+    for card_flags in rules.when_played.get_card_flags(card) {
+        let provenance = rules.provenance.get_when_played(card_flags);
+        let secret = rules.secret_number(provenance);
+
+        if let Some(event_log) = event_log.as_mut() {
+            match (provenance, secret) {
+                (_, Some(number)) => event_push!(
+                    event_log,
+                    b"rule #",
+                    number.to_string().as_bytes(),
+                    b" triggers.",
+                ),
+                (Some(p), None) => event_push!(
+                    event_log,
+                    b"rule #",
+                    p.number.to_string().as_bytes(),
+                    b" by ",
+                    player_name(in_game.human_seats, p.author).as_bytes(),
+                    b" triggers:",
+                ),
+                (None, None) => {}
+            }
         }
 
-        change.apply_to_state(in_game, rng, event_log);
+        for change in rules.when_played.get_card_flags_changes(card_flags) {
+            if let (Some(event_log), None) = (event_log.as_mut(), secret) {
+                event_push!(event_log, b" * ", change.to_string().as_bytes());
+            }
+
+            change.apply_to_state(in_game, rng, event_log);
+        }
     }
 }
 
-fn log_wild_selection(state: &mut GameState, player: PlayerID) {
-    if let Some(suit) = state.in_game.top_wild_declared_as {
-        let player_name = player_name(player);
+pub fn log_wild_selection(state: &mut GameState, player: PlayerID) {
+    log_wild_selection_parts(&state.in_game, &mut state.event_log, player)
+}
+pub fn log_wild_selection_parts(
+    in_game: &in_game::State,
+    event_log: &mut EventLog,
+    player: PlayerID,
+) {
+    if let Some(suit) = in_game.top_wild_declared_as {
+        let player_name = player_name(in_game.human_seats, player);
         let suit_str = get_suit_str(suit);
         event_push!(
-            state.event_log,
+            event_log,
             player_name.as_bytes(),
             b" selected ",
             suit_str.as_bytes(),
@@ -210,6 +238,93 @@ fn log_wild_selection(state: &mut GameState, player: PlayerID) {
     }
 }
 
+/// Has `player` play the card at `index` in their hand, on their turn. Returns whether
+/// they played or drew a card, once their turn is over, or `None` if the card cannot be
+/// played now, so they still need to choose what to do.
+pub fn play_from_hand(game_state: &mut GameState, player: PlayerID, index: u8) -> Option<bool> {
+    let state = &mut game_state.in_game;
+    let rules = &game_state.rules;
+    let event_log = &mut game_state.event_log;
+
+    let blocker = state
+        .get_hand(player)
+        .get(index)
+        .map(|&card| (card, rules.get_blocker(state, card)));
+
+    match blocker {
+        Some((_, None)) => {
+            state.current_player = player;
+            add_discard_animation(state, index, event_log, rules);
+            Some(true)
+        }
+        Some((card, Some(blocker))) if game_state.config.hidden_rules => {
+            // In Mao, an illegal play costs a card and the turn.
+            event_log.push_hr();
+            match rules.get_secret_cause(state, card, blocker) {
+                Some(number) => event_push!(
+                    event_log,
+                    b"penalty: failure to follow rule #",
+                    number.to_string().as_bytes(),
+                    b".",
+                ),
+                None => event_push!(
+                    event_log,
+                    b"penalty: the ",
+                    get_card_string(card).as_bytes(),
+                    b" cannot be played there.",
+                ),
+            }
+
+            Some(draw_on_turn(game_state, player))
+        }
+        _ => None,
+    }
+}
+
+/// Has `player` draw a card instead of playing one, on their turn. Returns whether there
+/// was a card to draw.
+pub fn draw_on_turn(game_state: &mut GameState, player: PlayerID) -> bool {
+    game_state.in_game.current_player = player;
+    add_draw_animation(
+        &mut game_state.in_game,
+        &mut game_state.event_log,
+        &mut game_state.rngs.reshuffle,
+    )
+}
+
+/// Checks for winners, and if the current player has had their turn, passes `acted`,
+/// which says whether they played or drew a card, on to `in_game::State::end_turn`.
+pub fn finish_turn(game_state: &mut GameState, acted: Option<bool>) {
+    let state = &mut game_state.in_game;
+
+    if state.no_winners_yet() {
+        state.winners = state.empty_handed_players();
+    }
+
+    let resolution = acted.and_then(|acted| state.end_turn(acted, &mut game_state.rngs.reshuffle));
+    if let Some(resolution) = resolution {
+        announce_stalemate(state, &mut game_state.event_log, resolution);
+    }
+}
+
+fn announce_stalemate(
+    state: &in_game::State,
+    event_log: &mut EventLog,
+    resolution: StalemateResolution,
+) {
+    event_log.push_hr();
+    event_log.push(b"no one could play or draw a card for a whole lap.");
+    match resolution {
+        StalemateResolution::Draw | StalemateResolution::FewestCards => {
+            event_log.push(state.get_winner_text().as_bytes());
+        }
+        StalemateResolution::Reshuffle => {
+            event_log.push(b"stalemate! the whole discard pile is shuffled into the deck.");
+        }
+    }
+    event_log.push_hr();
+}
+
 pub fn advance(state: &mut GameState, speaker: &mut Speaker) {
     // I should really be able to use `Vec::retain` here,
     // but that passes a `&T` insteead of a `&mut T`.
@@ -221,7 +336,14 @@ pub fn advance(state: &mut GameState, speaker: &mut Speaker) {
 
             let last_pos = (animation.card.x, animation.card.y);
 
-            animation.approach_target();
+            match state.watch.animation_steps() {
+                Some(steps) => {
+                    for _ in 0..steps {
+                        animation.approach_target();
+                    }
+                }
+                None => animation.skip_to_target(),
+            }
 
             (animation.is_complete(), last_pos)
         };
@@ -237,7 +359,7 @@ pub fn advance(state: &mut GameState, speaker: &mut Speaker) {
                     speaker.request_sfx(SFX::CardPlace);
                 }
                 Action::SelectWild(player_id) => {
-                    if is_cpu_player(player_id) {
+                    if is_cpu_player(state.in_game.human_seats, player_id) {
                         state.in_game.top_wild_declared_as = {
                             let hand = state.in_game.get_hand(player_id);
                             hand.most_common_suit()
@@ -285,7 +407,7 @@ pub fn add_discard_animation(
 ) {
     let player = state.current_player;
     if let Some(card) = state.remove_positioned_card(player, card_index) {
-        let player_name = player_name(player);
+        let player_name = player_name(state.human_seats, player);
 
         let card_string = get_card_string(card.card);
 
@@ -313,14 +435,18 @@ pub fn add_discard_animation(
     }
 }
 
+/// Returns whether there was a card to draw.
 pub fn add_draw_animation(
     state: &mut in_game::State,
     event_log: &mut EventLog,
     rng: &mut Xs,
-) {
+) -> bool {
     let player = state.current_player;
     if let Some(animation) = get_draw_animation(state, player, event_log, rng) {
         state.card_animations.push(animation);
+        true
+    } else {
+        false
     }
 }
 
@@ -347,7 +473,7 @@ fn get_draw_animation(
 
     let (x, y) = get_card_position(spread, len + 1, len);
 
-    let player_name = player_name(player);
+    let player_name = player_name(state.human_seats, player);
 
     event_push!(event_log, player_name.as_bytes(), b" drew a card.");
 
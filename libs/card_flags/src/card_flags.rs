@@ -6,7 +6,11 @@ use std::fmt;
 pub struct CardFlags(u64);
 
 pub const ONE_PAST_CARD_FLAGS_MAX: u64 = 1 << DECK_SIZE as u64;
+/// Every card in a standard deck, leaving out the jokers.
 pub const ALL_FLAGS: u64 = ONE_PAST_CARD_FLAGS_MAX - 1;
+pub const JOKER_FLAGS: u64 = (1 << RED_JOKER) | (1 << BLACK_JOKER);
+/// Every card face, including the jokers.
+pub const ALL_FACE_FLAGS: u64 = ALL_FLAGS | JOKER_FLAGS;
 
 const GENERATION_DECK: [u8; 16] = [0, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 4];
 
@@ -34,11 +38,11 @@ impl CardFlags {
 
 impl CardFlags {
     pub fn new(edges: u64) -> Self {
-        CardFlags(edges & (ONE_PAST_CARD_FLAGS_MAX - 1))
+        CardFlags(edges & ALL_FACE_FLAGS)
     }
 
     pub fn has_card(&self, card: Card) -> bool {
-        self.0 & (1 << card) != 0
+        self.0 & (1 << get_face(card)) != 0
     }
 
     pub fn toggle_card(&mut self, card: Card) {
@@ -55,20 +59,22 @@ impl CardFlags {
     }
 
     pub fn set_card(&mut self, card: Card) {
-        self.0 |= 1 << card;
+        self.0 |= 1 << get_face(card);
     }
     pub fn unset_card(&mut self, card: Card) {
-        self.0 &= !(1 << card);
+        self.0 &= !(1 << get_face(card));
     }
 
+    /// Inverts which of the standard cards are in the set. The jokers are left as they
+    /// were, so inverting a set picked from the standard cards does not add them.
     pub fn invert(&mut self) {
-        *self = CardFlags::new(!self.0);
+        *self = CardFlags::new((!self.0 & ALL_FLAGS) | (self.0 & JOKER_FLAGS));
     }
 
     pub fn cards(&self) -> Vec<Card> {
-        let mut output = Vec::with_capacity(DECK_SIZE as _);
+        let mut output = Vec::with_capacity(FACE_COUNT as _);
 
-        for card in 0..DECK_SIZE {
+        for card in 0..FACE_COUNT {
             if self.has_card(card) {
                 output.push(card);
             }
@@ -102,6 +108,64 @@ impl CardFlags {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Returns whether every card in `other` is also in `self`.
+    pub fn contains_all(&self, other: CardFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns every card, regardless of suit, whose value satisfies `predicate`. An ace
+    /// has the value 1, and a jack, queen and king have the values 11, 12 and 13.
+    pub fn with_values(predicate: impl Fn(u8) -> bool) -> Self {
+        let mut output = 0;
+
+        for rank in 0..RANK_COUNT {
+            if predicate(rank + 1) {
+                output |= RANK_FLAGS[rank as usize];
+            }
+        }
+
+        CardFlags::new(output)
+    }
+}
+
+/// Commonly wanted sets of cards, including the families suggested by the "card(n)"
+/// notation in `design/rule_space.md`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CardSet {
+    All,
+    Suit(Suit),
+    Red,
+    Black,
+    Face,
+    Rank(Rank),
+    Odds,
+    Evens,
+    Primes,
+    MultiplesOf(u8),
+}
+
+impl CardSet {
+    pub fn flags(self) -> CardFlags {
+        match self {
+            CardSet::All => CardFlags::new(ALL_FLAGS),
+            CardSet::Suit(suit) => {
+                CardFlags::new(SUIT_FLAGS.get(suit as usize).cloned().unwrap_or_default())
+            }
+            CardSet::Red => CardFlags::new(RED_FLAGS),
+            CardSet::Black => CardFlags::new(BLACK_FLAGS),
+            CardSet::Face => CardFlags::new(RED_FACE_FLAGS | BLACK_FACE_FLAGS),
+            CardSet::Rank(rank) => {
+                CardFlags::new(RANK_FLAGS.get(rank as usize).cloned().unwrap_or_default())
+            }
+            CardSet::Odds => CardFlags::with_values(|value| value % 2 == 1),
+            CardSet::Evens => CardFlags::with_values(|value| value % 2 == 0),
+            CardSet::Primes => CardFlags::with_values(|value| {
+                value >= 2 && (2..value).all(|divisor| value % divisor != 0)
+            }),
+            CardSet::MultiplesOf(n) => CardFlags::with_values(|value| n != 0 && value % n == 0),
+        }
+    }
 }
 
 impl Iterator for CardFlags {
@@ -1788,8 +1852,12 @@ macro_rules! consecutive_ranks {
     };
 }
 
-const SPECIAL_FLAGS: [u64; 584] = [
+/// Sets of cards that have something in common, like a suit or a rank, along with
+/// combinations of them.
+pub const SPECIAL_FLAGS: [u64; 588] = [
+    ALL_FACE_FLAGS,
     ALL_FLAGS,
+    JOKER_FLAGS,
     BLACK_FLAGS,
     RED_FLAGS,
     CLUBS_FLAGS,
@@ -2381,6 +2449,8 @@ const SPECIAL_FLAGS: [u64; 584] = [
     1 << 49,
     1 << 50,
     1 << 51,
+    1 << RED_JOKER,
+    1 << BLACK_JOKER,
 ];
 
 fn get_special_subsets(all_special_flags: &[u64], card_flags: CardFlags) -> Vec<u64> {
@@ -2539,7 +2609,9 @@ fn write_card_set_str<'f, 's>(flags: &'f u64) -> Cow<'s, str> {
     }
     match *flags {
         0 => "{}".into(),
+        ALL_FACE_FLAGS => "any card, jokers included".into(),
         ALL_FLAGS => "any card".into(),
+        JOKER_FLAGS => "the jokers".into(),
         //Colours
         BLACK_FLAGS => "the black cards".into(),
         RED_FLAGS => "the red cards".into(),
@@ -4492,7 +4564,7 @@ impl fmt::Debug for CardFlags {
                 write!(f, "{:052b}", v)
             };
         }
-        if v >= 1 << 52 {
+        if v > ALL_FACE_FLAGS {
             write!(f, "INVALID EDGES: {:?}, valid portion:", v)?;
         }
 
@@ -1,19 +1,13 @@
 use common::*;
 use game_state::{
-    can_play, get_status_text, in_game, CardFlagsChoiceState, Choice, Chosen, GameState, Status,
-    RULE_TYPES,
+    can_play, get_status_text, in_game, in_game::StalemateResolution, CardFlagsChoiceState, Choice,
+    seed_string, Chosen, Config, GameMode, GameState, LogHeading, RulesInfo, SetupPage, Status,
+    RULE_TYPES, SEED_ALPHABET, SEED_STRING_LENGTH,
 };
-use platform_types::{Button, Input, Speaker};
+use crate::request::{choose, do_request, request_mut, Frame, Request, Step};
+use platform_types::{Button, Input, Speaker, SFX};
 use std::cmp::min;
 
-//This is needed because we want to use it in scopes where other parts of the state are borrowwed.
-macro_rules! cancel_rule_selection {
-    ($state:expr) => {
-        $state.choice = Choice::NoChoice;
-        $state.status = Status::RuleSelection;
-    };
-}
-
 pub fn choose_play_again(state: &mut GameState) -> Option<()> {
     match state.choice {
         Choice::NoChoice => {
@@ -62,10 +56,72 @@ fn do_unit_choice(
                 );
 
                 framebuffer.print(winner_text.as_bytes(), x, SPRITE_SIZE, 6);
+
+                // Showing the seed lets the players replay the game, or share it.
+                let seed_text = format!("seed: {}", seed_string(state.seed_number));
+                let (x, _) = center_line_in_rect(
+                    seed_text.len() as u8,
+                    ((SPRITE_SIZE, 0), (NINE_SLICE_MAX_INTERIOR_SIZE, 0)),
+                );
+                framebuffer.print(
+                    seed_text.as_bytes(),
+                    x,
+                    SPRITE_SIZE + dimensions.1 + FONT_SIZE / 2,
+                    WHITE_INDEX,
+                );
+            }
+
+            let game_over = state.game_is_over();
+
+            let progress_text = if state.config.mode == GameMode::Daily {
+                Some(if game_over {
+                    state.daily_summary()
+                } else {
+                    format!(
+                        "daily challenge, round {} of {}. {} points so far.",
+                        state.round_count,
+                        GameMode::DAILY_ROUNDS,
+                        state.current_points()
+                    )
+                })
+            } else if state.config.target_score > 0 {
+                let scores = state.current_scores();
+                let mut text = if game_over {
+                    "game over! ".to_owned()
+                } else {
+                    format!("first to {}. ", state.config.target_score)
+                };
+                let seated = state.in_game.seated_player_ids();
+                for (i, &player) in seated.iter().enumerate() {
+                    text.push_str(&format!(
+                        "{}: {}{}",
+                        player_name(state.in_game.human_seats, player),
+                        scores[player as usize],
+                        if i + 1 == seated.len() { "." } else { ", " }
+                    ));
+                }
+                Some(text)
+            } else {
+                None
+            };
+
+            if let Some(text) = progress_text {
+                let text = reflow(&text, NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS as usize);
+
+                framebuffer.print(
+                    text.as_bytes(),
+                    SPRITE_SIZE,
+                    SCREEN_HEIGHT / 2 + SPRITE_SIZE,
+                    WHITE_INDEX,
+                );
             }
 
             {
-                let question = b"would you like to play again?";
+                let question: &[u8] = if game_over {
+                    b"set up a new game?"
+                } else {
+                    b"would you like to play again?"
+                };
 
                 let (x, y) = center_line_in_rect(
                     question.len() as u8,
@@ -78,10 +134,16 @@ fn do_unit_choice(
                 framebuffer.print(question, x, y, WHITE_INDEX);
             }
         }
+        UnitChoiceScreen::Rules if state.in_game.human_seats.is_empty() => {
+            print_choice_header(
+                framebuffer,
+                b"you are watching the cpu players. z pauses, and while paused, x plays one turn. left and right change the speed. up shows everyone's cards. press shift for this menu, again for the rules. press enter to show the event log. ready?",
+            );
+        }
         UnitChoiceScreen::Rules => {
             print_choice_header(
                 framebuffer,
-                b"use z, x, enter, shift and the arrow keys to play. press shift to show this menu again. press enter to show the event log. z and x quickly scroll through the log. use arrows and z to navigate menus and play cards. press x to draw a card. ready to play?",
+                b"use z, x, enter, shift and the arrow keys to play. press shift for this menu, again for the rules. press enter to show the event log. z and x quickly scroll through the log. use arrows and z to navigate menus and play cards. up inspects a card. x draws one. ready?",
             );
         }
     }
@@ -90,7 +152,18 @@ fn do_unit_choice(
     let h = SPRITE_SIZE * 3;
     let y = SCREEN_HEIGHT as u8 - (h + SPRITE_SIZE);
 
-    let (x, _) = center_rect_in_rect((w, h), ((0, y), (SCREEN_WIDTH as u8, h)));
+    // The rules screen always has the options button, and the winners screen has a
+    // reveal button if there is anything to reveal.
+    let has_second_control = match screen {
+        UnitChoiceScreen::Winners => !state.rules.secrets.is_empty(),
+        UnitChoiceScreen::Rules => true,
+    };
+
+    let x = if has_second_control {
+        SCREEN_WIDTH - (w + SPRITE_SIZE)
+    } else {
+        center_rect_in_rect((w, h), ((0, y), (SCREEN_WIDTH as u8, h))).0
+    };
 
     let text = "yes".to_owned();
 
@@ -110,9 +183,67 @@ fn do_unit_choice(
         }
     }
 
-    if state.context.hot != 1 {
+    if has_second_control {
+        match screen {
+            UnitChoiceScreen::Winners => {
+                let spec2 = ButtonSpec {
+                    x: SPRITE_SIZE,
+                    y,
+                    w,
+                    h,
+                    id: 2,
+                    text: "reveal".to_owned(),
+                };
+
+                if do_button(framebuffer, &mut state.context, input, speaker, &spec2) {
+                    reveal_secret_rules(state);
+                }
+            }
+            UnitChoiceScreen::Rules => {
+                let spec2 = ButtonSpec {
+                    x: SPRITE_SIZE,
+                    y,
+                    w,
+                    h,
+                    id: 2,
+                    text: "options".to_owned(),
+                };
+
+                if do_button(framebuffer, &mut state.context, input, speaker, &spec2) {
+                    state.show_options = true;
+                }
+            }
+        }
+    }
+
+    if state.context.hot != 1 && (!has_second_control || state.context.hot != 2) {
         state.context.set_next_hot(1);
+    } else if has_second_control
+        && (input.pressed_this_frame(Button::LEFT) || input.pressed_this_frame(Button::RIGHT))
+    {
+        if state.context.hot == 1 {
+            state.context.set_next_hot(2);
+        } else {
+            state.context.set_next_hot(1);
+        }
+    }
+}
+
+/// Makes any secret rules public, and opens the event log to where they are listed.
+pub fn reveal_secret_rules(state: &mut GameState) {
+    if state.rules.secrets.is_empty() {
+        return;
     }
+
+    let top_index = state.event_log.len();
+
+    state
+        .rules
+        .secrets
+        .reveal(&mut state.event_log, state.in_game.human_seats);
+
+    state.event_log.top_index = min(top_index, state.event_log.len().saturating_sub(1));
+    state.log_heading = LogHeading::Down;
 }
 
 pub fn show_rules_screen(
@@ -124,1171 +255,3098 @@ pub fn show_rules_screen(
     do_unit_choice(framebuffer, state, input, speaker, UnitChoiceScreen::Rules)
 }
 
-#[inline]
-fn do_bool_choice(
+const SETUP_MODE_ID: UIId = 1;
+const SETUP_HUMAN_COUNT_ID: UIId = 2;
+const SETUP_CPU_COUNT_ID: UIId = 3;
+const SETUP_HAND_SIZE_ID: UIId = 4;
+const SETUP_TARGET_ID: UIId = 5;
+const SETUP_DIFFICULTY_ID: UIId = 6;
+const SETUP_SEED_ID: UIId = 7;
+const SETUP_RULES_ID: UIId = 8;
+const SETUP_START_ID: UIId = 9;
+
+/// The screen where a new game is set up, before the first deal. Returns whether the
+/// player chose to start the game.
+pub fn show_setup_screen(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
     speaker: &mut Speaker,
-) {
+) -> bool {
     framebuffer.full_window();
 
+    match state.setup {
+        Some(SetupPage::RuleTypes) => {
+            do_setup_rule_types(framebuffer, state, input, speaker);
+            false
+        }
+        Some(SetupPage::Seed { number }) => {
+            do_setup_seed(framebuffer, state, input, speaker, number);
+            false
+        }
+        Some(SetupPage::Main) | None => do_setup_main(framebuffer, state, input, speaker),
+    }
+}
+
+fn do_setup_main(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) -> bool {
+    let max_heading_y = print_choice_header(framebuffer, b"new game");
+
+    let context = &mut state.context;
+    let config = &mut state.config;
+    let mut start = false;
+    let mut page = None;
+
+    let h = SPRITE_SIZE * 2;
+    let row_y = |i: u8| max_heading_y + SPRITE_SIZE / 2 + (h + FONT_SIZE / 4) * i;
+    let half_w = NINE_SLICE_MAX_INTERIOR_SIZE / 2;
+    let right_x = SPRITE_SIZE + half_w;
+
     {
-        let question = b"close this window?";
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: row_y(0),
+            w: NINE_SLICE_MAX_INTERIOR_SIZE,
+            h,
+            id: SETUP_MODE_ID,
+            text: format!("< {} >", config.mode.label()),
+        };
 
-        let (x, y) = center_line_in_rect(
-            question.len() as u8,
-            (
-                (SPRITE_SIZE, SPRITE_SIZE),
-                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
-            ),
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            config.set_mode(config.mode.next());
+        } else if context.hot == spec.id {
+            if input.pressed_this_frame(Button::LEFT) {
+                config.set_mode(config.mode.previous());
+            } else if input.pressed_this_frame(Button::RIGHT) {
+                config.set_mode(config.mode.next());
+            }
+        }
+    }
+
+    {
+        let mut count = config.human_seats.count();
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: row_y(1),
+            w: half_w,
+            h,
+            id: SETUP_HUMAN_COUNT_ID,
+            text: format!("< {} human{} >", count, if count == 1 { "" } else { "s" }),
+        };
+
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            &mut count,
+            (0, MAX_HUMAN_COUNT),
         );
+        config.human_seats = HumanSeats::new(count);
+        config.fit_cpu_count();
+    }
+
+    {
+        let count = config.cpu_count;
+        let spec = ButtonSpec {
+            x: right_x,
+            y: row_y(1),
+            w: half_w,
+            h,
+            id: SETUP_CPU_COUNT_ID,
+            text: format!("< {} cpu{} >", count, if count == 1 { "" } else { "s" }),
+        };
 
-        framebuffer.print(question, x, y, WHITE_INDEX);
+        let bounds = config.cpu_count_bounds();
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            &mut config.cpu_count,
+            bounds,
+        );
     }
 
-    let w = SPRITE_SIZE * 5;
-    let h = SPRITE_SIZE * 3;
-    let y = SCREEN_HEIGHT as u8 - (h + SPRITE_SIZE);
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: row_y(2),
+            w: half_w,
+            h,
+            id: SETUP_HAND_SIZE_ID,
+            text: format!("< {} cards >", config.hand_size),
+        };
 
-    let spec1 = ButtonSpec {
-        x: SPRITE_SIZE,
-        y,
-        w,
-        h,
-        id: 1,
-        text: "yes".to_owned(),
-    };
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            &mut config.hand_size,
+            (in_game::Deal::MIN_HAND_SIZE, in_game::Deal::MAX_HAND_SIZE),
+        );
+    }
 
-    if do_button(framebuffer, &mut state.context, input, speaker, &spec1) {
-        state.choice = Choice::Already(Chosen::Bool(true));
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: row_y(3),
+            w: half_w,
+            h,
+            id: SETUP_DIFFICULTY_ID,
+            text: format!("< {} >", config.difficulty.label()),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            config.difficulty = config.difficulty.next();
+        } else if context.hot == spec.id {
+            if input.pressed_this_frame(Button::LEFT) {
+                config.difficulty = config.difficulty.previous();
+            } else if input.pressed_this_frame(Button::RIGHT) {
+                config.difficulty = config.difficulty.next();
+            }
+        }
     }
 
-    let spec2 = ButtonSpec {
-        x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
-        y,
-        w,
-        h,
-        id: 2,
-        text: "no".to_owned(),
-    };
+    {
+        let target = config.target_score;
+        let spec = ButtonSpec {
+            x: right_x,
+            y: row_y(2),
+            w: half_w,
+            h,
+            id: SETUP_TARGET_ID,
+            text: match target {
+                0 => "< no target >".to_owned(),
+                1 => "< 1 win >".to_owned(),
+                _ => format!("< {} wins >", target),
+            },
+        };
 
-    if do_button(framebuffer, &mut state.context, input, speaker, &spec2) {
-        state.choice = Choice::Already(Chosen::Bool(false));
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            &mut config.target_score,
+            (0, Config::MAX_TARGET_SCORE),
+        );
     }
 
-    if state.context.hot != 1 && state.context.hot != 2 {
-        state.context.set_next_hot(1);
-    } else if input.pressed_this_frame(Button::LEFT) || input.pressed_this_frame(Button::RIGHT) {
-        if state.context.hot == 1 {
-            state.context.set_next_hot(2);
-        } else {
-            state.context.set_next_hot(1);
+    {
+        let spec = ButtonSpec {
+            x: right_x,
+            y: row_y(3),
+            w: half_w,
+            h,
+            id: SETUP_SEED_ID,
+            text: match config.seed {
+                Some(number) => format!("seed {}", seed_string(number)),
+                None => "random seed".to_owned(),
+            },
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            page = Some(SetupPage::Seed {
+                number: config.seed.unwrap_or(0),
+            });
         }
     }
-}
 
-pub fn choose_suit(state: &mut GameState) -> Option<Suit> {
-    match state.choice {
-        Choice::NoChoice => {
-            state.choice = Choice::OfSuit;
-            None
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: row_y(4),
+            w: half_w,
+            h,
+            id: SETUP_RULES_ID,
+            text: "rules".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            page = Some(SetupPage::RuleTypes);
         }
-        Choice::Already(Chosen::Suit(suit)) => {
-            state.choice = Choice::NoChoice;
-            Some(suit)
+    }
+
+    {
+        let spec = ButtonSpec {
+            x: right_x,
+            y: row_y(4),
+            w: half_w,
+            h,
+            id: SETUP_START_ID,
+            text: "start".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            start = true;
         }
-        _ => None,
     }
+
+    {
+        let description: Option<&str> = match context.hot {
+            SETUP_MODE_ID => Some(config.mode.description()),
+            SETUP_HUMAN_COUNT_ID if config.human_seats.is_empty() => {
+                Some("no one plays. watch the cpu players instead.")
+            }
+            SETUP_HUMAN_COUNT_ID => Some("how many people take turns on this device."),
+            SETUP_CPU_COUNT_ID => Some("how many cpu players play too."),
+            SETUP_HAND_SIZE_ID => Some("how many cards everyone starts with."),
+            SETUP_DIFFICULTY_ID => Some(match config.difficulty {
+                in_game::Difficulty::Easy => "cpu players play any card they can.",
+                in_game::Difficulty::Normal => "cpu players think each play through.",
+                in_game::Difficulty::Hard => "cpu players also save their wild cards.",
+            }),
+            SETUP_TARGET_ID if config.target_score == 0 => Some("play as many rounds as you like."),
+            SETUP_TARGET_ID => Some("win this many rounds to win the game."),
+            SETUP_SEED_ID => Some("the same seed deals the same cards."),
+            SETUP_RULES_ID => Some("which types of rules can be made."),
+            _ => None,
+        };
+
+        if let Some(description) = description {
+            let x = SPRITE_SIZE / 2;
+            let width_in_chars = (SCREEN_WIDTH - x * 2) / FONT_ADVANCE;
+            let reflowed = bytes_reflow(description.as_bytes(), width_in_chars as usize);
+            for (i, line) in bytes_lines(&reflowed).enumerate() {
+                framebuffer.print(line, x, row_y(5) + FONT_SIZE * i as u8, 6);
+            }
+        }
+    }
+
+    if context.hot == 0 || context.hot > SETUP_START_ID {
+        context.set_next_hot(1);
+    } else if input.pressed_this_frame(Button::UP) {
+        let next = dice_mod(context.hot - 1, SETUP_START_ID);
+        context.set_next_hot(next);
+    } else if input.pressed_this_frame(Button::DOWN) {
+        let next = dice_mod(context.hot + 1, SETUP_START_ID);
+        context.set_next_hot(next);
+    }
+
+    if let Some(page) = page {
+        state.setup = Some(page);
+        state.context.set_next_hot(1);
+    }
+
+    start
 }
 
-#[inline]
-pub fn do_suit_choice(
+const SETUP_SECRET_ID: UIId = RULE_TYPES.len() as UIId + 1;
+const SETUP_BACK_ID: UIId = SETUP_SECRET_ID + 1;
+
+fn do_setup_rule_types(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
     speaker: &mut Speaker,
 ) {
-    if input.gamepad.contains(Button::B) {
-        return;
+    let max_heading_y = print_choice_header(framebuffer, b"which rules can change?");
+
+    let context = &mut state.context;
+    let config = &mut state.config;
+    let mut back = false;
+
+    let row_h = SPRITE_SIZE + FONT_SIZE / 2;
+    let top = max_heading_y + SPRITE_SIZE;
+
+    for (i, &status) in RULE_TYPES.iter().enumerate() {
+        let spec = CheckboxSpec {
+            x: SPRITE_SIZE,
+            y: top + row_h * i as u8,
+            id: i as UIId + 1,
+            checked: config.rule_types[i],
+            text: get_status_text(status).to_owned(),
+        };
+
+        if do_checkbox(framebuffer, context, input, speaker, &spec) {
+            config.rule_types[i] = !config.rule_types[i];
+        }
     }
 
-    framebuffer.full_window();
     {
-        let text = b"choose a suit for the card";
-
-        let (x, _) = center_line_in_rect(
-            text.len() as u8,
-            (
-                (SPRITE_SIZE, SPRITE_SIZE),
-                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
-            ),
-        );
+        let spec = CheckboxSpec {
+            x: SPRITE_SIZE,
+            y: top + row_h * RULE_TYPES.len() as u8 + FONT_SIZE / 2,
+            id: SETUP_SECRET_ID,
+            checked: config.hidden_rules,
+            text: "secret rules".to_owned(),
+        };
 
-        framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
+        if do_checkbox(framebuffer, context, input, speaker, &spec) {
+            config.hidden_rules = !config.hidden_rules;
+        }
     }
 
-    let w = NINE_SLICE_MAX_INTERIOR_SIZE;
-    let h = SPRITE_SIZE * 3;
-    let x = SPRITE_SIZE;
-
-    for (i, suit) in suits::ALL.iter().cloned().enumerate() {
-        let i = (i + 1) as u8;
-
-        let (_, suit_char) = get_suit_colour_and_char(suit);
-
-        let mut text = String::with_capacity(1);
-        text.push(char::from(suit_char));
-
+    {
+        let w = SPRITE_SIZE * 4;
+        let h = SPRITE_SIZE * 2;
         let spec = ButtonSpec {
-            x,
-            y: h * i,
+            x: SCREEN_WIDTH - (w + SPRITE_SIZE),
+            y: SCREEN_HEIGHT - (h + SPRITE_SIZE),
             w,
             h,
-            id: i,
-            text,
+            id: SETUP_BACK_ID,
+            text: "back".to_owned(),
         };
 
-        if do_button(framebuffer, &mut state.context, input, speaker, &spec) {
-            state.choice = Choice::Already(Chosen::Suit(suit));
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            back = true;
         }
     }
 
-    if state.context.hot == 0 || state.context.hot > 4 {
-        state.context.set_next_hot(1);
+    if !config.rule_types.iter().any(|&enabled| enabled) {
+        framebuffer.print(
+            b"no rules will change.",
+            SPRITE_SIZE,
+            SCREEN_HEIGHT - (SPRITE_SIZE * 4),
+            6,
+        );
+    }
+
+    if context.hot == 0 || context.hot > SETUP_BACK_ID {
+        context.set_next_hot(1);
     } else if input.pressed_this_frame(Button::UP) {
-        let next = dice_mod(state.context.hot - 1, 4);
-        state.context.set_next_hot(next);
+        let next = dice_mod(context.hot - 1, SETUP_BACK_ID);
+        context.set_next_hot(next);
     } else if input.pressed_this_frame(Button::DOWN) {
-        let next = dice_mod(state.context.hot + 1, 4);
-        state.context.set_next_hot(next);
+        let next = dice_mod(context.hot + 1, SETUP_BACK_ID);
+        context.set_next_hot(next);
     }
-}
 
-fn dice_mod(x: u8, m: u8) -> u8 {
-    if x == 0 {
-        m
-    } else {
-        (x.saturating_sub(1) % m).saturating_add(1)
+    if back || input.pressed_this_frame(Button::B) {
+        state.setup = Some(SetupPage::Main);
+        state.context.set_next_hot(SETUP_RULES_ID);
     }
 }
 
-pub fn choose_in_game_changes(state: &mut GameState) -> in_game::ChoiceState {
-    match state.choice {
-        Choice::NoChoice => {
-            state.choice = Choice::OfInGameChanges(d!());
-            d!()
-        }
-        Choice::Already(Chosen::InGameChanges(_)) => {
-            if let Choice::Already(Chosen::InGameChanges(choice_state)) = state.choice.take() {
-                choice_state
-            } else {
-                invariant_violation!({ d!() }, "Somehow we're multi-threaded or somthing?!")
-            }
-        }
-        _ => d!(),
-    }
-}
+const SEED_DIGIT_COUNT: UIId = SEED_STRING_LENGTH as UIId;
+const SEED_DONE_ID: UIId = SEED_DIGIT_COUNT + 1;
+const SEED_RANDOM_ID: UIId = SEED_DONE_ID + 1;
 
-#[inline]
-pub fn do_in_game_changes_choice(
+fn do_setup_seed(
     framebuffer: &mut Framebuffer,
     state: &mut GameState,
     input: Input,
     speaker: &mut Speaker,
+    mut number: u32,
 ) {
-    if let Choice::OfInGameChanges(ref mut choice_state) = state.choice {
-        match choice_state.layer {
-            in_game::Layer::Card => {
-                let mut sub_choice = in_game::ChoiceStateAndRules {
-                    choice_state,
-                    rules: &state.rules,
-                };
+    let max_heading_y = print_choice_header(framebuffer, b"enter a seed");
+
+    let context = &mut state.context;
+    let mut chosen = None;
+
+    let digit_w = SPRITE_SIZE + SPRITE_SIZE / 2;
+    let digit_advance = digit_w + 2;
+    let digit_y = max_heading_y + SPRITE_SIZE * 2;
+    let digits_x = center_rect_in_rect(
+        (digit_advance * SEED_DIGIT_COUNT - 2, 0),
+        ((SPRITE_SIZE, 0), (NINE_SLICE_MAX_INTERIOR_SIZE, 0)),
+    )
+    .0;
+
+    for i in 0..SEED_DIGIT_COUNT {
+        let shift = 5 * (SEED_DIGIT_COUNT - 1 - i) as u32;
+        // The first character only has the bits left over after the others.
+        let digit_count = 1 << min(5, 32 - shift);
+        let digit = (number >> shift) & 0x1f;
 
-                let cancel = do_card_flags_sub_choice(
-                    framebuffer,
-                    &mut state.context,
-                    input,
-                    speaker,
-                    &mut sub_choice,
-                    b"choose a set of cards.",
-                );
+        let spec = ButtonSpec {
+            x: digits_x + digit_advance * i,
+            y: digit_y,
+            w: digit_w,
+            h: SPRITE_SIZE * 2,
+            id: i + 1,
+            text: (SEED_ALPHABET[digit as usize] as char).to_string(),
+        };
 
-                if let CancelRuleChoice::Yes = cancel {
-                    cancel_rule_selection!(state);
-                } else {
-                    match choice_state.layer {
-                        in_game::Layer::Changes => {
-                            let card_changes = state
-                                .rules
-                                .when_played
-                                .get_card_flags_changes(choice_state.card_set);
-                            choice_state.changes.clear();
-                            for change in card_changes {
-                                choice_state.changes.push(change);
-                            }
-                        }
-                        in_game::Layer::Done | in_game::Layer::Card => {}
-                    }
-                }
-            }
-            in_game::Layer::Changes => {
-                in_game_changes_choose_changes(
-                    framebuffer,
-                    &mut state.context,
-                    input,
-                    speaker,
-                    choice_state,
-                );
+        let change = if do_button(framebuffer, context, input, speaker, &spec) {
+            1
+        } else if context.hot != spec.id {
+            0
+        } else if input.pressed_this_frame(Button::UP) {
+            1
+        } else if input.pressed_this_frame(Button::DOWN) {
+            digit_count - 1
+        } else {
+            0
+        };
 
-                match choice_state.layer {
-                    in_game::Layer::Changes => {}
-                    in_game::Layer::Done => {
-                        state.choice = Choice::Already(Chosen::InGameChanges(choice_state.clone()));
-                    }
-                    in_game::Layer::Card => {}
-                }
-            }
-            in_game::Layer::Done => {
-                framebuffer.center_half_window();
-            }
-        }
-    } else {
-        invariant_violation!(
-            { state.choice = Choice::NoChoice },
-            "`do_in_game_changes_choice` was called with the wrong choice type!"
-        )
+        let new_digit = (digit + change) % digit_count;
+        number = (number & !(0x1f << shift)) | (new_digit << shift);
     }
-}
 
-pub fn print_choice_header(framebuffer: &mut Framebuffer, text: &[u8]) -> u8 {
-    let mut max_heading_y = heading_y(-1);
-
-    let reflowed = bytes_reflow(text, NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS as _);
-
-    for (i, line) in bytes_lines(&reflowed).enumerate() {
+    {
+        let text = b"up and down change these.";
         let (x, _) = center_line_in_rect(
-            line.len() as u8,
-            (
-                (SPRITE_SIZE, SPRITE_SIZE),
-                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
-            ),
+            text.len() as u8,
+            ((SPRITE_SIZE, SPRITE_SIZE), (NINE_SLICE_MAX_INTERIOR_SIZE, 0)),
         );
+        framebuffer.print(text, x, digit_y + SPRITE_SIZE * 3, 6);
+    }
 
-        max_heading_y = heading_y(i as i8);
+    let button_y = digit_y + SPRITE_SIZE * 5;
+    let half_w = NINE_SLICE_MAX_INTERIOR_SIZE / 2;
 
-        framebuffer.print(line, x, max_heading_y, WHITE_INDEX);
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: button_y,
+            w: half_w,
+            h: SPRITE_SIZE * 2,
+            id: SEED_DONE_ID,
+            text: "done".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            chosen = Some(Some(number));
+        }
     }
 
-    max_heading_y
-}
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE + half_w,
+            y: button_y,
+            w: half_w,
+            h: SPRITE_SIZE * 2,
+            id: SEED_RANDOM_ID,
+            text: "random".to_owned(),
+        };
 
-fn set_from_change(s: &mut Vec<u8>, change: &in_game::Change) {
-    let description = change.to_string();
-    s.clear();
-    for &b in description.as_bytes() {
-        s.push(b);
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            chosen = Some(None);
+        }
+    }
+
+    // Up and down change the digits, so left and right move between everything.
+    if context.hot == 0 || context.hot > SEED_RANDOM_ID {
+        context.set_next_hot(1);
+    } else if input.pressed_this_frame(Button::LEFT) {
+        let next = dice_mod(context.hot - 1, SEED_RANDOM_ID);
+        context.set_next_hot(next);
+    } else if input.pressed_this_frame(Button::RIGHT) {
+        let next = dice_mod(context.hot + 1, SEED_RANDOM_ID);
+        context.set_next_hot(next);
+    } else if context.hot > SEED_DIGIT_COUNT
+        && (input.pressed_this_frame(Button::UP) || input.pressed_this_frame(Button::DOWN))
+    {
+        context.set_next_hot(1);
+    }
+
+    state.setup = Some(SetupPage::Seed { number });
+
+    if let Some(seed) = chosen {
+        state.config.seed = seed;
+    }
+
+    if chosen.is_some() || input.pressed_this_frame(Button::B) {
+        state.setup = Some(SetupPage::Main);
+        state.context.set_next_hot(SETUP_SEED_ID);
     }
-    bytes_reflow_in_place(s, 18);
 }
 
-fn in_game_changes_choose_changes(
+const OPTIONS_CHAOS_ID: UIId = 4;
+
+pub fn show_options_screen(
     framebuffer: &mut Framebuffer,
-    context: &mut UIContext,
+    state: &mut GameState,
     input: Input,
     speaker: &mut Speaker,
-    choice_state: &mut in_game::ChoiceState,
 ) {
     framebuffer.full_window();
 
-    let size = choice_state.card_set.size();
-    let verb_to_be: &[u8] = if size == 1 || size == DECK_SIZE as u32 {
-        b" is "
-    } else {
-        b" are "
-    };
-
-    let card_set_string = format!("{:64}", choice_state.card_set);
-
-    let text = bytes_concat!(
-        b"choose what will happen when ",
-        card_set_string.as_bytes(),
-        verb_to_be,
-        b"played.",
-    );
+    let max_heading_y = print_choice_header(framebuffer, b"options");
 
-    let max_heading_y = print_choice_header(framebuffer, text);
+    let context = &mut state.context;
 
     {
-        let w = SPRITE_SIZE * 5;
-        let spec = ButtonSpec {
-            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
-            y: SPRITE_SIZE * 12,
-            w,
-            h: SPRITE_SIZE * 3,
+        let spec = CheckboxSpec {
+            x: SPRITE_SIZE,
+            y: max_heading_y + SPRITE_SIZE,
             id: 1,
-            text: "done".to_owned(),
+            checked: state.config.hidden_rules,
+            text: "secret rules".to_owned(),
         };
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            choice_state.layer = in_game::Layer::Done;
+        if do_checkbox(framebuffer, context, input, speaker, &spec) {
+            state.config.hidden_rules = !state.config.hidden_rules;
+
+            if !state.config.hidden_rules {
+                reveal_secret_rules(state);
+            }
         }
     }
-    const FIRST_SCROLL_START_ID: UIId = 2;
 
-    const SCROLL_ROW_COUNT: u8 = 7;
-
-    const SECOND_SCROLL_START_ID: UIId = FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT;
-
-    let min_scroll_y = max_heading_y + SPRITE_SIZE * 2;
-    let max_scroll_y = min_scroll_y + SPRITE_SIZE * SCROLL_ROW_COUNT;
-
-    let left_id_range = FIRST_SCROLL_START_ID..FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT;
+    let context = &mut state.context;
+    let config = &mut state.config;
+    let seats = state.in_game.human_seats;
+    // Everyone plays the daily challenge against the same cpu players, so those settings
+    // stay as they are.
+    let daily = config.mode == GameMode::Daily;
+    let bounds = |value: u8, min: u8, max: u8| if daily { (value, value) } else { (min, max) };
+    // The humans sit from `PLAYER_ID` down, so the seats below them are the ones cpu
+    // players sit in, and the only ones whose chaos matters.
+    let cpu_seats = PLAYER_ID_COUNT - seats.count() as usize;
+    let back_id = OPTIONS_CHAOS_ID + cpu_seats as UIId;
 
-    //The + 1 is to leave a gap to mark the loop point, and keep the modulus non-zero.
-    let left_modulus = in_game::ALL_CHANGES.len() + 1;
     {
-        let mut addition = None;
-        for id in left_id_range.clone() {
-            let i = id - FIRST_SCROLL_START_ID;
-
-            let x = SPRITE_SIZE;
-            let y = min_scroll_y + SPRITE_SIZE * i - SPRITE_SIZE / 2;
-
-            let index = (choice_state.left_scroll as usize + i as usize) % left_modulus as usize;
-            if let Some(change) = in_game::ALL_CHANGES.get(index) {
-                let label = change.row_label();
-
-                if id == context.hot {
-                    set_from_change(&mut choice_state.description, change);
-                }
-
-                let spec = RowSpec { x, y, id, label };
+        let budget = config.rule_budget;
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: max_heading_y + SPRITE_SIZE * 2 + FONT_SIZE / 4,
+            w: NINE_SLICE_MAX_INTERIOR_SIZE,
+            h: SPRITE_SIZE * 2,
+            id: 2,
+            text: format!("< {} rule changes per win >", budget),
+        };
 
-                if do_pressable_row(framebuffer, context, input, speaker, &spec) {
-                    addition = Some(index);
-                }
-            } else {
-                let spec = RowSpec {
-                    x,
-                    y,
-                    id,
-                    label: Default::default(),
-                };
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            &mut config.rule_budget,
+            bounds(budget, 1, Config::MAX_RULE_BUDGET),
+        );
+    }
 
-                do_pressable_row(framebuffer, context, input, speaker, &spec);
-            }
-        }
+    {
+        let spec = ButtonSpec {
+            x: SPRITE_SIZE,
+            y: max_heading_y + SPRITE_SIZE * 4 + FONT_SIZE / 2,
+            w: NINE_SLICE_MAX_INTERIOR_SIZE,
+            h: SPRITE_SIZE * 2,
+            id: 3,
+            text: format!("< stalemate: {} >", config.stalemate.label()),
+        };
 
-        if let Some(index) = addition {
-            if let Some(&change) = in_game::ALL_CHANGES.get(index) {
-                let i = min(
-                    choice_state.right_scroll as usize + choice_state.marker_y as usize,
-                    choice_state.changes.len(),
-                );
-                choice_state.changes.insert(i, change);
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            config.stalemate = config.stalemate.next();
+        } else if context.hot == spec.id {
+            if input.pressed_this_frame(Button::LEFT) {
+                config.stalemate = config.stalemate.previous();
+            } else if input.pressed_this_frame(Button::RIGHT) {
+                config.stalemate = config.stalemate.next();
             }
         }
     }
 
-    let right_id_range = SECOND_SCROLL_START_ID..SECOND_SCROLL_START_ID + SCROLL_ROW_COUNT;
-    //leave a gap to mark the loop point, and keep the modulus non-zero.
-    let right_modulus = choice_state.changes.len() + 1;
-    {
-        let mut removal = None;
-        let x = SPRITE_SIZE + ROW_WIDTH + SPRITE_SIZE;
-        for id in right_id_range.clone() {
-            let i = id - SECOND_SCROLL_START_ID;
+    let chaos_y = max_heading_y + SPRITE_SIZE * 6 + FONT_SIZE * 3 / 4;
+    let chaos_w = SPRITE_SIZE * 3 + SPRITE_SIZE / 4;
+    let chaos_gap = (NINE_SLICE_MAX_INTERIOR_SIZE - chaos_w * PLAYER_ID_COUNT as u8)
+        / (PLAYER_ID_COUNT as u8 - 1);
 
-            let y = min_scroll_y + SPRITE_SIZE * i - SPRITE_SIZE / 2;
+    for (i, chaos) in config.cpu_chaos.iter_mut().enumerate().take(cpu_seats) {
+        let x = SPRITE_SIZE + (chaos_w + chaos_gap) * i as u8;
 
-            let index = (choice_state.right_scroll as usize + i as usize) % right_modulus;
-            if let Some(change) = choice_state.changes.get(index) {
-                let label = change.row_label();
+        let name = player_name(seats, i as PlayerID);
+        let (name_x, _) =
+            center_line_in_rect(name.len() as u8, ((x, chaos_y), (chaos_w, FONT_SIZE)));
+        framebuffer.print(name.as_bytes(), name_x, chaos_y, 6);
 
-                if id == context.hot {
-                    set_from_change(&mut choice_state.description, change);
-                }
+        let spec = ButtonSpec {
+            x,
+            y: chaos_y + FONT_SIZE,
+            w: chaos_w,
+            h: SPRITE_SIZE * 2,
+            id: OPTIONS_CHAOS_ID + i as UIId,
+            text: format!("< {} >", chaos),
+        };
 
-                let spec = RowSpec { x, y, id, label };
+        let chaos_bounds = bounds(*chaos, 0, Config::MAX_CHAOS);
+        do_stepper(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            &spec,
+            chaos,
+            chaos_bounds,
+        );
+    }
 
-                if do_pressable_row(framebuffer, context, input, speaker, &spec) {
-                    removal = Some(index);
+    let w = SPRITE_SIZE * 4;
+    let h = SPRITE_SIZE * 2;
+    let back_x = SCREEN_WIDTH - (w + SPRITE_SIZE);
+
+    {
+        let description: Option<String> = match context.hot {
+            1 => Some("cpu players keep their rules secret, as in mao.".to_owned()),
+            2 if daily => Some("the daily challenge sets this for everyone.".to_owned()),
+            2 => Some("each winner makes this many rule changes.".to_owned()),
+            3 => Some(
+                match config.stalemate {
+                    StalemateResolution::Draw => "when play is stuck, no one wins.",
+                    StalemateResolution::FewestCards => "when play is stuck, fewest cards wins.",
+                    StalemateResolution::Reshuffle => {
+                        "when play is stuck, reshuffle the whole discard."
+                    }
                 }
-            } else {
-                let spec = RowSpec {
-                    x,
-                    y,
-                    id,
-                    label: Default::default(),
-                };
+                .to_owned(),
+            ),
+            id if daily && (OPTIONS_CHAOS_ID..back_id).contains(&id) => {
+                Some("the daily challenge sets this for everyone.".to_owned())
+            }
+            id if (OPTIONS_CHAOS_ID..back_id).contains(&id) => Some(format!(
+                "how often {} makes random rules.",
+                player_name(seats, (id - OPTIONS_CHAOS_ID) as PlayerID)
+            )),
+            _ => None,
+        };
 
-                do_pressable_row(framebuffer, context, input, speaker, &spec);
+        if let Some(description) = description {
+            let width_in_chars = (back_x - SPRITE_SIZE * 2) / FONT_ADVANCE;
+            let reflowed = bytes_reflow(description.as_bytes(), width_in_chars as usize);
+            for (i, line) in bytes_lines(&reflowed).enumerate() {
+                framebuffer.print(
+                    line,
+                    SPRITE_SIZE,
+                    chaos_y + FONT_SIZE + SPRITE_SIZE * 2 + FONT_SIZE / 4 + FONT_SIZE * i as u8,
+                    6,
+                );
             }
         }
+    }
 
-        let y = min_scroll_y + SPRITE_SIZE * choice_state.marker_y - SPRITE_SIZE;
+    let mut close = input.pressed_this_frame(Button::B);
 
-        framebuffer.row_marker(x, y, ROW_WIDTH);
+    {
+        let spec = ButtonSpec {
+            x: back_x,
+            y: SCREEN_HEIGHT - (h + SPRITE_SIZE),
+            w,
+            h,
+            id: back_id,
+            text: "back".to_owned(),
+        };
 
-        if let Some(index) = removal {
-            choice_state.changes.remove(index);
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            close = true;
         }
     }
 
-    let mut y = max_scroll_y;
-    let max_y = SCREEN_HEIGHT as u8 - SPRITE_SIZE;
-    //TODO allow scrolling?
-    for line in bytes_lines(&choice_state.description) {
-        framebuffer.print_line(line, SPRITE_SIZE, y, WHITE_INDEX);
-
-        y += FONT_SIZE;
-        if y >= max_y {
-            break;
-        }
+    if context.hot == 0 || context.hot > back_id {
+        context.set_next_hot(1);
+    } else if input.pressed_this_frame(Button::UP) {
+        let next = dice_mod(context.hot - 1, back_id);
+        context.set_next_hot(next);
+    } else if input.pressed_this_frame(Button::DOWN) {
+        let next = dice_mod(context.hot + 1, back_id);
+        context.set_next_hot(next);
     }
 
-    if context.hot < FIRST_SCROLL_START_ID as _ {
-        if context.hot == 0 {
-            context.set_next_hot(1);
-        } else if input.pressed_this_frame(Button::UP) {
-            context.set_next_hot(SECOND_SCROLL_START_ID + SCROLL_ROW_COUNT - 1);
-        } else if input.pressed_this_frame(Button::DOWN) {
-            context.set_next_hot(SECOND_SCROLL_START_ID);
-        } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT)
-        {
-            context.set_next_hot(FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT - 1);
-        }
-    } else if input.pressed_this_frame(Button::RIGHT) {
-        let next = if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
-            context.hot + SCROLL_ROW_COUNT
-        } else {
-            1
-        };
-        context.set_next_hot(next);
-    } else if input.pressed_this_frame(Button::LEFT) {
-        let next = if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
-            1
-        } else {
-            context.hot - SCROLL_ROW_COUNT
-        };
-        context.set_next_hot(next);
-    } else if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
-        let left_scroll = &mut choice_state.left_scroll;
-        *left_scroll = handle_scroll_movement(
-            context,
-            input,
-            left_id_range,
-            ModOffset {
-                modulus: left_modulus,
-                current: *left_scroll,
-                ..d!()
-            },
-        );
-    } else {
-        if input.pressed_this_frame(Button::UP) {
-            choice_state.marker_y = choice_state.marker_y.saturating_sub(1);
-        } else if input.pressed_this_frame(Button::DOWN) {
-            choice_state.marker_y = min(choice_state.marker_y + 1, SCROLL_ROW_COUNT);
-        }
+    // So a change applies to the round in progress too.
+    state.in_game.stalemate_resolution = state.config.stalemate;
 
-        let right_scroll = &mut choice_state.right_scroll;
-        *right_scroll = handle_scroll_movement(
-            context,
-            input,
-            right_id_range.clone(),
-            ModOffset {
-                modulus: right_modulus,
-                current: *right_scroll,
-                ..d!()
-            },
-        );
+    if close {
+        state.show_options = false;
+        state.context.set_next_hot(2);
     }
+}
 
-    if outside_range(&right_id_range, context.hot)
-        && inside_range(&right_id_range, context.next_hot)
-    {
-        choice_state.marker_y = context.next_hot - right_id_range.start + 1;
+/// A button for a number between `min` and `max`, inclusive, which left and right change
+/// while the button is hot. Pressing the button counts up, wrapping around, for the sake
+/// of discoverability.
+fn do_stepper(
+    framebuffer: &mut Framebuffer,
+    context: &mut UIContext,
+    input: Input,
+    speaker: &mut Speaker,
+    spec: &ButtonSpec,
+    value: &mut u8,
+    (min, max): (u8, u8),
+) {
+    if do_button(framebuffer, context, input, speaker, spec) {
+        *value = if *value >= max { min } else { *value + 1 };
+    } else if context.hot == spec.id {
+        if input.pressed_this_frame(Button::LEFT) && *value > min {
+            *value -= 1;
+        } else if input.pressed_this_frame(Button::RIGHT) && *value < max {
+            *value += 1;
+        }
     }
 }
 
-fn inside_range<Idx>(range: &Range<Idx>, x: Idx) -> bool
-where
-    Idx: PartialOrd<Idx>,
-{
-    x >= range.start && x < range.end
-}
+pub fn show_rules_info_screen(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+    info: RulesInfo,
+) {
+    framebuffer.full_window();
 
-fn outside_range<Idx>(range: &Range<Idx>, x: Idx) -> bool
-where
-    Idx: PartialOrd<Idx>,
-{
-    x < range.start || x >= range.end
-}
+    let (heading, paragraphs): (&[u8], _) = match info {
+        RulesInfo::Reference => (
+            b"current rules",
+            state.rules.get_reference(state.in_game.human_seats),
+        ),
+        RulesInfo::Timeline => (
+            b"rules timeline",
+            state.rules.get_timeline(state.in_game.human_seats),
+        ),
+    };
 
-pub fn choose_can_play_graph(state: &mut GameState) -> Vec<can_play::Change> {
-    match state.choice {
-        Choice::NoChoice => {
-            state.choice = Choice::OfCanPlayGraph(d!());
-            Vec::new()
-        }
-        Choice::Already(Chosen::CanPlayGraph(_)) => {
-            if let Choice::Already(Chosen::CanPlayGraph(changes)) = state.choice.take() {
-                changes
-            } else {
-                invariant_violation!({ Vec::new() }, "Somehow we're multi-threaded or somthing?!")
-            }
-        }
-        _ => Vec::new(),
+    print_choice_header(framebuffer, heading);
+
+    do_scrolling_paragraphs(
+        framebuffer,
+        &paragraphs,
+        &mut state.rules_info_top,
+        (heading_y(2), SCREEN_HEIGHT - SPRITE_SIZE * 2),
+        CLOSE_FOOTER,
+        input,
+        speaker,
+    );
+
+    if input.pressed_this_frame(Button::B) {
+        state.rules_info = None;
     }
 }
 
-enum CancelRuleChoice {
-    No,
-    Yes,
+pub fn show_card_inspector(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    let card = match state
+        .in_game
+        .viewer_hand()
+        .and_then(|hand| hand.get(state.in_game.hand_index))
+    {
+        Some(&card) => card,
+        None => {
+            state.show_card_inspector = false;
+            return;
+        }
+    };
+
+    framebuffer.full_window();
+
+    framebuffer.draw_card(card, SPRITE_SIZE, SPRITE_SIZE);
+
+    let card_string = get_card_string(card);
+    framebuffer.print(
+        card_string.as_bytes(),
+        SPRITE_SIZE * 2 + card::WIDTH,
+        SPRITE_SIZE + (card::HEIGHT - FONT_SIZE) / 2,
+        WHITE_INDEX,
+    );
+
+    let paragraphs = state.rules.describe_card(&state.in_game, card);
+
+    do_scrolling_paragraphs(
+        framebuffer,
+        &paragraphs,
+        &mut state.card_inspector_top,
+        (
+            SPRITE_SIZE * 2 + card::HEIGHT,
+            SCREEN_HEIGHT - SPRITE_SIZE * 2,
+        ),
+        CLOSE_FOOTER,
+        input,
+        speaker,
+    );
+
+    if input.pressed_this_frame(Button::B) {
+        state.show_card_inspector = false;
+    }
 }
 
-fn do_card_flags_sub_choice<C: CardFlagsSubChoice>(
+/// The screen shown between the turns of different human players, so the next player can
+/// take the device without the previous player's hand on show. Returns whether `player`
+/// is ready to see their hand.
+pub fn show_handoff_screen(
     framebuffer: &mut Framebuffer,
-    context: &mut UIContext,
+    state: &mut GameState,
     input: Input,
     speaker: &mut Speaker,
-    choice_state: &mut C,
-    text: &[u8],
-) -> CancelRuleChoice {
-    let mut output = CancelRuleChoice::No;
-
+    player: PlayerID,
+) -> bool {
     framebuffer.full_window();
 
-    let max_heading_y = print_choice_header(framebuffer, text);
+    let heading = format!("pass to {}", player_name(state.in_game.human_seats, player));
+    let max_heading_y = print_choice_header(framebuffer, heading.as_bytes());
+
+    {
+        let text = b"everyone else, look away!";
+        let (x, _) = center_line_in_rect(
+            text.len() as u8,
+            (
+                (SPRITE_SIZE, SPRITE_SIZE),
+                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+            ),
+        );
+
+        framebuffer.print(text, x, max_heading_y + SPRITE_SIZE, WHITE_INDEX);
+    }
 
-    let w = SPRITE_SIZE * 5;
     let h = SPRITE_SIZE * 3;
-    let x = SCREEN_WIDTH as u8 - (w + SPRITE_SIZE);
+    let spec = ButtonSpec {
+        x: SPRITE_SIZE,
+        y: SCREEN_HEIGHT - (h + SPRITE_SIZE * 2),
+        w: NINE_SLICE_MAX_INTERIOR_SIZE,
+        h,
+        id: 1,
+        text: "ready".to_owned(),
+    };
 
-    let upward_offset = SPRITE_SIZE * 3 / 4;
+    let context = &mut state.context;
+    if context.hot == 0 {
+        context.set_next_hot(spec.id);
+    }
 
-    {
-        let y = SPRITE_SIZE * 4 - upward_offset;
+    do_button(framebuffer, context, input, speaker, &spec)
+}
 
-        let spec = ButtonSpec {
-            x,
-            y,
-            w,
-            h,
-            id: 1,
-            text: "reset".to_owned(),
-        };
+const CLOSE_FOOTER: &[u8] = b"up and down scroll. x closes.";
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            choice_state.reset();
+/// Prints the paragraphs between `top_y` and `footer_y`, using the up and down buttons to
+/// scroll them, followed by `footer` which should explain how to scroll.
+fn do_scrolling_paragraphs(
+    framebuffer: &mut Framebuffer,
+    paragraphs: &[String],
+    top: &mut usize,
+    (top_y, footer_y): (u8, u8),
+    footer: &[u8],
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    let mut lines: Vec<Vec<u8>> = Vec::new();
+    for paragraph in paragraphs {
+        if paragraph.is_empty() {
+            lines.push(Vec::new());
+            continue;
         }
+
+        let reflowed = bytes_reflow(
+            paragraph.as_bytes(),
+            NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS as usize,
+        );
+        lines.extend(bytes_lines(&reflowed).map(|line| line.to_vec()));
     }
 
-    {
-        let y = SPRITE_SIZE * 7 - upward_offset * 2;
+    let visible_count = ((footer_y - top_y) / FONT_SIZE) as usize;
+    let max_top = lines.len().saturating_sub(visible_count);
 
-        let spec = ButtonSpec {
-            x,
-            y,
-            w,
-            h,
-            id: 2,
-            text: "ok".to_owned(),
-        };
+    if input.pressed_this_frame(Button::UP) && *top > 0 {
+        *top -= 1;
+        speaker.request_sfx(SFX::CardSlide);
+    } else if input.pressed_this_frame(Button::DOWN) && *top < max_top {
+        *top += 1;
+        speaker.request_sfx(SFX::CardSlide);
+    }
+    *top = min(*top, max_top);
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            choice_state.mark_done();
-        }
+    for (i, line) in lines.iter().skip(*top).take(visible_count).enumerate() {
+        framebuffer.print(line, SPRITE_SIZE, top_y + i as u8 * FONT_SIZE, WHITE_INDEX);
     }
 
-    {
-        let y = SPRITE_SIZE * 10 - upward_offset * 3;
+    let (x, _) = center_line_in_rect(
+        footer.len() as u8,
+        (
+            (SPRITE_SIZE, SPRITE_SIZE),
+            (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+        ),
+    );
+    framebuffer.print(footer, x, footer_y, 6);
+}
 
-        let spec = ButtonSpec {
-            x,
+/// Asks a yes or no question.
+#[derive(Clone, Debug)]
+pub struct BoolRequest {
+    pub question: &'static [u8],
+}
+
+impl Request for BoolRequest {
+    type Output = bool;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<bool> {
+        let Frame {
+            framebuffer,
+            context,
+            input,
+            speaker,
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
+
+        framebuffer.full_window();
+
+        {
+            let (x, y) = center_line_in_rect(
+                self.question.len() as u8,
+                (
+                    (SPRITE_SIZE, SPRITE_SIZE),
+                    (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+                ),
+            );
+
+            framebuffer.print(self.question, x, y, WHITE_INDEX);
+        }
+
+        let w = SPRITE_SIZE * 5;
+        let h = SPRITE_SIZE * 3;
+        let y = SCREEN_HEIGHT as u8 - (h + SPRITE_SIZE);
+
+        let spec1 = ButtonSpec {
+            x: SPRITE_SIZE,
             y,
             w,
             h,
-            id: 3,
-            text: "cancel".to_owned(),
+            id: 1,
+            text: "yes".to_owned(),
         };
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            output = CancelRuleChoice::Yes;
+        if do_button(framebuffer, context, input, speaker, &spec1) {
+            output = Step::Done(true);
         }
-    }
-
-    let (scroll_card, flags) = choice_state.borrow_pair_mut();
 
-    {
-        let y = SPRITE_SIZE * 13 - upward_offset * 4;
-
-        let spec = ButtonSpec {
-            x,
+        let spec2 = ButtonSpec {
+            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
             y,
             w,
             h,
-            id: 4,
-            text: "invert".to_owned(),
+            id: 2,
+            text: "no".to_owned(),
         };
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            flags.invert();
+        if do_button(framebuffer, context, input, speaker, &spec2) {
+            output = Step::Done(false);
+        }
+
+        if context.hot != 1 && context.hot != 2 {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::LEFT) || input.pressed_this_frame(Button::RIGHT) {
+            if context.hot == 1 {
+                context.set_next_hot(2);
+            } else {
+                context.set_next_hot(1);
+            }
         }
+
+        output
     }
+}
 
-    const FIRST_CHECKBOX_ID: UIId = 5;
+pub fn choose_suit(state: &mut GameState) -> Option<Suit> {
+    match choose(state, |_| SuitRequest) {
+        Step::Done(suit) => Some(suit),
+        Step::Pending | Step::Back | Step::Cancel => None,
+    }
+}
 
-    do_scrolling_card_checkbox(
-        (
+/// Asks which suit a wild card should be declared as.
+#[derive(Clone, Debug)]
+pub struct SuitRequest;
+
+impl Request for SuitRequest {
+    type Output = Suit;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<Suit> {
+        let Frame {
             framebuffer,
             context,
+            input,
             speaker,
-            scroll_card,
-            flags,
-        ),
-        input,
-        FIRST_CHECKBOX_ID,
-        max_heading_y,
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
+
+        // Holding B hides the window so the player can see the table.
+        if input.gamepad.contains(Button::B) {
+            return output;
+        }
+
+        framebuffer.full_window();
+        {
+            let text = b"choose a suit for the card";
+
+            let (x, _) = center_line_in_rect(
+                text.len() as u8,
+                (
+                    (SPRITE_SIZE, SPRITE_SIZE),
+                    (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+                ),
+            );
+
+            framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
+        }
+
+        let w = NINE_SLICE_MAX_INTERIOR_SIZE;
+        let h = SPRITE_SIZE * 3;
+        let x = SPRITE_SIZE;
+
+        for (i, suit) in suits::ALL.iter().cloned().enumerate() {
+            let i = (i + 1) as u8;
+
+            let (_, suit_char) = get_suit_colour_and_char(suit);
+
+            let mut text = String::with_capacity(1);
+            text.push(char::from(suit_char));
+
+            let spec = ButtonSpec {
+                x,
+                y: h * i,
+                w,
+                h,
+                id: i,
+                text,
+            };
+
+            if do_button(framebuffer, context, input, speaker, &spec) {
+                output = Step::Done(suit);
+            }
+        }
+
+        if context.hot == 0 || context.hot > 4 {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::UP) {
+            let next = dice_mod(context.hot - 1, 4);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            let next = dice_mod(context.hot + 1, 4);
+            context.set_next_hot(next);
+        }
+
+        output
+    }
+}
+
+/// Asks which of a few options to pick, given the text to show for each of them.
+/// Returns the index of the option picked.
+pub fn choose_option(
+    state: &mut GameState,
+    heading: &str,
+    labels: Vec<String>,
+    descriptions: Vec<Vec<String>>,
+) -> Option<u8> {
+    match choose(state, |_| OptionRequest {
+        heading: heading.to_owned(),
+        labels,
+        descriptions,
+        top: 0,
+    }) {
+        Step::Done(index) => Some(index),
+        Step::Pending | Step::Back | Step::Cancel => None,
+    }
+}
+
+/// Asks which of `labels` to pick, showing the paragraphs in `descriptions` that go with
+/// the one that is selected.
+#[derive(Clone, Debug)]
+pub struct OptionRequest {
+    pub heading: String,
+    pub labels: Vec<String>,
+    pub descriptions: Vec<Vec<String>>,
+    /// How far the description is scrolled.
+    pub top: usize,
+}
+
+impl Request for OptionRequest {
+    type Output = u8;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<u8> {
+        let Frame {
+            framebuffer,
+            context,
+            input,
+            speaker,
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
+
+        framebuffer.full_window();
+
+        let max_heading_y = print_choice_header(framebuffer, self.heading.as_bytes());
+
+        let count = self.labels.len() as u8;
+        if count == 0 {
+            return output;
+        }
+
+        let h = SPRITE_SIZE * 2;
+        let button_y = SCREEN_HEIGHT - (h + SPRITE_SIZE);
+        let footer_y = button_y - (FONT_SIZE + FONT_SIZE / 2);
+
+        if let Some(paragraphs) = self
+            .descriptions
+            .get(context.hot.saturating_sub(1) as usize)
+        {
+            do_scrolling_paragraphs(
+                framebuffer,
+                paragraphs,
+                &mut self.top,
+                (max_heading_y, footer_y),
+                b"up and down scroll.",
+                input,
+                speaker,
+            );
+        }
+
+        let w = NINE_SLICE_MAX_INTERIOR_SIZE / count;
+        for (i, label) in self.labels.iter().enumerate() {
+            let spec = ButtonSpec {
+                x: SPRITE_SIZE + w * i as u8,
+                y: button_y,
+                w,
+                h,
+                id: i as u8 + 1,
+                text: label.clone(),
+            };
+
+            if do_button(framebuffer, context, input, speaker, &spec) {
+                output = Step::Done(i as u8);
+            }
+        }
+
+        if context.hot == 0 || context.hot > count {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::LEFT) {
+            context.set_next_hot(dice_mod(context.hot - 1, count));
+            self.top = 0;
+        } else if input.pressed_this_frame(Button::RIGHT) {
+            context.set_next_hot(dice_mod(context.hot + 1, count));
+            self.top = 0;
+        }
+
+        output
+    }
+}
+
+fn dice_mod(x: u8, m: u8) -> u8 {
+    if x == 0 {
+        m
+    } else {
+        (x.saturating_sub(1) % m).saturating_add(1)
+    }
+}
+
+pub fn choose_in_game_changes(state: &mut GameState) -> Option<in_game::ChoiceState> {
+    match state.choice {
+        Choice::NoChoice => {
+            state.choice = Choice::OfInGameChanges(d!());
+            None
+        }
+        Choice::Already(Chosen::InGameChanges(_)) => {
+            if let Choice::Already(Chosen::InGameChanges(choice_state)) = state.choice.take() {
+                Some(choice_state)
+            } else {
+                invariant_violation!({ None }, "Somehow we're multi-threaded or somthing?!")
+            }
+        }
+        _ => None,
+    }
+}
+
+#[inline]
+pub fn do_in_game_changes_choice(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    if let Choice::OfInGameChanges(ref mut choice_state) = state.choice {
+        match choice_state.layer {
+            in_game::Layer::Card => {
+                let mut sub_choice = in_game::ChoiceStateAndRules {
+                    choice_state,
+                    rules: &state.rules,
+                };
+
+                let cancel = do_card_flags_sub_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    &mut sub_choice,
+                    b"choose a set of cards.",
+                    state.rules.deal.composition,
+                );
+
+                match cancel {
+                    CancelRuleChoice::Yes | CancelRuleChoice::Back => {
+                        state.pop_status();
+                    }
+                    CancelRuleChoice::No => {}
+                }
+            }
+            in_game::Layer::Changes => {
+                in_game_changes_choose_changes(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    choice_state,
+                );
+
+                match choice_state.layer {
+                    in_game::Layer::Changes => {}
+                    in_game::Layer::Done => {
+                        state.choice = Choice::Already(Chosen::InGameChanges(choice_state.clone()));
+                    }
+                    in_game::Layer::Card => {}
+                }
+            }
+            in_game::Layer::Done => {
+                framebuffer.center_half_window();
+            }
+        }
+    } else {
+        invariant_violation!(
+            { state.choice = Choice::NoChoice },
+            "`do_in_game_changes_choice` was called with the wrong choice type!"
+        )
+    }
+}
+
+pub fn print_choice_header(framebuffer: &mut Framebuffer, text: &[u8]) -> u8 {
+    let mut max_heading_y = heading_y(-1);
+
+    let reflowed = bytes_reflow(text, NINE_SLICE_MAX_INTERIOR_WIDTH_IN_CHARS as _);
+
+    for (i, line) in bytes_lines(&reflowed).enumerate() {
+        let (x, _) = center_line_in_rect(
+            line.len() as u8,
+            (
+                (SPRITE_SIZE, SPRITE_SIZE),
+                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+            ),
+        );
+
+        max_heading_y = heading_y(i as i8);
+
+        framebuffer.print(line, x, max_heading_y, WHITE_INDEX);
+    }
+
+    max_heading_y
+}
+
+fn set_from_change(s: &mut Vec<u8>, change: &in_game::Change) {
+    let description = change.to_string();
+    s.clear();
+    for &b in description.as_bytes() {
+        s.push(b);
+    }
+    bytes_reflow_in_place(s, 18);
+}
+
+fn in_game_changes_choose_changes(
+    framebuffer: &mut Framebuffer,
+    context: &mut UIContext,
+    input: Input,
+    speaker: &mut Speaker,
+    choice_state: &mut in_game::ChoiceState,
+) {
+    framebuffer.full_window();
+
+    let size = choice_state.card_set.size();
+    let verb_to_be: &[u8] = if size == 1 || size == DECK_SIZE as u32 {
+        b" is "
+    } else {
+        b" are "
+    };
+
+    let card_set_string = format!("{:64}", choice_state.card_set);
+
+    let text = bytes_concat!(
+        b"choose what will happen when ",
+        card_set_string.as_bytes(),
+        verb_to_be,
+        b"played.",
     );
 
+    let max_heading_y = print_choice_header(framebuffer, text);
+
     {
-        let x = SPRITE_SIZE * 11;
-        let y = SPRITE_SIZE * 13;
+        let w = SPRITE_SIZE * 5;
+        // There is nothing to be done if nothing was changed, so offer to go back instead.
+        let unchanged = choice_state.is_unchanged();
 
-        let lines = choice_state.get_status_lines();
+        let spec = ButtonSpec {
+            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
+            y: SPRITE_SIZE * 12,
+            w,
+            h: SPRITE_SIZE * 3,
+            id: 1,
+            text: if unchanged { "cancel" } else { "done" }.to_owned(),
+        };
 
-        framebuffer.print_line(&lines[0], x, y, WHITE_INDEX);
-        framebuffer.print_line(&lines[1], x, y + FONT_SIZE, WHITE_INDEX);
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            if unchanged {
+                choice_state.previous_layer();
+            } else {
+                choice_state.layer = in_game::Layer::Done;
+            }
+        } else if input.pressed_this_frame(Button::B) {
+            choice_state.previous_layer();
+        }
+    }
+    const FIRST_SCROLL_START_ID: UIId = 2;
+
+    const SCROLL_ROW_COUNT: u8 = 7;
+
+    const SECOND_SCROLL_START_ID: UIId = FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT;
+
+    let min_scroll_y = max_heading_y + SPRITE_SIZE * 2;
+    let max_scroll_y = min_scroll_y + SPRITE_SIZE * SCROLL_ROW_COUNT;
+
+    let left_id_range = FIRST_SCROLL_START_ID..FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT;
+
+    //The + 1 is to leave a gap to mark the loop point, and keep the modulus non-zero.
+    let left_modulus = in_game::ALL_CHANGES.len() + 1;
+    {
+        let mut addition = None;
+        for id in left_id_range.clone() {
+            let i = id - FIRST_SCROLL_START_ID;
+
+            let x = SPRITE_SIZE;
+            let y = min_scroll_y + SPRITE_SIZE * i - SPRITE_SIZE / 2;
+
+            let index = (choice_state.left_scroll as usize + i as usize) % left_modulus as usize;
+            if let Some(change) = in_game::ALL_CHANGES.get(index) {
+                let label = change.row_label();
+
+                if id == context.hot {
+                    set_from_change(&mut choice_state.description, change);
+                }
+
+                let spec = RowSpec { x, y, id, label };
+
+                if do_pressable_row(framebuffer, context, input, speaker, &spec) {
+                    addition = Some(index);
+                }
+            } else {
+                let spec = RowSpec {
+                    x,
+                    y,
+                    id,
+                    label: Default::default(),
+                };
+
+                do_pressable_row(framebuffer, context, input, speaker, &spec);
+            }
+        }
+
+        if let Some(index) = addition {
+            if let Some(&change) = in_game::ALL_CHANGES.get(index) {
+                let i = min(
+                    choice_state.right_scroll as usize + choice_state.marker_y as usize,
+                    choice_state.changes.len(),
+                );
+                choice_state.changes.insert(i, change);
+            }
+        }
+    }
+
+    let right_id_range = SECOND_SCROLL_START_ID..SECOND_SCROLL_START_ID + SCROLL_ROW_COUNT;
+    //leave a gap to mark the loop point, and keep the modulus non-zero.
+    let right_modulus = choice_state.changes.len() + 1;
+    {
+        let mut removal = None;
+        let x = SPRITE_SIZE + ROW_WIDTH + SPRITE_SIZE;
+        for id in right_id_range.clone() {
+            let i = id - SECOND_SCROLL_START_ID;
+
+            let y = min_scroll_y + SPRITE_SIZE * i - SPRITE_SIZE / 2;
+
+            let index = (choice_state.right_scroll as usize + i as usize) % right_modulus;
+            if let Some(change) = choice_state.changes.get(index) {
+                let label = change.row_label();
+
+                if id == context.hot {
+                    set_from_change(&mut choice_state.description, change);
+                }
+
+                let spec = RowSpec { x, y, id, label };
+
+                if do_pressable_row(framebuffer, context, input, speaker, &spec) {
+                    removal = Some(index);
+                }
+            } else {
+                let spec = RowSpec {
+                    x,
+                    y,
+                    id,
+                    label: Default::default(),
+                };
+
+                do_pressable_row(framebuffer, context, input, speaker, &spec);
+            }
+        }
+
+        let y = min_scroll_y + SPRITE_SIZE * choice_state.marker_y - SPRITE_SIZE;
+
+        framebuffer.row_marker(x, y, ROW_WIDTH);
+
+        if let Some(index) = removal {
+            choice_state.changes.remove(index);
+        }
+    }
+
+    let mut y = max_scroll_y;
+    let max_y = SCREEN_HEIGHT as u8 - SPRITE_SIZE;
+    //TODO allow scrolling?
+    for line in bytes_lines(&choice_state.description) {
+        framebuffer.print_line(line, SPRITE_SIZE, y, WHITE_INDEX);
+
+        y += FONT_SIZE;
+        if y >= max_y {
+            break;
+        }
+    }
+
+    if context.hot < FIRST_SCROLL_START_ID as _ {
+        if context.hot == 0 {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::UP) {
+            context.set_next_hot(SECOND_SCROLL_START_ID + SCROLL_ROW_COUNT - 1);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            context.set_next_hot(SECOND_SCROLL_START_ID);
+        } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT)
+        {
+            context.set_next_hot(FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT - 1);
+        }
+    } else if input.pressed_this_frame(Button::RIGHT) {
+        let next = if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
+            context.hot + SCROLL_ROW_COUNT
+        } else {
+            1
+        };
+        context.set_next_hot(next);
+    } else if input.pressed_this_frame(Button::LEFT) {
+        let next = if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
+            1
+        } else {
+            context.hot - SCROLL_ROW_COUNT
+        };
+        context.set_next_hot(next);
+    } else if context.hot < FIRST_SCROLL_START_ID + SCROLL_ROW_COUNT {
+        let left_scroll = &mut choice_state.left_scroll;
+        *left_scroll = handle_scroll_movement(
+            context,
+            input,
+            left_id_range,
+            ModOffset {
+                modulus: left_modulus,
+                current: *left_scroll,
+                ..d!()
+            },
+        );
+    } else {
+        if input.pressed_this_frame(Button::UP) {
+            choice_state.marker_y = choice_state.marker_y.saturating_sub(1);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            choice_state.marker_y = min(choice_state.marker_y + 1, SCROLL_ROW_COUNT);
+        }
+
+        let right_scroll = &mut choice_state.right_scroll;
+        *right_scroll = handle_scroll_movement(
+            context,
+            input,
+            right_id_range.clone(),
+            ModOffset {
+                modulus: right_modulus,
+                current: *right_scroll,
+                ..d!()
+            },
+        );
+    }
+
+    if outside_range(&right_id_range, context.hot)
+        && inside_range(&right_id_range, context.next_hot)
+    {
+        choice_state.marker_y = context.next_hot - right_id_range.start + 1;
+    }
+}
+
+fn inside_range<Idx>(range: &Range<Idx>, x: Idx) -> bool
+where
+    Idx: PartialOrd<Idx>,
+{
+    x >= range.start && x < range.end
+}
+
+fn outside_range<Idx>(range: &Range<Idx>, x: Idx) -> bool
+where
+    Idx: PartialOrd<Idx>,
+{
+    x < range.start || x >= range.end
+}
+
+pub fn choose_can_play_graph(state: &mut GameState) -> Vec<can_play::Change> {
+    match state.choice {
+        Choice::NoChoice => {
+            state.choice = Choice::OfCanPlayGraph(d!());
+            Vec::new()
+        }
+        Choice::Already(Chosen::CanPlayGraph(_)) => {
+            if let Choice::Already(Chosen::CanPlayGraph(changes)) = state.choice.take() {
+                changes
+            } else {
+                invariant_violation!({ Vec::new() }, "Somehow we're multi-threaded or somthing?!")
+            }
+        }
+        _ => Vec::new(),
+    }
+}
+
+enum CancelRuleChoice {
+    No,
+    Yes,
+    /// The player pressed `Button::B` to go back one level.
+    Back,
+}
+
+fn do_card_flags_sub_choice<C: CardFlagsSubChoice>(
+    framebuffer: &mut Framebuffer,
+    context: &mut UIContext,
+    input: Input,
+    speaker: &mut Speaker,
+    choice_state: &mut C,
+    text: &[u8],
+    composition: DeckComposition,
+) -> CancelRuleChoice {
+    let mut output = CancelRuleChoice::No;
+
+    if choice_state.quick_select_mut().open {
+        let (_, &mut flags) = choice_state.borrow_pair_mut();
+
+        if let Some(selection) = do_quick_select(
+            framebuffer,
+            context,
+            input,
+            speaker,
+            choice_state.quick_select_mut(),
+            flags,
+        ) {
+            if let Some(selection) = selection {
+                let (_, flags) = choice_state.borrow_pair_mut();
+                selection.apply(flags);
+            }
+
+            choice_state.quick_select_mut().open = false;
+            context.set_next_hot(QUICK_BUTTON_ID);
+        }
+
+        return output;
+    }
+
+    framebuffer.full_window();
+
+    let max_heading_y = print_choice_header(framebuffer, text);
+
+    let w = SPRITE_SIZE * 5;
+    let h = SPRITE_SIZE * 3;
+    let x = SCREEN_WIDTH as u8 - (w + SPRITE_SIZE);
+
+    let upward_offset = SPRITE_SIZE * 3 / 4;
+
+    {
+        let y = SPRITE_SIZE * 4 - upward_offset;
+
+        let spec = ButtonSpec {
+            x,
+            y,
+            w,
+            h,
+            id: 1,
+            text: "reset".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            choice_state.reset();
+        }
+    }
+
+    {
+        let y = SPRITE_SIZE * 7 - upward_offset * 2;
+
+        let spec = ButtonSpec {
+            x,
+            y,
+            w,
+            h,
+            id: 2,
+            text: "ok".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            choice_state.mark_done();
+        }
+    }
+
+    {
+        let y = SPRITE_SIZE * 10 - upward_offset * 3;
+
+        let spec = ButtonSpec {
+            x,
+            y,
+            w,
+            h,
+            id: 3,
+            text: "cancel".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            output = CancelRuleChoice::Yes;
+        }
+    }
+
+    {
+        let y = SPRITE_SIZE * 13 - upward_offset * 4;
+
+        let spec = ButtonSpec {
+            x,
+            y,
+            w,
+            h,
+            id: QUICK_BUTTON_ID,
+            text: "quick".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            choice_state.quick_select_mut().open = true;
+            context.set_next_hot(FIRST_QUICK_SELECT_ID);
+        }
+    }
+
+    let (scroll_card, flags) = choice_state.borrow_pair_mut();
+
+    const FIRST_CHECKBOX_ID: UIId = 5;
+
+    do_scrolling_card_checkbox(
+        (
+            framebuffer,
+            context,
+            speaker,
+            scroll_card,
+            flags,
+        ),
+        input,
+        FIRST_CHECKBOX_ID,
+        max_heading_y,
+        composition,
+    );
+
+    {
+        let x = SPRITE_SIZE * 11;
+        let y = SPRITE_SIZE * 13;
+
+        let lines = choice_state.get_status_lines();
+
+        framebuffer.print_line(&lines[0], x, y, WHITE_INDEX);
+        framebuffer.print_line(&lines[1], x, y + FONT_SIZE, WHITE_INDEX);
+    }
+
+    if let CancelRuleChoice::No = output {
+        if input.pressed_this_frame(Button::B) {
+            output = CancelRuleChoice::Back;
+        }
+    }
+
+    output
+}
+
+const QUICK_BUTTON_ID: UIId = 4;
+
+/// A one-press change to a set of cards, offered by the quick select menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum QuickSelection {
+    Nothing,
+    Invert,
+    Set(CardSet),
+}
+
+const QUICK_SELECTION_COUNT: usize = 15;
+
+impl QuickSelection {
+    /// The quick selection for each row of the menu, using the currently chosen
+    /// parameters for the rows that have one.
+    fn from_row(quick_select: &QuickSelectState, index: usize) -> Option<Self> {
+        use self::QuickSelection::*;
+
+        Some(match index {
+            0 => Set(CardSet::All),
+            1 => Nothing,
+            2 => Invert,
+            3..=6 => Set(CardSet::Suit((index - 3) as Suit)),
+            7 => Set(CardSet::Red),
+            8 => Set(CardSet::Black),
+            9 => Set(CardSet::Face),
+            10 => Set(CardSet::Rank(quick_select.rank)),
+            11 => Set(CardSet::Odds),
+            12 => Set(CardSet::Evens),
+            13 => Set(CardSet::Primes),
+            14 => Set(CardSet::MultiplesOf(quick_select.multiple)),
+            _ => return None,
+        })
+    }
+
+    /// Sets of cards are added to `flags`, unless they are all in it already, in which case
+    /// they are removed. So pressing the same row twice undoes the first press.
+    fn apply(self, flags: &mut CardFlags) {
+        match self {
+            QuickSelection::Nothing => *flags = CardFlags::new(0),
+            QuickSelection::Invert => flags.invert(),
+            QuickSelection::Set(set) => {
+                let set = set.flags();
+                *flags = CardFlags::new(if flags.contains_all(set) {
+                    flags.get_bits() & !set.get_bits()
+                } else {
+                    flags.get_bits() | set.get_bits()
+                });
+            }
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            QuickSelection::Nothing => "none".to_owned(),
+            QuickSelection::Invert => "invert".to_owned(),
+            QuickSelection::Set(set) => match set {
+                CardSet::All => "all".to_owned(),
+                CardSet::Suit(suit) => get_suit_str(suit).to_owned(),
+                CardSet::Red => "red".to_owned(),
+                CardSet::Black => "black".to_owned(),
+                CardSet::Face => "face cards".to_owned(),
+                CardSet::Rank(rank) => format!("< {}s >", get_rank_str(rank)),
+                CardSet::Odds => "odds".to_owned(),
+                CardSet::Evens => "evens".to_owned(),
+                CardSet::Primes => "primes".to_owned(),
+                CardSet::MultiplesOf(n) => format!("<mult of {}>", n),
+            },
+        }
+    }
+}
+
+fn to_row_label(label: &[u8]) -> RowLabel {
+    let mut output: RowLabel = d!();
+    let end = min(output.len(), label.len());
+    output[..end].copy_from_slice(&label[..end]);
+    output
+}
+
+const FIRST_QUICK_SELECT_ID: UIId = 1;
+
+/// Returns `Some(Some(selection))` if a selection was made, `Some(None)` if the player
+/// chose to go back, and `None` if no choice has been made yet.
+fn do_quick_select(
+    framebuffer: &mut Framebuffer,
+    context: &mut UIContext,
+    input: Input,
+    speaker: &mut Speaker,
+    quick_select: &mut QuickSelectState,
+    flags: CardFlags,
+) -> Option<Option<QuickSelection>> {
+    let mut output = None;
+
+    framebuffer.full_window();
+
+    let max_heading_y = print_choice_header(
+        framebuffer,
+        b"choose cards to select, or to deselect if all of them are selected.",
+    );
+
+    const VISIBLE_ROW_COUNT: u8 = 9;
+    let id_range = FIRST_QUICK_SELECT_ID..FIRST_QUICK_SELECT_ID + VISIBLE_ROW_COUNT;
+
+    // One row for "back", and one blank row to mark the loop point.
+    let modulus = QUICK_SELECTION_COUNT + 2;
+
+    let mut hot_row = None;
+
+    for id in id_range.clone() {
+        let i = id - FIRST_QUICK_SELECT_ID;
+        let index = (quick_select.scroll + i as usize) % modulus;
+
+        let row = QuickSelection::from_row(quick_select, index);
+
+        let label = match row {
+            Some(selection) => to_row_label(selection.label().as_bytes()),
+            None if index == QUICK_SELECTION_COUNT => to_row_label(b"back"),
+            None => d!(),
+        };
+
+        let spec = RowSpec {
+            x: SPRITE_SIZE,
+            y: max_heading_y + SPRITE_SIZE * (i + 1),
+            id,
+            label,
+        };
+
+        if context.hot == id {
+            hot_row = Some(index);
+        }
+
+        if do_pressable_row(framebuffer, context, input, speaker, &spec) {
+            match row {
+                Some(selection) => output = Some(Some(selection)),
+                None if index == QUICK_SELECTION_COUNT => output = Some(None),
+                None => {}
+            }
+        }
+    }
+
+    if let Some(selection) = hot_row.and_then(|index| QuickSelection::from_row(quick_select, index))
+    {
+        let description = match selection {
+            QuickSelection::Nothing => "deselects all cards.".to_owned(),
+            QuickSelection::Invert => "selects the unselected cards.".to_owned(),
+            QuickSelection::Set(set) => {
+                let set = set.flags();
+                format!(
+                    "{} these {} cards: {:40}.",
+                    if flags.contains_all(set) {
+                        "deselects"
+                    } else {
+                        "selects"
+                    },
+                    set.size(),
+                    set
+                )
+            }
+        };
+
+        let x = SPRITE_SIZE * 2 + ROW_WIDTH;
+        let width_in_chars = (SCREEN_WIDTH - (x + SPRITE_SIZE)) / FONT_ADVANCE;
+        let reflowed = bytes_reflow(description.as_bytes(), width_in_chars as usize);
+
+        for (i, line) in bytes_lines(&reflowed).enumerate() {
+            framebuffer.print(
+                line,
+                x,
+                max_heading_y + SPRITE_SIZE + FONT_SIZE * i as u8,
+                WHITE_INDEX,
+            );
+        }
+    }
+
+    if !inside_range(&id_range, context.hot) {
+        context.set_next_hot(FIRST_QUICK_SELECT_ID);
+    } else if input.pressed_this_frame(Button::LEFT) || input.pressed_this_frame(Button::RIGHT) {
+        let step_forward = input.pressed_this_frame(Button::RIGHT);
+        match hot_row.and_then(|index| QuickSelection::from_row(quick_select, index)) {
+            Some(QuickSelection::Set(CardSet::Rank(rank))) => {
+                quick_select.rank = if step_forward {
+                    (rank + 1) % RANK_COUNT
+                } else {
+                    (rank + RANK_COUNT - 1) % RANK_COUNT
+                };
+            }
+            Some(QuickSelection::Set(CardSet::MultiplesOf(n))) => {
+                // Multiples of 1 would be every card, so go from 2 up to the king.
+                quick_select.multiple = if step_forward {
+                    if n >= RANK_COUNT {
+                        2
+                    } else {
+                        n + 1
+                    }
+                } else if n <= 2 {
+                    RANK_COUNT
+                } else {
+                    n - 1
+                };
+            }
+            _ => {}
+        }
+    } else {
+        quick_select.scroll = handle_scroll_movement(
+            context,
+            input,
+            id_range,
+            ModOffset {
+                modulus,
+                current: quick_select.scroll,
+                ..d!()
+            },
+        );
+    }
+
+    if output.is_none() && input.pressed_this_frame(Button::B) {
+        output = Some(None);
+    }
+
+    output
+}
+
+fn do_card_sub_choice<C: CardSubChoice>(
+    framebuffer: &mut Framebuffer,
+    context: &mut UIContext,
+    input: Input,
+    speaker: &mut Speaker,
+    choice_state: &mut C,
+    composition: DeckComposition,
+) -> CancelRuleChoice {
+    let mut output = CancelRuleChoice::No;
+
+    framebuffer.full_window();
+
+    {
+        let text = b"choose a card to change.";
+
+        let (x, _) = center_line_in_rect(
+            text.len() as u8,
+            (
+                (SPRITE_SIZE, SPRITE_SIZE),
+                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+            ),
+        );
+
+        framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
+    }
+
+    let w = SPRITE_SIZE * 5;
+    let h = SPRITE_SIZE * 2;
+    let y_step = h + SPRITE_SIZE / 4;
+    let top_y = SPRITE_SIZE * 7 / 2;
+
+    {
+        let y = top_y;
+
+        let spec = ButtonSpec {
+            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
+            y,
+            w,
+            h,
+            id: 1,
+            text: "reset".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            //TODO add confirm dialog
+            choice_state.reset();
+        }
+    }
+
+    {
+        let y = top_y + y_step;
+
+        let spec = ButtonSpec {
+            x: SCREEN_WIDTH - (w + SPRITE_SIZE),
+            y,
+            w,
+            h,
+            id: 2,
+            text: "set".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            choice_state.next_set_layer();
+        }
+    }
+
+    {
+        let y = top_y + y_step * 2;
+
+        let spec = ButtonSpec {
+            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
+            y,
+            w,
+            h,
+            id: 3,
+            text: "cancel".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            output = CancelRuleChoice::Yes;
+        }
+    }
+
+    if choice_state.should_show_done_button() {
+        let y = top_y + y_step * 3;
+
+        let spec = ButtonSpec {
+            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
+            y,
+            w,
+            h,
+            id: 4,
+            text: "done".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            choice_state.mark_done();
+        }
+    }
+
+    {
+        let x = SPRITE_SIZE * 11;
+        let y = SPRITE_SIZE * 13;
+
+        let current_highlighted_card = nth_next_card(
+            *choice_state.borrow_mut(),
+            context.hot.wrapping_sub(FIRST_SCROLL_ID),
+            composition,
+        );
+
+        let lines = choice_state.get_status_lines(current_highlighted_card);
+
+        framebuffer.print_line(&lines[0], x, y, WHITE_INDEX);
+        framebuffer.print_line(&lines[1], x, y + FONT_SIZE, WHITE_INDEX);
+    }
+
+    let w = SPRITE_SIZE * 10;
+    let h = SPRITE_SIZE * 3;
+    let x = SPRITE_SIZE;
+
+    const FIRST_SCROLL_ID: UIId = 5;
+
+    const SCROLL_BUTTON_COUNT: u8 = 4;
+    let id_range = FIRST_SCROLL_ID..FIRST_SCROLL_ID + SCROLL_BUTTON_COUNT;
+    for id in id_range.clone() {
+        let i = id - FIRST_SCROLL_ID;
+        let card = nth_next_card(*choice_state.borrow_mut(), i, composition);
+        let text = get_card_string(card);
+
+        let spec = ButtonSpec {
+            x,
+            y: h * (i + 1) as u8 + (SPRITE_SIZE as u8 / 2),
+            w,
+            h,
+            id,
+            text,
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec) {
+            *choice_state.borrow_mut() = card;
+            choice_state.next_layer();
+        }
+    }
+
+    if context.hot < FIRST_SCROLL_ID as _ {
+        if context.hot == 0 {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::UP) {
+            let next = dice_mod(context.hot - 1, FIRST_SCROLL_ID - 1);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            let next = dice_mod(context.hot + 1, FIRST_SCROLL_ID - 1);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT)
+        {
+            let next = (FIRST_SCROLL_ID - 1) + context.hot;
+            context.set_next_hot(next);
+        }
+    } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT) {
+        let next = min(
+            context.hot.saturating_sub(FIRST_SCROLL_ID) + 1,
+            FIRST_SCROLL_ID - 1,
+        );
+        context.set_next_hot(next);
+    } else {
+        let card = choice_state.borrow_mut();
+        *card = handle_scroll_movement(
+            context,
+            input,
+            id_range,
+            ModOffset {
+                modulus: composition.face_count(),
+                current: *card,
+                ..d!()
+            },
+        );
+    }
+
+    if let CancelRuleChoice::No = output {
+        if input.pressed_this_frame(Button::B) {
+            output = CancelRuleChoice::Back;
+        }
+    }
+
+    output
+}
+
+use std::fmt;
+use std::ops::{Add, Range, Rem, Sub};
+
+fn handle_scroll_movement<T>(
+    context: &mut UIContext,
+    input: Input,
+    Range { start, end }: Range<UIId>,
+    mod_offset: ModOffset<T>,
+) -> T
+where
+    T: fmt::Debug
+        + From<u8>
+        + Add<T, Output = T>
+        + Sub<T, Output = T>
+        + Rem<T, Output = T>
+        + Ord
+        + Copy,
+{
+    let mut output = mod_offset.current;
+
+    let column_count = mod_offset.offset;
+
+    let mut unoffset = context.hot - start;
+    let visible_rows = end - start;
+    if visible_rows == 0 {
+        invariant_violation!(
+            {
+                return output;
+            },
+            "`visible_rows == 0`"
+        );
+    }
+
+    invariant_assert!(column_count != 0);
+    invariant_assert_eq!(mod_offset.modulus % column_count.into(), 0u8.into());
+
+    if input.pressed_this_frame(Button::UP) {
+        if unoffset < column_count {
+            output = previous_mod(mod_offset);
+        } else {
+            unoffset -= column_count;
+        }
+    } else if input.pressed_this_frame(Button::DOWN) {
+        if unoffset / column_count >= (visible_rows / column_count) - 1 {
+            output = next_mod(mod_offset);
+        } else {
+            let unoffset_mod: ModOffset<UIId> = ModOffset {
+                modulus: visible_rows,
+                current: unoffset,
+                offset: mod_offset.offset,
+            };
+            unoffset = next_mod(unoffset_mod);
+        }
+    }
+
+    context.set_next_hot(unoffset + start);
+
+    output
+}
+
+fn heading_y(i: i8) -> u8 {
+    (SPRITE_SIZE as i8 * 2 + FONT_SIZE as i8 * i) as u8
+}
+
+fn do_scrolling_card_checkbox(
+    (
+        framebuffer,
+        context,
+        speaker,
+        scroll_card,
+        card_flags,
+    ) : (
+        &mut Framebuffer,
+        &mut UIContext,
+        &mut Speaker,
+        &mut Card,
+        &mut CardFlags,
+    ),
+    input: Input,
+    first_checkbox_id: UIId,
+    max_heading_y: u8,
+    composition: DeckComposition,
+) {
+    const SCROLL_ROWS_COUNT: u8 = 10;
+    const SCROLL_COLS_COUNT: u8 = 2;
+
+    let face_count = composition.face_count();
+    invariant_assert_eq!(
+        (face_count / SCROLL_COLS_COUNT) * SCROLL_COLS_COUNT,
+        face_count,
+    );
+    let current_scroll_card = nth_next_card(*scroll_card, 0, composition);
+
+    for y in 0..SCROLL_ROWS_COUNT {
+        for x in 0..SCROLL_COLS_COUNT {
+            let i = x + y * SCROLL_COLS_COUNT;
+            let id = i as UIId + first_checkbox_id;
+
+            let card = nth_next_card(current_scroll_card, i, composition);
+            let text = get_suit_rank_pair(card);
+
+            let spec = CheckboxSpec {
+                x: SPRITE_SIZE
+                    + (SPRITE_SIZE * 2 + RANK_SUIT_PAIR_WITH_IN_CHARS * FONT_ADVANCE) * x,
+                y: max_heading_y + SPRITE_SIZE * (y + 1) as u8 + (SPRITE_SIZE as u8 / 2),
+                id,
+                text,
+                checked: card_flags.has_card(card),
+            };
+
+            if do_checkbox(framebuffer, context, input, speaker, &spec) {
+                card_flags.toggle_card(card);
+            }
+        }
+    }
+
+    if context.hot < first_checkbox_id as _ {
+        if context.hot == 0 {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::UP) {
+            let next = dice_mod(context.hot - 1, first_checkbox_id - 1);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            let next = dice_mod(context.hot + 1, first_checkbox_id - 1);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::RIGHT) {
+            if context.hot == 1 {
+                context.set_next_hot(first_checkbox_id);
+            } else {
+                context.set_next_hot(first_checkbox_id + 3 * SCROLL_COLS_COUNT);
+            }
+        } else if input.pressed_this_frame(Button::LEFT) {
+            if context.hot == 1 {
+                context.set_next_hot(first_checkbox_id + 1);
+            } else {
+                context.set_next_hot(first_checkbox_id + 3 * SCROLL_COLS_COUNT + 1);
+            }
+        }
+    } else if input.pressed_this_frame(Button::LEFT) {
+        if context.hot & 1 == first_checkbox_id & 1 {
+            if context.hot > first_checkbox_id + 3 * SCROLL_COLS_COUNT {
+                context.set_next_hot(first_checkbox_id - 1);
+            } else {
+                context.set_next_hot(first_checkbox_id - 2);
+            }
+        } else {
+            let next = context.hot - 1;
+            context.set_next_hot(next);
+        }
+    } else if input.pressed_this_frame(Button::RIGHT) {
+        context.set_next_hot(if context.hot & 1 == first_checkbox_id & 1 {
+            context.hot + 1
+        } else if context.hot > first_checkbox_id + 3 * SCROLL_COLS_COUNT {
+            first_checkbox_id - 1
+        } else {
+            first_checkbox_id - 2
+        });
+    } else {
+        *scroll_card = handle_scroll_movement(
+            context,
+            input,
+            first_checkbox_id..first_checkbox_id + (SCROLL_ROWS_COUNT * SCROLL_COLS_COUNT),
+            ModOffset {
+                modulus: face_count,
+                current: *scroll_card,
+                offset: SCROLL_COLS_COUNT,
+            },
+        );
+    }
+}
+
+#[inline]
+pub fn do_can_play_graph_choice(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    if let Choice::OfCanPlayGraph(ref mut choice_state) = state.choice {
+        let mut cancel = CancelRuleChoice::No;
+        let mut chosen = None;
+        let can_play_graph = &state.rules.can_play_graph;
+
+        match choice_state.layer {
+            can_play::Layer::Card => {
+                cancel = do_card_sub_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    choice_state,
+                    state.rules.deal.composition,
+                );
+
+                match choice_state.layer {
+                    can_play::Layer::Edges => {
+                        choice_state.edges =
+                            choice_state.get_current_edges(can_play_graph, choice_state.card);
+                        choice_state.reset_edges = choice_state.edges;
+                    }
+                    can_play::Layer::Done => {
+                        chosen = Some(choice_state.changes.clone());
+                    }
+                    can_play::Layer::Card
+                    | can_play::Layer::Set
+                    | can_play::Layer::SetOperation
+                    | can_play::Layer::SetEdges => {}
+                }
+            }
+            can_play::Layer::Edges => {
+                let text = bytes_concat!(
+                    b"choose the cards the ",
+                    get_card_string(choice_state.card).as_bytes(),
+                    b" can be played on.",
+                );
+
+                cancel = do_card_flags_sub_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    choice_state,
+                    text,
+                    state.rules.deal.composition,
+                );
+
+                if let can_play::Layer::Card = choice_state.layer {
+                    choice_state
+                        .changes
+                        .push(can_play::Change::new(choice_state.edges, choice_state.card));
+                }
+            }
+            can_play::Layer::Set => {
+                cancel = do_card_flags_sub_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    choice_state,
+                    b"choose a set of cards to change together.",
+                    state.rules.deal.composition,
+                );
+            }
+            can_play::Layer::SetOperation => {
+                let get_edges = |card| choice_state.get_current_edges(can_play_graph, card);
+                let (union, intersection) =
+                    can_play::get_union_and_intersection(choice_state.set, get_edges);
+
+                match do_set_operation_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    (choice_state.set, union, intersection),
+                ) {
+                    Some(Some(operation)) => {
+                        choice_state.operation = operation;
+                        choice_state.edges = match operation {
+                            can_play::SetOperation::Add | can_play::SetOperation::Remove => {
+                                CardFlags::new(0)
+                            }
+                            can_play::SetOperation::Replace => intersection,
+                        };
+                        choice_state.reset_edges = choice_state.edges;
+                        choice_state.layer = can_play::Layer::SetEdges;
+                    }
+                    Some(None) => {
+                        choice_state.previous_layer();
+                    }
+                    None => {}
+                }
+            }
+            can_play::Layer::SetEdges => {
+                let set_string = format!("{:40}", choice_state.set);
+                let text = match choice_state.operation {
+                    can_play::SetOperation::Add => bytes_concat!(
+                        b"choose cards that ",
+                        set_string.as_bytes(),
+                        b" can also be played on.",
+                    ),
+                    can_play::SetOperation::Remove => bytes_concat!(
+                        b"choose cards that ",
+                        set_string.as_bytes(),
+                        b" can no longer be played on.",
+                    ),
+                    can_play::SetOperation::Replace => bytes_concat!(
+                        b"choose the cards ",
+                        set_string.as_bytes(),
+                        b" can be played on.",
+                    ),
+                };
+
+                cancel = do_card_flags_sub_choice(
+                    framebuffer,
+                    &mut state.context,
+                    input,
+                    speaker,
+                    choice_state,
+                    text,
+                    state.rules.deal.composition,
+                );
+
+                if let can_play::Layer::Card = choice_state.layer {
+                    let set_change = can_play::SetChange {
+                        cards: choice_state.set,
+                        operation: choice_state.operation,
+                        edges: choice_state.edges,
+                    };
+
+                    let expanded = set_change
+                        .expand(|card| choice_state.get_current_edges(can_play_graph, card));
+
+                    choice_state.changes.extend(expanded);
+                }
+            }
+            can_play::Layer::Done => {
+                framebuffer.center_half_window();
+            }
+        }
+
+        match cancel {
+            CancelRuleChoice::Yes => {
+                state.pop_status();
+            }
+            CancelRuleChoice::Back => {
+                if !choice_state.previous_layer() {
+                    state.pop_status();
+                }
+            }
+            CancelRuleChoice::No => {
+                if let Some(changes) = chosen {
+                    state.choice = Choice::Already(Chosen::CanPlayGraph(changes));
+                }
+            }
+        }
+    } else {
+        invariant_violation!(
+            { state.choice = Choice::NoChoice },
+            "`do_can_play_graph_choice` was called with the wrong choice type!"
+        )
     }
-
-    output
 }
 
-fn do_card_sub_choice<C: CardSubChoice>(
+/// Returns `Some(Some(operation))` if an operation was chosen, `Some(None)` if the
+/// player chose to go back, either with the button or `Button::B`, and `None` if no
+/// choice has been made yet.
+fn do_set_operation_choice(
     framebuffer: &mut Framebuffer,
     context: &mut UIContext,
     input: Input,
     speaker: &mut Speaker,
-    choice_state: &mut C,
-) -> CancelRuleChoice {
-    let mut output = CancelRuleChoice::No;
+    (set, union, intersection): (CardFlags, CardFlags, CardFlags),
+) -> Option<Option<can_play::SetOperation>> {
+    let mut output = None;
 
     framebuffer.full_window();
 
-    {
-        let text = b"choose a card to change.";
-
-        let (x, _) = center_line_in_rect(
-            text.len() as u8,
-            (
-                (SPRITE_SIZE, SPRITE_SIZE),
-                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
-            ),
-        );
+    let text = format!(
+        "{:40} can all be played on {:40}. some can be played on {:40}.",
+        set, intersection, union
+    );
 
-        framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
-    }
+    print_choice_header(framebuffer, text.as_bytes());
 
-    let w = SPRITE_SIZE * 5;
+    let w = SPRITE_SIZE * 6;
     let h = SPRITE_SIZE * 3;
-
-    {
-        let y = SPRITE_SIZE * 4;
-
+    let left_x = SPRITE_SIZE;
+    let right_x = SCREEN_WIDTH - (w + SPRITE_SIZE);
+    let top_y = SCREEN_HEIGHT - (h * 2 + SPRITE_SIZE * 3 / 2);
+    let bottom_y = SCREEN_HEIGHT - (h + SPRITE_SIZE);
+
+    let buttons = [
+        (1, left_x, top_y, "add", Some(can_play::SetOperation::Add)),
+        (2, right_x, top_y, "remove", Some(can_play::SetOperation::Remove)),
+        (3, left_x, bottom_y, "replace", Some(can_play::SetOperation::Replace)),
+        (4, right_x, bottom_y, "back", None),
+    ];
+
+    for &(id, x, y, text, operation) in buttons.iter() {
         let spec = ButtonSpec {
-            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
+            x,
             y,
             w,
             h,
-            id: 1,
-            text: "reset".to_owned(),
+            id,
+            text: text.to_owned(),
         };
 
         if do_button(framebuffer, context, input, speaker, &spec) {
-            //TODO add confirm dialog
-            choice_state.reset();
+            output = Some(operation);
         }
     }
 
-    {
-        let y = SPRITE_SIZE * 7;
+    if output.is_none() && input.pressed_this_frame(Button::B) {
+        output = Some(None);
+    }
 
-        let spec = ButtonSpec {
-            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
-            y,
-            w,
-            h,
-            id: 2,
-            text: "cancel".to_owned(),
+    if context.hot < 1 || context.hot > 4 {
+        context.set_next_hot(1);
+    } else if input.pressed_this_frame(Button::LEFT) || input.pressed_this_frame(Button::RIGHT) {
+        let next = if context.hot % 2 == 1 {
+            context.hot + 1
+        } else {
+            context.hot - 1
         };
-
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            output = CancelRuleChoice::Yes;
-        }
+        context.set_next_hot(next);
+    } else if input.pressed_this_frame(Button::UP) || input.pressed_this_frame(Button::DOWN) {
+        let next = if context.hot <= 2 {
+            context.hot + 2
+        } else {
+            context.hot - 2
+        };
+        context.set_next_hot(next);
     }
 
-    if choice_state.should_show_done_button() {
-        let y = SPRITE_SIZE * 10;
+    output
+}
 
-        let spec = ButtonSpec {
-            x: SCREEN_WIDTH as u8 - (w + SPRITE_SIZE),
-            y,
-            w,
-            h,
-            id: 3,
-            text: "done".to_owned(),
-        };
+pub fn choose_rule(state: &mut GameState) -> Option<Status> {
+    match choose(state, |state| StatusRequest {
+        changes_left: state.rule_budget_left,
+        enabled: state.config.rule_types,
+        chooser: if state.in_game.human_seats.count() > 1 {
+            Some(player_name(state.in_game.human_seats, state.rule_maker()))
+        } else {
+            None
+        },
+    }) {
+        Step::Done(status) => Some(status),
+        Step::Pending | Step::Back | Step::Cancel => None,
+    }
+}
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            choice_state.mark_done();
+/// Asks for a set of cards, starting with `original_flags` selected.
+#[derive(Clone, Debug)]
+pub struct CardFlagsRequest {
+    pub choice_state: CardFlagsChoiceState,
+    pub text: &'static [u8],
+    /// Which cards can be picked from.
+    pub composition: DeckComposition,
+}
+
+impl CardFlagsRequest {
+    pub fn new(
+        original_flags: CardFlags,
+        text: &'static [u8],
+        composition: DeckComposition,
+    ) -> Self {
+        CardFlagsRequest {
+            choice_state: CardFlagsChoiceState::new(original_flags),
+            text,
+            composition,
         }
     }
+}
 
-    {
-        let x = SPRITE_SIZE * 11;
-        let y = SPRITE_SIZE * 13;
-
-        let current_highlighted_card =
-            (*choice_state.borrow_mut() + context.hot.wrapping_sub(FIRST_SCROLL_ID)) % DECK_SIZE;
+impl Request for CardFlagsRequest {
+    type Output = CardFlags;
 
-        let lines = choice_state.get_status_lines(current_highlighted_card);
+    fn frame(&mut self, frame: &mut Frame) -> Step<CardFlags> {
+        let cancel = do_card_flags_sub_choice(
+            frame.framebuffer,
+            frame.context,
+            frame.input,
+            frame.speaker,
+            &mut self.choice_state,
+            self.text,
+            self.composition,
+        );
 
-        framebuffer.print_line(&lines[0], x, y, WHITE_INDEX);
-        framebuffer.print_line(&lines[1], x, y + FONT_SIZE, WHITE_INDEX);
+        if let CancelRuleChoice::Yes = cancel {
+            Step::Cancel
+        } else if let CancelRuleChoice::Back = cancel {
+            Step::Back
+        } else if let Some(flags) = self.choice_state.get_chosen() {
+            Step::Done(flags)
+        } else {
+            Step::Pending
+        }
     }
+}
 
-    let w = SPRITE_SIZE * 10;
-    let h = SPRITE_SIZE * 3;
-    let x = SPRITE_SIZE;
+/// Asks which of the cards in `composition` should be wild, starting with `flags` selected.
+pub fn wild_flags_request(
+    original_flags: CardFlags,
+    flags: CardFlags,
+    composition: DeckComposition,
+) -> CardFlagsRequest {
+    CardFlagsRequest {
+        choice_state: CardFlagsChoiceState::with_flags(original_flags, flags),
+        text: b"select which cards are wild",
+        composition,
+    }
+}
 
-    const FIRST_SCROLL_ID: UIId = 4;
+pub fn choose_wild_flags(state: &mut GameState) -> Option<CardFlags> {
+    match choose(state, |state| {
+        wild_flags_request(
+            state.rules.wild,
+            state.rules.wild,
+            state.rules.deal.composition,
+        )
+    }) {
+        Step::Done(flags) => Some(flags),
+        Step::Pending => None,
+        Step::Back | Step::Cancel => {
+            state.pop_status();
+            None
+        }
+    }
+}
 
-    const SCROLL_BUTTON_COUNT: u8 = 4;
-    let id_range = FIRST_SCROLL_ID..FIRST_SCROLL_ID + SCROLL_BUTTON_COUNT;
-    for id in id_range.clone() {
-        let i = id - FIRST_SCROLL_ID;
-        let card = nth_next_card(*choice_state.borrow_mut(), i);
-        let text = get_card_string(card);
+/// Asks how the cards should be dealt, starting with `deal`.
+#[derive(Clone, Debug)]
+pub struct DealRequest {
+    pub deal: in_game::Deal,
+    /// Who is sitting where, to name the players by.
+    pub seats: HumanSeats,
+}
 
-        let spec = ButtonSpec {
-            x,
-            y: h * (i + 1) as u8 + (SPRITE_SIZE as u8 / 2),
-            w,
-            h,
-            id,
-            text,
-        };
+const DEAL_FLIP_ID: UIId = PLAYER_ID_COUNT as UIId + 1;
+const DEAL_ROTATE_ID: UIId = DEAL_FLIP_ID + 1;
+const DEAL_DECKS_ID: UIId = DEAL_ROTATE_ID + 1;
+const DEAL_JOKERS_ID: UIId = DEAL_DECKS_ID + 1;
+const DEAL_DONE_ID: UIId = DEAL_JOKERS_ID + 1;
 
-        if do_button(framebuffer, context, input, speaker, &spec) {
-            *choice_state.borrow_mut() = card;
-            choice_state.next_layer();
-        }
-    }
+impl Request for DealRequest {
+    type Output = in_game::Deal;
 
-    if context.hot < FIRST_SCROLL_ID as _ {
-        if context.hot == 0 {
-            context.set_next_hot(1);
-        } else if input.pressed_this_frame(Button::UP) {
-            let next = dice_mod(context.hot - 1, 3);
-            context.set_next_hot(next);
-        } else if input.pressed_this_frame(Button::DOWN) {
-            let next = dice_mod(context.hot + 1, 3);
-            context.set_next_hot(next);
-        } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT)
-        {
-            let next = (FIRST_SCROLL_ID - 1) + context.hot;
-            context.set_next_hot(next);
-        }
-    } else if input.pressed_this_frame(Button::RIGHT) || input.pressed_this_frame(Button::LEFT) {
-        let next = min(
-            context.hot.saturating_sub(FIRST_SCROLL_ID) + 1,
-            FIRST_SCROLL_ID - 1,
-        );
-        context.set_next_hot(next);
-    } else {
-        let card = choice_state.borrow_mut();
-        *card = handle_scroll_movement(
+    fn frame(&mut self, frame: &mut Frame) -> Step<in_game::Deal> {
+        let Frame {
+            framebuffer,
             context,
             input,
-            id_range,
-            ModOffset {
-                modulus: DECK_SIZE,
-                current: *card,
-                ..d!()
-            },
-        );
-    }
+            speaker,
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
 
-    output
-}
+        framebuffer.full_window();
 
-use std::fmt;
-use std::ops::{Add, Range, Rem, Sub};
+        let max_heading_y = print_choice_header(framebuffer, b"how to deal the cards");
 
-fn handle_scroll_movement<T>(
-    context: &mut UIContext,
-    input: Input,
-    Range { start, end }: Range<UIId>,
-    mod_offset: ModOffset<T>,
-) -> T
-where
-    T: fmt::Debug
-        + From<u8>
-        + Add<T, Output = T>
-        + Sub<T, Output = T>
-        + Rem<T, Output = T>
-        + Ord
-        + Copy,
-{
-    let mut output = mod_offset.current;
+        let sizes_y = max_heading_y + SPRITE_SIZE + FONT_SIZE / 2;
+        let size_w = SPRITE_SIZE * 3 + SPRITE_SIZE / 4;
+        let size_gap = (NINE_SLICE_MAX_INTERIOR_SIZE - size_w * PLAYER_ID_COUNT as u8)
+            / (PLAYER_ID_COUNT as u8 - 1);
 
-    let column_count = mod_offset.offset;
+        for player in all_player_ids() {
+            let x = SPRITE_SIZE + (size_w + size_gap) * player;
 
-    let mut unoffset = context.hot - start;
-    let visible_rows = end - start;
-    if visible_rows == 0 {
-        invariant_violation!(
-            {
-                return output;
-            },
-            "`visible_rows == 0`"
-        );
-    }
+            let name = player_name(self.seats, player);
+            let (name_x, _) =
+                center_line_in_rect(name.len() as u8, ((x, sizes_y), (size_w, FONT_SIZE)));
+            framebuffer.print(name.as_bytes(), name_x, sizes_y, 6);
 
-    invariant_assert!(column_count != 0);
-    invariant_assert_eq!(mod_offset.modulus % column_count.into(), 0u8.into());
+            let size = &mut self.deal.hand_sizes[player as usize];
 
-    if input.pressed_this_frame(Button::UP) {
-        if unoffset < column_count {
-            output = previous_mod(mod_offset);
-        } else {
-            unoffset -= column_count;
-        }
-    } else if input.pressed_this_frame(Button::DOWN) {
-        if unoffset / column_count >= (visible_rows / column_count) - 1 {
-            output = next_mod(mod_offset);
-        } else {
-            let unoffset_mod: ModOffset<UIId> = ModOffset {
-                modulus: visible_rows,
-                current: unoffset,
-                offset: mod_offset.offset,
+            let spec = ButtonSpec {
+                x,
+                y: sizes_y + FONT_SIZE,
+                w: size_w,
+                h: SPRITE_SIZE * 2,
+                id: player + 1,
+                text: format!("< {} >", size),
             };
-            unoffset = next_mod(unoffset_mod);
+
+            do_stepper(
+                framebuffer,
+                context,
+                input,
+                speaker,
+                &spec,
+                size,
+                (in_game::Deal::MIN_HAND_SIZE, in_game::Deal::MAX_HAND_SIZE),
+            );
         }
-    }
 
-    context.set_next_hot(unoffset + start);
+        {
+            let spec = CheckboxSpec {
+                x: SPRITE_SIZE,
+                y: sizes_y + FONT_SIZE + SPRITE_SIZE * 3,
+                id: DEAL_FLIP_ID,
+                checked: self.deal.flip_starting_card,
+                text: "turn over a starting card".to_owned(),
+            };
 
-    output
-}
+            if do_checkbox(framebuffer, context, input, speaker, &spec) {
+                self.deal.flip_starting_card = !self.deal.flip_starting_card;
+            }
+        }
 
-fn heading_y(i: i8) -> u8 {
-    (SPRITE_SIZE as i8 * 2 + FONT_SIZE as i8 * i) as u8
-}
+        {
+            let spec = CheckboxSpec {
+                x: SPRITE_SIZE,
+                y: sizes_y + FONT_SIZE + SPRITE_SIZE * 4 + SPRITE_SIZE / 2,
+                id: DEAL_ROTATE_ID,
+                checked: self.deal.rotate_dealer,
+                text: "rotate the dealer".to_owned(),
+            };
 
-fn do_scrolling_card_checkbox(
-    (
-        framebuffer,
-        context,
-        speaker,
-        scroll_card,
-        card_flags,
-    ) : (
-        &mut Framebuffer,
-        &mut UIContext,
-        &mut Speaker,
-        &mut Card,
-        &mut CardFlags,
-    ),
-    input: Input,
-    first_checkbox_id: UIId,
-    max_heading_y: u8,
-) {
-    const SCROLL_ROWS_COUNT: u8 = 10;
-    const SCROLL_COLS_COUNT: u8 = 2;
+            if do_checkbox(framebuffer, context, input, speaker, &spec) {
+                self.deal.rotate_dealer = !self.deal.rotate_dealer;
+            }
+        }
 
-    invariant_assert_eq!(
-        (DECK_SIZE / SCROLL_COLS_COUNT) * SCROLL_COLS_COUNT,
-        DECK_SIZE,
-    );
-    let current_scroll_card = nth_next_card(*scroll_card, 0);
+        let composition = &mut self.deal.composition;
 
-    for y in 0..SCROLL_ROWS_COUNT {
-        for x in 0..SCROLL_COLS_COUNT {
-            let i = x + y * SCROLL_COLS_COUNT;
-            let id = i as UIId + first_checkbox_id;
+        {
+            let decks = &mut composition.decks;
+
+            let spec = ButtonSpec {
+                x: SPRITE_SIZE,
+                y: sizes_y + FONT_SIZE + SPRITE_SIZE * 6,
+                w: SPRITE_SIZE * 6,
+                h: SPRITE_SIZE * 2,
+                id: DEAL_DECKS_ID,
+                text: format!("< {} deck{} >", decks, if *decks == 1 { "" } else { "s" }),
+            };
 
-            let card = nth_next_card(current_scroll_card, i);
-            let text = get_suit_rank_pair(card);
+            do_stepper(
+                framebuffer,
+                context,
+                input,
+                speaker,
+                &spec,
+                decks,
+                (1, DeckComposition::MAX_DECKS),
+            );
+        }
 
+        {
             let spec = CheckboxSpec {
-                x: SPRITE_SIZE
-                    + (SPRITE_SIZE * 2 + RANK_SUIT_PAIR_WITH_IN_CHARS * FONT_ADVANCE) * x,
-                y: max_heading_y + SPRITE_SIZE * (y + 1) as u8 + (SPRITE_SIZE as u8 / 2),
-                id,
-                text,
-                checked: card_flags.has_card(card),
+                x: SPRITE_SIZE,
+                y: sizes_y + FONT_SIZE + SPRITE_SIZE * 8 + SPRITE_SIZE / 2,
+                id: DEAL_JOKERS_ID,
+                checked: composition.jokers,
+                text: "jokers".to_owned(),
             };
 
             if do_checkbox(framebuffer, context, input, speaker, &spec) {
-                card_flags.toggle_card(card);
+                composition.jokers = !composition.jokers;
             }
         }
-    }
 
-    if context.hot < first_checkbox_id as _ {
-        if context.hot == 0 {
+        {
+            let w = SPRITE_SIZE * 5;
+            let h = SPRITE_SIZE * 3;
+            let spec = ButtonSpec {
+                x: SCREEN_WIDTH - (w + SPRITE_SIZE),
+                y: SCREEN_HEIGHT - (h + SPRITE_SIZE),
+                w,
+                h,
+                id: DEAL_DONE_ID,
+                text: "done".to_owned(),
+            };
+
+            if do_button(framebuffer, context, input, speaker, &spec) {
+                output = Step::Done(self.deal);
+            }
+        }
+
+        if context.hot == 0 || context.hot > DEAL_DONE_ID {
             context.set_next_hot(1);
         } else if input.pressed_this_frame(Button::UP) {
-            let next = dice_mod(context.hot - 1, first_checkbox_id - 1);
+            let next = dice_mod(context.hot - 1, DEAL_DONE_ID);
             context.set_next_hot(next);
         } else if input.pressed_this_frame(Button::DOWN) {
-            let next = dice_mod(context.hot + 1, first_checkbox_id - 1);
+            let next = dice_mod(context.hot + 1, DEAL_DONE_ID);
             context.set_next_hot(next);
-        } else if input.pressed_this_frame(Button::RIGHT) {
-            if context.hot == 1 {
-                context.set_next_hot(first_checkbox_id);
-            } else {
-                context.set_next_hot(first_checkbox_id + 3 * SCROLL_COLS_COUNT);
-            }
-        } else if input.pressed_this_frame(Button::LEFT) {
-            if context.hot == 1 {
-                context.set_next_hot(first_checkbox_id + 1);
-            } else {
-                context.set_next_hot(first_checkbox_id + 3 * SCROLL_COLS_COUNT + 1);
-            }
         }
-    } else if input.pressed_this_frame(Button::LEFT) {
-        if context.hot & 1 == first_checkbox_id & 1 {
-            if context.hot > first_checkbox_id + 3 * SCROLL_COLS_COUNT {
-                context.set_next_hot(first_checkbox_id - 1);
-            } else {
-                context.set_next_hot(first_checkbox_id - 2);
+
+        if let Step::Pending = output {
+            if input.pressed_this_frame(Button::B) {
+                output = Step::Back;
             }
-        } else {
-            let next = context.hot - 1;
-            context.set_next_hot(next);
         }
-    } else if input.pressed_this_frame(Button::RIGHT) {
-        context.set_next_hot(if context.hot & 1 == first_checkbox_id & 1 {
-            context.hot + 1
-        } else if context.hot > first_checkbox_id + 3 * SCROLL_COLS_COUNT {
-            first_checkbox_id - 1
-        } else {
-            first_checkbox_id - 2
-        });
-    } else {
-        *scroll_card = handle_scroll_movement(
+
+        output
+    }
+}
+
+pub fn choose_deal(state: &mut GameState) -> Option<in_game::Deal> {
+    match choose(state, |state| DealRequest {
+        deal: state.rules.deal,
+        seats: state.in_game.human_seats,
+    }) {
+        Step::Done(deal) => Some(deal),
+        Step::Pending => None,
+        Step::Back | Step::Cancel => {
+            state.pop_status();
+            None
+        }
+    }
+}
+
+/// What the player chose to do with a pending rule change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Confirmation {
+    Yes,
+    No,
+    /// Simulate some rounds to see what difference the change would make.
+    Preview,
+}
+
+pub fn choose_to_confirm_rule(state: &mut GameState) -> Option<Confirmation> {
+    match choose(state, |state| ConfirmRequest {
+        lines: confirm_rule_lines(state),
+        top: 0,
+        can_preview: state.rule_preview.is_none(),
+    }) {
+        Step::Done(confirmation) => Some(confirmation),
+        Step::Pending => None,
+        Step::Back | Step::Cancel => Some(Confirmation::No),
+    }
+}
+
+fn confirm_rule_lines(state: &GameState) -> Vec<String> {
+    let mut lines = match state.pending_rule {
+        Some(ref pending) => state
+            .rules
+            .describe_pending(pending, state.in_game.human_seats),
+        None => Vec::new(),
+    };
+
+    if let Some(ref preview) = state.rule_preview {
+        lines.extend(preview.describe());
+    }
+
+    lines
+}
+
+/// Updates the rule confirmation screen, if it is up, with the latest preview progress.
+pub fn refresh_rule_confirmation(state: &mut GameState) {
+    let lines = confirm_rule_lines(state);
+    let preview_done = state.rule_preview.as_ref().map(|p| p.is_done());
+
+    if let Some(request) = request_mut::<ConfirmRequest>(&mut state.choice) {
+        request.lines = lines;
+        request.can_preview = preview_done.is_none();
+        if preview_done == Some(true) {
+            // Scroll to the results, which are at the bottom.
+            request.top = usize::MAX;
+        }
+    }
+}
+
+/// Shows a summary of a rule change and asks whether to make it.
+#[derive(Clone, Debug)]
+pub struct ConfirmRequest {
+    pub lines: Vec<String>,
+    pub top: usize,
+    /// Whether to show the button that starts a preview.
+    pub can_preview: bool,
+}
+
+impl Request for ConfirmRequest {
+    type Output = Confirmation;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<Confirmation> {
+        let Frame {
+            framebuffer,
             context,
             input,
-            first_checkbox_id..first_checkbox_id + (SCROLL_ROWS_COUNT * SCROLL_COLS_COUNT),
-            ModOffset {
-                modulus: DECK_SIZE,
-                current: *scroll_card,
-                offset: SCROLL_COLS_COUNT,
-            },
+            speaker,
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
+
+        framebuffer.full_window();
+
+        print_choice_header(framebuffer, b"confirm these rule changes?");
+
+        let w = SPRITE_SIZE * 4;
+        let h = SPRITE_SIZE * 2;
+        let y = SCREEN_HEIGHT - (h + SPRITE_SIZE);
+
+        do_scrolling_paragraphs(
+            framebuffer,
+            &self.lines,
+            &mut self.top,
+            (heading_y(1), y - (FONT_SIZE + SPRITE_SIZE / 2)),
+            b"up and down scroll.",
+            input,
+            speaker,
         );
-    }
-}
 
-#[inline]
-pub fn do_can_play_graph_choice(
-    framebuffer: &mut Framebuffer,
-    state: &mut GameState,
-    input: Input,
-    speaker: &mut Speaker,
-) {
-    if let Choice::OfCanPlayGraph(ref mut choice_state) = state.choice {
-        let mut cancel = CancelRuleChoice::No;
+        let spec1 = ButtonSpec {
+            x: SPRITE_SIZE,
+            y,
+            w,
+            h,
+            id: 1,
+            text: "yes".to_owned(),
+        };
 
-        match choice_state.layer {
-            can_play::Layer::Card => {
-                cancel = do_card_sub_choice(
-                    framebuffer,
-                    &mut state.context,
-                    input,
-                    speaker,
-                    choice_state,
-                );
+        if do_button(framebuffer, context, input, speaker, &spec1) {
+            output = Step::Done(Confirmation::Yes);
+        }
 
-                match choice_state.layer {
-                    can_play::Layer::Edges => {
-                        let can_play_graph = &state.rules.can_play_graph;
-
-                        choice_state.edges = choice_state
-                            .changes
-                            .iter()
-                            .rev()
-                            .find(|c| c.card() == choice_state.card)
-                            .map(|c| c.edges())
-                            .unwrap_or_else(|| can_play_graph.get_edges(choice_state.card));
-                        choice_state.reset_edges = choice_state.edges;
-                    }
-                    can_play::Layer::Done => {
-                        state.choice =
-                            Choice::Already(Chosen::CanPlayGraph(choice_state.changes.clone()));
-                    }
-                    can_play::Layer::Card => {}
-                }
+        let spec2 = ButtonSpec {
+            x: SCREEN_WIDTH - (w + SPRITE_SIZE),
+            y,
+            w,
+            h,
+            id: 2,
+            text: "no".to_owned(),
+        };
+
+        if do_button(framebuffer, context, input, speaker, &spec2) {
+            output = Step::Done(Confirmation::No);
+        }
+
+        // The buttons in the order they appear, left to right.
+        let order: &[UIId] = if self.can_preview {
+            let preview_w = SPRITE_SIZE * 5;
+            let spec3 = ButtonSpec {
+                x: center_rect_in_rect((preview_w, h), ((0, y), (SCREEN_WIDTH, h))).0,
+                y,
+                w: preview_w,
+                h,
+                id: 3,
+                text: "preview".to_owned(),
+            };
+
+            if do_button(framebuffer, context, input, speaker, &spec3) {
+                output = Step::Done(Confirmation::Preview);
             }
-            can_play::Layer::Edges => {
-                let text = bytes_concat!(
-                    b"choose the cards the ",
-                    get_card_string(choice_state.card).as_bytes(),
-                    b" can be played on.",
-                );
 
-                cancel = do_card_flags_sub_choice(
-                    framebuffer,
-                    &mut state.context,
-                    input,
-                    speaker,
-                    choice_state,
-                    text,
-                );
+            &[1, 3, 2]
+        } else {
+            &[1, 2]
+        };
 
-                match choice_state.layer {
-                    can_play::Layer::Done => {}
-                    can_play::Layer::Edges => {}
-                    can_play::Layer::Card => {
-                        choice_state
-                            .changes
-                            .push(can_play::Change::new(choice_state.edges, choice_state.card));
-                    }
+        match order.iter().position(|&id| id == context.hot) {
+            None => context.set_next_hot(1),
+            Some(i) => {
+                if input.pressed_this_frame(Button::LEFT) {
+                    context.set_next_hot(order[(i + order.len() - 1) % order.len()]);
+                } else if input.pressed_this_frame(Button::RIGHT) {
+                    context.set_next_hot(order[(i + 1) % order.len()]);
                 }
             }
-            can_play::Layer::Done => {
-                framebuffer.center_half_window();
-            }
         }
 
-        if let CancelRuleChoice::Yes = cancel {
-            cancel_rule_selection!(state);
+        if let Step::Pending = output {
+            if input.pressed_this_frame(Button::B) {
+                output = Step::Back;
+            }
         }
-    } else {
-        invariant_violation!(
-            { state.choice = Choice::NoChoice },
-            "`do_can_play_graph_choice` was called with the wrong choice type!"
-        )
+
+        output
     }
 }
 
-pub fn choose_rule(state: &mut GameState) -> Option<Status> {
-    match state.choice {
-        Choice::NoChoice => {
-            state.choice = Choice::OfStatus;
-            None
-        }
-        Choice::Already(Chosen::Status(status)) => {
-            state.choice = Choice::NoChoice;
-            Some(status)
+/// Asks which type of rule to change.
+#[derive(Clone, Debug)]
+pub struct StatusRequest {
+    /// How many rule changes the player can still make, including this one.
+    pub changes_left: u8,
+    /// Which of `RULE_TYPES` are offered. Indexed the same way.
+    pub enabled: [bool; RULE_TYPES.len()],
+    /// The name of the player making the change, if it needs saying because several humans
+    /// are playing.
+    pub chooser: Option<String>,
+}
+
+impl StatusRequest {
+    fn offered(&self) -> Vec<Status> {
+        let offered: Vec<Status> = RULE_TYPES
+            .iter()
+            .zip(self.enabled.iter())
+            .filter(|(_, &enabled)| enabled)
+            .map(|(&status, _)| status)
+            .collect();
+
+        if offered.is_empty() {
+            RULE_TYPES.to_vec()
+        } else {
+            offered
         }
-        _ => None,
     }
 }
 
-#[inline]
-pub fn do_card_flags_choice(
-    framebuffer: &mut Framebuffer,
-    state: &mut GameState,
-    input: Input,
-    speaker: &mut Speaker,
-) {
-    if let Choice::OfCardFlags(ref mut card_flags_state) = state.choice {
-        let cancel = do_card_flags_sub_choice(
+impl Request for StatusRequest {
+    type Output = Status;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<Status> {
+        let Frame {
             framebuffer,
-            &mut state.context,
+            context,
             input,
             speaker,
-            card_flags_state,
-            b"select which cards are wild",
-        );
+        } = frame;
+        let input = *input;
+        let mut output = Step::Pending;
 
-        if let CancelRuleChoice::Yes = cancel {
-            cancel_rule_selection!(state);
-        } else if let Some(c) = card_flags_state.get_chosen() {
-            state.choice = Choice::Already(Chosen::CardFlags(c));
-        }
-    } else {
-        invariant_violation!(
-            { state.choice = Choice::NoChoice },
-            "`do_card_flags_choice` was called with the wrong choice type!"
-        )
-    }
-}
+        framebuffer.full_window();
+        {
+            let text = match self.chooser {
+                Some(ref name) => format!("{}: pick a rule type", name),
+                None => "choose a type of rule".to_owned(),
+            };
+            let text = text.as_bytes();
+
+            let (x, _) = center_line_in_rect(
+                text.len() as u8,
+                (
+                    (SPRITE_SIZE, SPRITE_SIZE),
+                    (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+                ),
+            );
 
-pub fn choose_wild_flags(state: &mut GameState) -> Option<CardFlags> {
-    match state.choice {
-        Choice::NoChoice => {
-            state.choice = Choice::OfCardFlags(CardFlagsChoiceState::new(state.rules.wild));
-            None
+            framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
         }
-        Choice::Already(Chosen::CardFlags(flags)) => {
-            state.choice = Choice::NoChoice;
-            Some(flags)
-        }
-        _ => None,
-    }
-}
 
-#[inline]
-pub fn do_status_choice(
-    framebuffer: &mut Framebuffer,
-    state: &mut GameState,
-    input: Input,
-    speaker: &mut Speaker,
-) {
-    framebuffer.full_window();
-    {
-        let text = b"choose a type of rule";
+        let w = NINE_SLICE_MAX_INTERIOR_SIZE;
+        let h = SPRITE_SIZE * 2 + SPRITE_SIZE / 2;
+        let x = SPRITE_SIZE;
+        let top = SPRITE_SIZE * 3;
+        let offered = self.offered();
 
-        let (x, _) = center_line_in_rect(
-            text.len() as u8,
-            (
-                (SPRITE_SIZE, SPRITE_SIZE),
-                (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
-            ),
-        );
+        for (i, status) in offered.iter().cloned().enumerate() {
+            let y = top + h * i as u8;
+            let i = (i + 1) as u8;
 
-        framebuffer.print(text, x, SPRITE_SIZE * 2, WHITE_INDEX);
-    }
+            let text = get_status_text(status).to_string();
 
-    let w = NINE_SLICE_MAX_INTERIOR_SIZE;
-    let h = SPRITE_SIZE * 3;
-    let x = SPRITE_SIZE;
+            let spec = ButtonSpec {
+                x,
+                y,
+                w,
+                h,
+                id: i,
+                text,
+            };
 
-    for (i, status) in RULE_TYPES.iter().cloned().enumerate() {
-        let i = (i + 1) as u8;
+            if do_button(framebuffer, context, input, speaker, &spec) {
+                output = Step::Done(status);
+            }
+        }
 
-        let text = get_status_text(status).to_string();
+        {
+            let text = format!(
+                "{} rule change{} left",
+                self.changes_left,
+                if self.changes_left == 1 { "" } else { "s" }
+            );
 
-        let spec = ButtonSpec {
-            x,
-            y: h * i,
-            w,
-            h,
-            id: i,
-            text,
-        };
+            let (x, _) = center_line_in_rect(
+                text.len() as u8,
+                (
+                    (SPRITE_SIZE, SPRITE_SIZE),
+                    (NINE_SLICE_MAX_INTERIOR_SIZE, NINE_SLICE_MAX_INTERIOR_SIZE),
+                ),
+            );
 
-        if do_button(framebuffer, &mut state.context, input, speaker, &spec) {
-            state.choice = Choice::Already(Chosen::Status(status));
+            framebuffer.print(
+                text.as_bytes(),
+                x,
+                top + h * offered.len() as u8 + SPRITE_SIZE / 2,
+                6,
+            );
         }
-    }
 
-    #[allow(non_snake_case)]
-    let MAX_ID = RULE_TYPES.len() as UIId;
+        #[allow(non_snake_case)]
+        let MAX_ID = offered.len() as UIId;
 
-    if state.context.hot == 0 || state.context.hot > MAX_ID {
-        state.context.set_next_hot(1);
-    } else if input.pressed_this_frame(Button::UP) {
-        let next = dice_mod(state.context.hot - 1, MAX_ID);
-        state.context.set_next_hot(next);
-    } else if input.pressed_this_frame(Button::DOWN) {
-        let next = dice_mod(state.context.hot + 1, MAX_ID);
-        state.context.set_next_hot(next);
+        if context.hot == 0 || context.hot > MAX_ID {
+            context.set_next_hot(1);
+        } else if input.pressed_this_frame(Button::UP) {
+            let next = dice_mod(context.hot - 1, MAX_ID);
+            context.set_next_hot(next);
+        } else if input.pressed_this_frame(Button::DOWN) {
+            let next = dice_mod(context.hot + 1, MAX_ID);
+            context.set_next_hot(next);
+        }
+
+        output
     }
 }
 
@@ -1301,10 +3359,7 @@ pub fn do_choices(
     match state.choice {
         Choice::OfInGameChanges(_) => do_in_game_changes_choice(framebuffer, state, input, speaker),
         Choice::OfCanPlayGraph(_) => do_can_play_graph_choice(framebuffer, state, input, speaker),
-        Choice::OfCardFlags(_) => do_card_flags_choice(framebuffer, state, input, speaker),
-        Choice::OfStatus => do_status_choice(framebuffer, state, input, speaker),
-        Choice::OfSuit => do_suit_choice(framebuffer, state, input, speaker),
-        Choice::OfBool => do_bool_choice(framebuffer, state, input, speaker),
+        Choice::OfRequest(_) => do_request(framebuffer, state, input, speaker),
         Choice::OfUnit => do_unit_choice(
             framebuffer,
             state,
@@ -1321,3 +3376,22 @@ pub fn do_choices(
         Choice::Already(_) => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_a_quick_selection_deselects_when_pressed_again() {
+        let mut flags = CardFlags::new(0);
+        flags.set_card(0);
+
+        let primes = QuickSelection::Set(CardSet::Primes);
+
+        primes.apply(&mut flags);
+        assert_eq!(flags.size(), 1 + 6 * SUIT_COUNT as u32);
+
+        primes.apply(&mut flags);
+        assert_eq!(flags.cards(), vec![0]);
+    }
+}
@@ -1,2 +1,4 @@
 mod choices;
 pub use self::choices::*;
+
+pub mod request;
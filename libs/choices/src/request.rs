@@ -0,0 +1,323 @@
+//! A composable way to present choices to the player.
+//!
+//! A `Request` knows how to draw itself and report what was chosen, and requests can be
+//! chained together with `then`, transformed with `map`, and branched between with
+//! `Either`. A whole tree of requests is stored in `GameState::choice` as a single value,
+//! so adding a new screen does not require new `Choice`/`Chosen` variants. See
+//! `design/presenting_the_player_with_choices.md` for the motivation.
+use common::*;
+use game_state::{Choice, ErasedRequest, GameState};
+use platform_types::{Input, Speaker};
+use std::any::Any;
+use std::fmt;
+
+/// The result of running a request for a frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step<T> {
+    /// The player has not chosen yet.
+    Pending,
+    Done(T),
+    /// The player wants to go back to the previous choice, if any.
+    Back,
+    /// The player wants to abandon the whole choice.
+    Cancel,
+}
+
+/// What a request has access to while it is being presented.
+pub struct Frame<'a> {
+    pub framebuffer: &'a mut Framebuffer,
+    pub context: &'a mut UIContext,
+    pub input: Input,
+    pub speaker: &'a mut Speaker,
+}
+
+pub trait Request: Clone + fmt::Debug + 'static {
+    type Output: Clone + fmt::Debug + 'static;
+
+    /// Draws the request and returns what, if anything, the player chose this frame.
+    fn frame(&mut self, frame: &mut Frame) -> Step<Self::Output>;
+
+    /// Presents `self` and then the request returned by `next`, which can depend on what
+    /// was chosen. If the second request goes back, `self` is presented again.
+    fn then<B: Request>(self, next: fn(&Self::Output) -> B) -> Then<Self, B> {
+        Then {
+            first: self,
+            next,
+            second: None,
+        }
+    }
+
+    fn map<T: Clone + fmt::Debug + 'static>(self, f: fn(Self::Output) -> T) -> Map<Self, T> {
+        Map { request: self, f }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Then<A: Request, B: Request> {
+    first: A,
+    next: fn(&A::Output) -> B,
+    second: Option<(A::Output, B)>,
+}
+
+impl<A: Request, B: Request> Request for Then<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<Self::Output> {
+        match self.second {
+            None => {
+                match self.first.frame(frame) {
+                    Step::Done(a) => {
+                        let second = (self.next)(&a);
+                        self.second = Some((a, second));
+                        // Start the next request with a fresh selection.
+                        frame.context.set_next_hot(0);
+                        Step::Pending
+                    }
+                    Step::Pending => Step::Pending,
+                    Step::Back => Step::Back,
+                    Step::Cancel => Step::Cancel,
+                }
+            }
+            Some((ref a, ref mut second)) => match second.frame(frame) {
+                Step::Done(b) => Step::Done((a.clone(), b)),
+                Step::Pending => Step::Pending,
+                Step::Back => {
+                    self.second = None;
+                    frame.context.set_next_hot(0);
+                    Step::Pending
+                }
+                Step::Cancel => Step::Cancel,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Map<R: Request, T> {
+    request: R,
+    f: fn(R::Output) -> T,
+}
+
+impl<R: Request, T: Clone + fmt::Debug + 'static> Request for Map<R, T> {
+    type Output = T;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<T> {
+        match self.request.frame(frame) {
+            Step::Done(output) => Step::Done((self.f)(output)),
+            Step::Pending => Step::Pending,
+            Step::Back => Step::Back,
+            Step::Cancel => Step::Cancel,
+        }
+    }
+}
+
+/// One of two requests with the same output, for branching on a previous choice.
+#[derive(Clone, Debug)]
+pub enum Either<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<L: Request, R: Request<Output = L::Output>> Request for Either<L, R> {
+    type Output = L::Output;
+
+    fn frame(&mut self, frame: &mut Frame) -> Step<Self::Output> {
+        match *self {
+            Either::Left(ref mut l) => l.frame(frame),
+            Either::Right(ref mut r) => r.frame(frame),
+        }
+    }
+}
+
+/// Stores a request along with its result, once there is one.
+#[derive(Clone, Debug)]
+struct Driver<R: Request> {
+    request: R,
+    result: Option<Step<R::Output>>,
+}
+
+impl<R: Request> ErasedRequest for Driver<R> {
+    fn frame(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        context: &mut UIContext,
+        input: Input,
+        speaker: &mut Speaker,
+    ) {
+        if self.result.is_some() {
+            return;
+        }
+
+        let mut frame = Frame {
+            framebuffer,
+            context,
+            input,
+            speaker,
+        };
+
+        match self.request.frame(&mut frame) {
+            Step::Pending => {}
+            step => self.result = Some(step),
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.result.is_some()
+    }
+
+    fn clone_box(&self) -> Box<dyn ErasedRequest> {
+        Box::new(self.clone())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Wraps `request` so it can be stored in `GameState::choice`, for instance to return to
+/// a partially made choice later. `choose` will then pick up where the request left off.
+pub fn to_choice<R: Request>(request: R) -> Choice {
+    Choice::OfRequest(Box::new(Driver {
+        request,
+        result: None,
+    }))
+}
+
+/// Returns the request stored in `choice` if it is an `R` that the player has not finished
+/// with, so what it shows can be updated while it is up.
+pub fn request_mut<R: Request>(choice: &mut Choice) -> Option<&mut R> {
+    match choice {
+        Choice::OfRequest(ref mut request) => request
+            .as_any_mut()
+            .downcast_mut::<Driver<R>>()
+            .filter(|driver| driver.result.is_none())
+            .map(|driver| &mut driver.request),
+        _ => None,
+    }
+}
+
+/// Starts presenting the request made by `make` if no choice is in progress, and returns
+/// the result once the player has finished with it. Like the other `choose_*` functions,
+/// this should be called every frame until it returns something other than `Pending`.
+pub fn choose<R: Request>(
+    state: &mut GameState,
+    make: impl FnOnce(&GameState) -> R,
+) -> Step<R::Output> {
+    match state.choice {
+        Choice::NoChoice => {
+            state.choice = to_choice(make(state));
+            Step::Pending
+        }
+        Choice::OfRequest(ref mut request) => {
+            let result = match request.as_any_mut().downcast_mut::<Driver<R>>() {
+                Some(driver) => driver.result.take(),
+                None => {
+                    // Some other request is in progress, so this one would never finish.
+                    invariant_violation!({}, "choose called with a different kind of request");
+                    state.choice = Choice::NoChoice;
+                    return Step::Pending;
+                }
+            };
+
+            match result {
+                Some(step) => {
+                    state.choice = Choice::NoChoice;
+                    step
+                }
+                None => Step::Pending,
+            }
+        }
+        _ => Step::Pending,
+    }
+}
+
+/// Presents the request stored in `state.choice`, if any.
+pub fn do_request(
+    framebuffer: &mut Framebuffer,
+    state: &mut GameState,
+    input: Input,
+    speaker: &mut Speaker,
+) {
+    if let Choice::OfRequest(ref mut request) = state.choice {
+        request.frame(framebuffer, &mut state.context, input, speaker);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the steps in order, one per frame, then stays pending.
+    #[derive(Clone, Debug)]
+    struct Scripted(Vec<Step<u8>>);
+
+    impl Request for Scripted {
+        type Output = u8;
+
+        fn frame(&mut self, _: &mut Frame) -> Step<u8> {
+            if self.0.is_empty() {
+                Step::Pending
+            } else {
+                self.0.remove(0)
+            }
+        }
+    }
+
+    fn run<R: Request>(request: &mut R, frames: usize) -> Vec<Step<R::Output>> {
+        let mut framebuffer = Framebuffer::new();
+        let mut context = UIContext::default();
+        let mut speaker = Speaker::default();
+
+        (0..frames)
+            .map(|_| {
+                request.frame(&mut Frame {
+                    framebuffer: &mut framebuffer,
+                    context: &mut context,
+                    input: Input::new(),
+                    speaker: &mut speaker,
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_then_goes_back_to_the_first_request_when_the_second_goes_back() {
+        let first = Scripted(vec![Step::Done(1), Step::Done(2)]);
+        let mut request = first
+            .then(|&a| {
+                Scripted(if a == 1 {
+                    vec![Step::Back]
+                } else {
+                    vec![Step::Done(a * 10)]
+                })
+            })
+            .map(|(a, b)| a + b);
+
+        assert_eq!(
+            run(&mut request, 4),
+            vec![Step::Pending, Step::Pending, Step::Pending, Step::Done(22)]
+        );
+    }
+
+    #[test]
+    fn test_cancel_from_the_second_request_cancels_the_whole_choice() {
+        let mut request =
+            Scripted(vec![Step::Done(1)]).then(|_| Scripted(vec![Step::Pending, Step::Cancel]));
+
+        assert_eq!(
+            run(&mut request, 3),
+            vec![Step::Pending, Step::Pending, Step::Cancel]
+        );
+    }
+
+    #[test]
+    fn test_choose_drops_a_different_kind_of_request_instead_of_waiting_on_it() {
+        let mut state = GameState::new([32; 16], d!());
+        state.choice = to_choice(Scripted(vec![Step::Done(1)]));
+
+        let step = choose(&mut state, |_| Scripted(vec![]).map(|a| a + 1));
+
+        assert_eq!(step, Step::Pending);
+        assert!(matches!(state.choice, Choice::NoChoice));
+    }
+}
@@ -39,6 +39,12 @@ impl CardAnimation {
         self.card.x == self.x && self.card.y == self.y
     }
 
+    /// Moves the card straight to where it is going.
+    pub fn skip_to_target(&mut self) {
+        self.card.x = self.x;
+        self.card.y = self.y;
+    }
+
     pub fn approach_target(&mut self) {
         let (d_x, d_y) = self.get_delta();
 
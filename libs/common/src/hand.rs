@@ -53,11 +53,13 @@ pub struct Hand {
     pub spread: Spread,
 }
 
-pub fn fresh_deck() -> Vec<Card> {
-    let mut deck = Vec::with_capacity(DECK_SIZE as usize);
+pub fn fresh_deck(composition: DeckComposition) -> Vec<Card> {
+    let mut deck = Vec::with_capacity(composition.size() as usize);
 
-    for i in 0..DECK_SIZE {
-        deck.push(i);
+    for deck_index in 0..composition.decks {
+        for face in 0..composition.face_count() {
+            deck.push(with_deck_index(face, deck_index));
+        }
     }
 
     deck
@@ -71,8 +73,8 @@ impl Hand {
         }
     }
 
-    pub fn new_shuffled_deck(rng: &mut Xs) -> Self {
-        let mut deck = fresh_deck();
+    pub fn new_shuffled_deck(rng: &mut Xs, composition: DeckComposition) -> Self {
+        let mut deck = fresh_deck(composition);
 
         xs::shuffle(rng, &mut deck);
 
@@ -164,7 +166,7 @@ impl Hand {
 
     pub fn most_common_suits(&self) -> [Option<Suit>; 4] {
         let mut counts: [(u8, u8); 4] = [(0, 0), (1, 0), (2, 0), (3, 0)];
-        for suit in self.cards.iter().cloned().map(get_suit) {
+        for suit in self.cards.iter().filter(|&&c| !is_joker(c)).cloned().map(get_suit) {
             let (suit, count) = counts[suit as usize];
             counts[suit as usize] = (suit, count + 1);
         }
@@ -183,7 +183,7 @@ impl Framebuffer {
             y,
         );
 
-        let (colour, suit_char) = get_suit_colour_and_char(get_suit(card));
+        let (colour, suit_char) = get_card_colour_and_suit_char(card);
 
         let rank_char = get_rank_char(card);
 
@@ -86,6 +86,7 @@ pub trait CardSubChoice: BorrowMut<Card> {
     fn mark_done(&mut self);
     fn reset(&mut self);
     fn next_layer(&mut self);
+    fn next_set_layer(&mut self);
     fn get_status_lines(&self, card: Card) -> StatusLines;
 }
 
@@ -97,6 +98,29 @@ pub trait CardFlagsSubChoice: BorrowPairMut<Card, CardFlags> {
     fn get_status_lines(&self) -> StatusLines {
         d!()
     }
+    fn quick_select_mut(&mut self) -> &mut QuickSelectState;
+}
+
+use inner_common::Rank;
+/// The state of the menu of one-press selections for the card flags menu.
+#[derive(Clone, Copy, Debug)]
+pub struct QuickSelectState {
+    pub open: bool,
+    pub scroll: usize,
+    pub rank: Rank,
+    /// The n in "multiples of n".
+    pub multiple: u8,
+}
+
+impl Default for QuickSelectState {
+    fn default() -> Self {
+        QuickSelectState {
+            open: false,
+            scroll: 0,
+            rank: 0,
+            multiple: 2,
+        }
+    }
 }
 
 pub trait BorrowPair<Borrowed1: ?Sized, Borrowed2: ?Sized> {
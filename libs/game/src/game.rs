@@ -1,38 +1,106 @@
 use choices::{
-    choose_can_play_graph, choose_in_game_changes, choose_play_again, choose_rule,
-    choose_wild_flags, do_choices, show_rules_screen,
+    choose_can_play_graph, choose_deal, choose_in_game_changes, choose_play_again, choose_rule,
+    choose_to_confirm_rule, choose_wild_flags, do_choices, refresh_rule_confirmation,
+    request::to_choice, reveal_secret_rules, show_card_inspector, show_handoff_screen,
+    show_options_screen, show_rules_info_screen, show_rules_screen, show_setup_screen,
+    wild_flags_request, Confirmation, DealRequest,
 };
-use common::{GLOBAL_ERROR_LOGGER, GLOBAL_LOGGER, xs::Xs, *};
-use game_state::{in_game, GameState, LogHeading, Rules, Status};
-use platform_types::{Button, Input, Speaker, State, SFX};
-pub use platform_types::StateParams;
-use rule_changes::{
-    apply_can_play_graph_changes, apply_when_played_changes, apply_wild_change, reset,
+use common::{GLOBAL_ERROR_LOGGER, GLOBAL_LOGGER, *};
+use game_state::{
+    can_play, in_game, parse_seed_string,
+    weights::{Weights, WEIGHTS_KEY},
+    Choice, Config, GameMode, GameState, LogHeading, PendingRule, RulePreview, RulesInfo,
+    SetupPage, Status, Watch,
 };
+use platform_types::{Button, Input, Logger, Speaker, State, Storage, SFX};
+pub use platform_types::StateParams;
+use rule_changes::{apply_rule, reset, start};
+use simulation::{advance_preview, take_cpu_turn, PREVIEW_ROUNDS_PER_FRAME};
+
+mod remote;
+pub use self::remote::{host, RemoteState};
 
 pub struct BartogState {
     pub game_state: GameState,
     pub framebuffer: Framebuffer,
     pub input: Input,
     pub speaker: Speaker,
+    storage: Storage,
+    saved_config: Config,
+}
+
+const CONFIG_KEY: &str = "config";
+
+fn init_logging(logger: Logger, error_logger: Logger) {
+    unsafe {
+        GLOBAL_LOGGER = logger;
+        GLOBAL_ERROR_LOGGER = error_logger;
+    }
+}
+
+/// The settings last saved from the setup screen, along with the seed asked for at
+/// launch, if any.
+fn load_config(storage: Storage, requested_seed: Option<String>, today: u32) -> Config {
+    let mut config = storage
+        .and_then(|(load, _)| load(CONFIG_KEY))
+        .map(|text| Config::parse(&text))
+        .unwrap_or_default();
+    config.today = today;
+
+    // A seed asked for at launch is shown on the setup screen, but is only saved along
+    // with any other settings that get changed there.
+    if let Some(text) = requested_seed {
+        match parse_seed_string(&text) {
+            Some(number) => config.seed = Some(number),
+            None => log(&format!("could not read {:?} as a seed", text)),
+        }
+    }
+
+    config
+}
+
+/// The weights saved in storage for the cpu players to make rules with, if there are any
+/// that parse. Otherwise the bundled weights.
+fn load_weights(storage: Storage) -> Weights {
+    match storage.and_then(|(load, _)| load(WEIGHTS_KEY)) {
+        Some(text) => Weights::parse(&text).unwrap_or_else(|e| {
+            log(&format!("could not use the saved weights: {}", e));
+            Weights::bundled()
+        }),
+        None => Weights::bundled(),
+    }
 }
 
 impl BartogState {
-    pub fn new((seed, logger, error_logger): StateParams) -> Self {
+    pub fn new(
+        StateParams {
+            seed,
+            logger,
+            error_logger,
+            storage,
+            requested_seed,
+            today,
+        }: StateParams,
+    ) -> Self {
         let framebuffer = Framebuffer::new();
 
-        unsafe {
-            GLOBAL_LOGGER = logger;
-            GLOBAL_ERROR_LOGGER = error_logger;
-        }
+        init_logging(logger, error_logger);
 
         features::log!(seed);
 
+        let config = load_config(storage, requested_seed, today);
+
+        let mut game_state = GameState::new(seed, config);
+        game_state.setup = Some(SetupPage::Main);
+        game_state.weights = load_weights(storage);
+
         BartogState {
-            game_state: GameState::new(seed),
+            game_state,
             framebuffer,
             input: Input::new(),
             speaker: Speaker::default(),
+            storage,
+            saved_config: config,
         }
     }
 }
@@ -50,6 +118,17 @@ impl State for BartogState {
 
         self.input.previous_gamepad = self.input.gamepad;
 
+        let config = self
+            .game_state
+            .chosen_config
+            .unwrap_or(self.game_state.config);
+        if config != self.saved_config {
+            self.saved_config = config;
+            if let Some((_, save)) = self.storage {
+                save(CONFIG_KEY, &self.saved_config.to_text());
+            }
+        }
+
         (&self.framebuffer.commands, self.speaker.slice())
     }
 
@@ -68,8 +147,9 @@ impl State for BartogState {
     }
 }
 
-#[allow(dead_code)]
-enum Face {
+/// Which way up the cards in a hand are drawn.
+#[derive(Clone, Copy)]
+pub enum Face {
     Up,
     Down,
 }
@@ -215,8 +295,10 @@ fn draw_event_log(framebuffer: &mut Framebuffer, state: &GameState) {
 }
 
 fn move_cursor(state: &mut in_game::State, input: Input, speaker: &mut Speaker) -> bool {
+    let len = state.viewer_hand().map(|hand| hand.len()).unwrap_or(0);
+
     if input.pressed_this_frame(Button::RIGHT) {
-        if state.hand_index < state.hand.len().saturating_sub(1) {
+        if state.hand_index < len.saturating_sub(1) {
             state.hand_index = state.hand_index.saturating_add(1);
         }
         speaker.request_sfx(SFX::CardSlide);
@@ -230,220 +312,41 @@ fn move_cursor(state: &mut in_game::State, input: Input, speaker: &mut Speaker)
     }
 }
 
-fn can_play(state: &in_game::State, rules: &Rules, &card: &Card) -> bool {
-    if let Some(&top_of_discard) = state.discard.last() {
-        // TODO should a card that is wild allow a non-wild card of the same rank
-        // to be played on it?
-        rules.is_wild(card)
-            || if rules.is_wild(top_of_discard) {
-                state.top_wild_declared_as == Some(get_suit(card))
-                    //this can happen depending on the card movement rules
-                    || state.top_wild_declared_as == None
-            } else {
-                rules.can_play_graph.is_playable_on(card, top_of_discard)
-            }
-    } else {
-        true
-    }
-}
-
-//Since this uses rng, calling this in response to repeatable user input allows rng manipulation.
-fn cpu_would_play(
-    state: &mut in_game::State,
-    rng: &mut Xs,
-    rules: &Rules,
-    player_id: PlayerID,
-) -> Option<u8> {
-    let playable: Vec<(usize, Card)> = {
-        let hand = state.get_hand(player_id);
-        hand.iter()
-            .cloned()
-            .enumerate()
-            .filter(|(_, card)| can_play(state, rules, card))
-            .collect()
-    };
-
-    let sim_state = get_sim_state(state, rng, player_id);
-
-    let mut indexes_and_hand_deltas = Vec::with_capacity(playable.len());
-
-    for (i, card) in playable {
-        let delta = get_hand_delta(&sim_state, rules, rng, player_id, card);
-
-        indexes_and_hand_deltas.push((i, delta));
-    }
-
-    //if we make repeated decisions with equal weight, sometimes choose differently.
-    xs::shuffle(rng, &mut indexes_and_hand_deltas);
-
-    indexes_and_hand_deltas.sort_by_key(
-        |&(_, delta)| -delta, //highest negative delta to end
-    );
-
-    indexes_and_hand_deltas.pop().map(|(i, _)| i as u8)
-}
-
-fn get_hand_delta(
-    state: &in_game::State,
-    rules: &Rules,
-    rng: &mut Xs,
-    player_id: PlayerID,
-    card: Card,
-) -> i8 {
-    let original = state.get_hand(player_id).len() as i8;
-    let mut s: in_game::State = (*state).clone();
-
-    animations::play_to_discard_parts(&mut s, rules, rng, &mut None, card);
-
-    let new = s.get_hand(player_id).len() as i8;
-
-    new - original
-}
-
-fn get_sim_state(
-    state: &in_game::State,
-    rng: &mut Xs,
-    player_id: PlayerID,
-) -> in_game::State {
-    // We don't want the cpu to cheat, so don't let them see what is really on top of the deck.
-    // or which cards are in each player's hand either. So put all unknown cards in one pile
-    // then shuffle them randomly to each unknown zone, maintaining the original amounts.
-    let mut pile = Vec::with_capacity(DECK_SIZE as usize);
-
-    macro_rules! add_to_pile {
-        ($e:expr) => {
-            pile.extend($e.clone().drain());
-        };
-    }
-
-    add_to_pile!(state.deck);
-    add_to_pile!(state.discard);
-
-    match player_id {
-        0 => {
-            add_to_pile!(state.cpu_hands[1]);
-            add_to_pile!(state.cpu_hands[2]);
-            add_to_pile!(state.hand);
-        }
-        1 => {
-            add_to_pile!(state.cpu_hands[0]);
-            add_to_pile!(state.cpu_hands[2]);
-            add_to_pile!(state.hand);
-        }
-        2 => {
-            add_to_pile!(state.cpu_hands[1]);
-            add_to_pile!(state.cpu_hands[2]);
-            add_to_pile!(state.hand);
-        }
-        MAX_PLAYER_ID => {
-            add_to_pile!(state.cpu_hands[0]);
-            add_to_pile!(state.cpu_hands[1]);
-            add_to_pile!(state.cpu_hands[2]);
-        }
-        _ => invariant_violation!({}, "get_sim_state called with bad PlayerID"),
-    }
-
-    xs::shuffle(rng, &mut pile);
-
-    let mut output = in_game::State {
-        current_player: state.current_player,
-        top_wild_declared_as: state.top_wild_declared_as,
-        ..d!()
-    };
-
-    macro_rules! deal_into {
-        ($hand:ident) => {
-            let pile_drain = pile.drain(..state.$hand.len() as usize);
-            output.$hand.fill(pile_drain);
-        };
-        ($hand:ident, [$index:expr]) => {
-            let pile_drain = pile.drain(..state.$hand[$index].len() as usize);
-            output.$hand[$index].fill(pile_drain);
-        };
-    }
-
-    deal_into!(deck);
-    deal_into!(discard);
-
-    match player_id {
-        0 => {
-            deal_into!(cpu_hands, [1]);
-            deal_into!(cpu_hands, [2]);
-            deal_into!(hand);
-        }
-        1 => {
-            deal_into!(cpu_hands, [0]);
-            deal_into!(cpu_hands, [2]);
-            deal_into!(hand);
-        }
-        2 => {
-            deal_into!(cpu_hands, [1]);
-            deal_into!(cpu_hands, [2]);
-            deal_into!(hand);
-        }
-        MAX_PLAYER_ID => {
-            deal_into!(cpu_hands, [0]);
-            deal_into!(cpu_hands, [1]);
-            deal_into!(cpu_hands, [2]);
-        }
-        _ => invariant_violation!({}, "get_sim_state called with bad PlayerID"),
-    }
-
-    output
-}
-
-fn incremented_current_player(state: &in_game::State) -> PlayerID {
-    if state.current_player >= MAX_PLAYER_ID {
-        0
-    } else {
-        state.current_player + 1
-    }
-}
-
 fn take_turn(game_state: &mut GameState, input: Input, speaker: &mut Speaker) {
-    let state = &mut game_state.in_game;
-
-    let rules = &game_state.rules;
-    let event_log = &mut game_state.event_log;
-    let rng = &mut game_state.rng;
-
     //Doing this here and assigning to `current_player` later means that to start on a given
     //player we need to set `current_player` to the previous player, but it means that
     //`current_player` is set to the same player only during the actual player's turn and for the
     //entire turn, rather than just until we get to the increment.
-    let next_player = incremented_current_player(state);
+    let next_player = game_state.in_game.incremented_current_player();
+    let seats = game_state.in_game.human_seats;
+
+    // A human's hand is only shown once the device has been passed to them.
+    if is_human_player(seats, next_player) && game_state.in_game.viewer != Some(next_player) {
+        game_state.in_game.set_viewer(None);
+        game_state.handoff = Some(next_player);
+        return;
+    }
+
+    // Whether the player whose turn it was played or drew a card, once their turn is over.
+    let mut acted = None;
     match next_player {
-        p if is_cpu_player(p) => {
-            state.current_player = next_player;
-            if let Some(index) = cpu_would_play(state, rng, rules, p) {
-                animations::add_discard_animation(state, index, event_log, rules);
-            } else {
-                animations::add_draw_animation(state, event_log, rng);
+        p if is_cpu_player(seats, p) => {
+            // Keep the last human's hand hidden from any others who are watching.
+            if seats.count() > 1 {
+                game_state.in_game.set_viewer(None);
             }
+
+            acted = Some(take_cpu_turn(game_state, p));
         }
-        PLAYER_ID => {
-            if move_cursor(state, input, speaker) {
+        p if is_human_player(seats, p) => {
+            if move_cursor(&mut game_state.in_game, input, speaker) {
                 //Already handled.
             } else if input.pressed_this_frame(Button::A) {
-                let index = state.hand_index;
-
-                let can_play_it = {
-                    state
-                        .hand
-                        .get(index)
-                        .map(|card| can_play(state, rules, card))
-                        .unwrap_or(false)
-                };
-
-                if can_play_it {
-                    state.current_player = next_player;
-                    animations::add_discard_animation(state, index, event_log, rules);
-                } else {
-                    //TODO good feedback. Tint the card red or shake it or something?
-                }
+                let index = game_state.in_game.hand_index;
+                acted = animations::play_from_hand(game_state, p, index);
+                //TODO good feedback when `acted` is `None`. Tint the card red or shake it or something?
             } else if input.pressed_this_frame(Button::B) {
-                state.current_player = next_player;
-                animations::add_draw_animation(state, event_log, rng);
+                acted = Some(animations::draw_on_turn(game_state, p));
             }
         }
         _id => {
@@ -451,15 +354,7 @@ fn take_turn(game_state: &mut GameState, input: Input, speaker: &mut Speaker) {
         }
     }
 
-    let winners: Vec<PlayerID> = all_player_ids()
-        .iter()
-        .filter(|&&player| state.get_hand(player).is_empty())
-        .cloned()
-        .collect();
-
-    if state.no_winners_yet() {
-        state.winners = winners;
-    }
+    animations::finish_turn(game_state, acted);
 }
 
 fn update(state: &mut GameState, input: Input, speaker: &mut Speaker) {
@@ -469,22 +364,46 @@ fn update(state: &mut GameState, input: Input, speaker: &mut Speaker) {
         Status::RuleSelectionCanPlay => update_can_play_graph(state),
         Status::RuleSelectionWild => update_wild(state),
         Status::RuleSelectionWhenPlayed => update_when_played(state),
+        Status::RuleSelectionDeal => update_deal(state),
+        Status::RuleSelectionConfirm => update_rule_confirmation(state),
     }
 }
 
+/// Asks the player to confirm `pending` before it is applied. If they do not, they are
+/// returned to `editor`, which should be the choice that made `pending`.
+fn confirm_rule(state: &mut GameState, pending: PendingRule, editor: Choice) {
+    state.pending_rule = Some(pending);
+    state.rule_preview = None;
+    state.choice = editor;
+    state.push_status(Status::RuleSelectionConfirm);
+}
+
 fn update_when_played(state: &mut GameState) {
     match choose_in_game_changes(state) {
-        in_game::ChoiceState {
-            card_set,
-            ref changes,
-            ..
-        } if !changes.is_empty() => {
-            apply_when_played_changes(state, card_set, changes.clone(), PLAYER_ID);
-            state.start_new_round();
-        }
-        _ => {
+        None => {
+            //wait until they choose
+        }
+        Some(mut choice_state) => {
+            let pending =
+                PendingRule::WhenPlayed(choice_state.card_set, choice_state.changes.clone());
+            choice_state.layer = in_game::Layer::Changes;
+            confirm_rule(state, pending, Choice::OfInGameChanges(choice_state));
+        }
+    }
+}
+
+fn update_deal(state: &mut GameState) {
+    match choose_deal(state) {
+        None => {
             //wait until they choose
         }
+        Some(deal) => {
+            let editor = to_choice(DealRequest {
+                deal,
+                seats: state.in_game.human_seats,
+            });
+            confirm_rule(state, PendingRule::Deal(deal), editor);
+        }
     }
 }
 
@@ -494,8 +413,9 @@ fn update_wild(state: &mut GameState) {
             //wait until they choose
         }
         Some(wild) => {
-            apply_wild_change(state, wild, PLAYER_ID);
-            state.start_new_round();
+            let composition = state.rules.deal.composition;
+            let editor = to_choice(wild_flags_request(state.rules.wild, wild, composition));
+            confirm_rule(state, PendingRule::Wild(wild), editor);
         }
     }
 }
@@ -506,8 +426,69 @@ fn update_can_play_graph(state: &mut GameState) {
             //wait until they choose
         }
         changes => {
-            apply_can_play_graph_changes(state, changes, PLAYER_ID);
-            state.start_new_round();
+            let editor = Choice::OfCanPlayGraph(can_play::ChoiceState {
+                changes: changes.clone(),
+                ..d!()
+            });
+            confirm_rule(state, PendingRule::CanPlay(changes), editor);
+        }
+    }
+}
+
+fn update_rule_confirmation(state: &mut GameState) {
+    if let Some(preview) = state.rule_preview.as_mut() {
+        if !preview.is_done() {
+            advance_preview(preview, PREVIEW_ROUNDS_PER_FRAME);
+            refresh_rule_confirmation(state);
+        }
+    }
+
+    match choose_to_confirm_rule(state) {
+        None => {
+            //wait until they choose
+        }
+        Some(Confirmation::Preview) => {
+            if let Some(ref rule) = state.pending_rule {
+                // Use a copy of the rng, so previewing does not change how the game goes.
+                let seed = xs::new_seed(&mut state.rngs.rules.clone());
+                state.rule_preview = Some(RulePreview::new(
+                    &state.rules,
+                    rule.clone(),
+                    seed,
+                    state.in_game.human_seats,
+                    state.config.cpu_count,
+                ));
+            }
+        }
+        Some(Confirmation::Yes) => {
+            match state.pending_rule.take() {
+                Some(rule) => {
+                    let player = state.rule_maker();
+                    apply_rule(state, rule, player);
+                }
+                None => {
+                    invariant_violation!(
+                        {
+                            state.pop_status();
+                            return;
+                        },
+                        "A rule was confirmed with no pending rule!"
+                    );
+                }
+            }
+
+            state.rule_budget_left = state.rule_budget_left.saturating_sub(1);
+            if state.rule_budget_left > 0 || state.next_rule_maker() {
+                state.navigation.clear();
+                state.status = Status::RuleSelection;
+            } else {
+                state.start_new_round();
+            }
+        }
+        Some(Confirmation::No) => {
+            state.pending_rule = None;
+            state.rule_preview = None;
+            state.pop_status();
         }
     }
 }
@@ -518,12 +499,14 @@ fn update_rule_selection(state: &mut GameState) {
             //wait until they choose
         }
         Some(status) => {
-            state.status = status;
+            state.push_status(status);
         }
     }
 }
 
-fn update_in_game(state: &mut GameState, input: Input, speaker: &mut Speaker) {
+/// Opens and closes the event log when start is pressed, and scrolls it while it is open.
+/// Returns whether it is open, in which case the input was meant for it.
+fn update_event_log(state: &mut GameState, input: Input) -> bool {
     match state.log_heading {
         LogHeading::Up => {
             state.log_height = state.log_height.saturating_sub(SPRITE_SIZE);
@@ -557,9 +540,52 @@ fn update_in_game(state: &mut GameState, input: Input, speaker: &mut Speaker) {
         } else if input.pressed_this_frame(Button::B) {
             state.event_log.jump_forward();
         }
+        true
+    } else {
+        false
+    }
+}
+
+/// Handles the controls for watching a game with no humans in it.
+fn update_watch(watch: &mut Watch, input: Input, speaker: &mut Speaker) {
+    if input.pressed_this_frame(Button::A) {
+        watch.paused = !watch.paused;
+        watch.step = false;
+    } else if input.pressed_this_frame(Button::B) {
+        watch.step = watch.paused;
+    } else if input.pressed_this_frame(Button::LEFT) {
+        watch.speed = watch.speed.saturating_sub(1);
+    } else if input.pressed_this_frame(Button::RIGHT) {
+        watch.speed = Watch::MAX_SPEED.min(watch.speed + 1);
+    } else if input.pressed_this_frame(Button::UP) {
+        watch.show_all = !watch.show_all;
+    } else {
+        return;
+    }
+
+    speaker.request_sfx(SFX::ButtonPress);
+}
+
+fn update_in_game(state: &mut GameState, input: Input, speaker: &mut Speaker) {
+    if update_event_log(state, input) {
+        //Already handled.
     } else if state.choice.is_idle() {
+        if state.in_game.human_seats.is_empty() {
+            update_watch(&mut state.watch, input, speaker);
+        } else {
+            let hand_is_empty = state
+                .in_game
+                .viewer_hand()
+                .is_none_or(|hand| hand.is_empty());
+            if input.pressed_this_frame(Button::UP) && !hand_is_empty {
+                state.show_card_inspector = true;
+                state.card_inspector_top = 0;
+                return;
+            }
+        }
+
         if state.animations_settled() {
-            if state.in_game.no_winners_yet() {
+            if !state.in_game.round_has_ended() && state.watch.next_turn_may_start() {
                 take_turn(state, input, speaker);
             }
         } else {
@@ -579,10 +605,33 @@ fn print_number_below_card(framebuffer: &mut Framebuffer, number: u8, x: u8, y:
     );
 }
 
+/// Prints a line of text across the middle of the screen, below the deck.
+fn print_status_line(framebuffer: &mut Framebuffer, text: &str) {
+    let (x, _) = center_line_in_rect(text.len() as u8, ((0, 0), (SCREEN_WIDTH, SCREEN_HEIGHT)));
+    framebuffer.print(
+        text.as_bytes(),
+        x,
+        DECK_Y + card::HEIGHT + FONT_SIZE * 2,
+        WHITE_INDEX,
+    );
+}
+
+fn print_watch_status(framebuffer: &mut Framebuffer, watch: &Watch) {
+    let speed = format!("speed: {}", watch.speed_label());
+    if watch.paused {
+        print_status_line(framebuffer, &format!("paused. {}", speed));
+    } else {
+        print_status_line(framebuffer, &speed);
+    }
+}
+
+/// Draws the cards, with everyone's hands but the viewer's drawn `others` side up.
 #[inline]
-pub fn render_in_game(framebuffer: &mut Framebuffer, state: &in_game::State) {
-    for hand in state.cpu_hands.iter() {
-        draw_hand(framebuffer, hand, Face::Down);
+pub fn render_in_game(framebuffer: &mut Framebuffer, state: &in_game::State, others: Face) {
+    for player in all_player_ids() {
+        if state.viewer != Some(player) {
+            draw_hand(framebuffer, state.get_hand(player), others);
+        }
     }
 
     let deck_len = state.deck.len();
@@ -615,7 +664,9 @@ pub fn render_in_game(framebuffer: &mut Framebuffer, state: &in_game::State) {
 
     print_number_below_card(framebuffer, state.discard.len(), DISCARD_X, DISCARD_Y);
 
-    draw_hand_with_cursor(framebuffer, &state.hand, state.hand_index as usize);
+    if let Some(hand) = state.viewer_hand() {
+        draw_hand_with_cursor(framebuffer, hand, state.hand_index as usize);
+    }
 
     for &CardAnimation {
         card,
@@ -647,19 +698,85 @@ pub fn update_and_render(
 
     framebuffer.clearTo(1 /* green */);
 
-    if state.show_rules || input.pressed_this_frame(Button::SELECT) {
-        state.show_rules = true;
+    if state.setup.is_some() {
+        if show_setup_screen(framebuffer, state, input, speaker) {
+            start(state);
+        }
+        return;
+    }
+
+    // Pressing select cycles from the game to the controls, through the rules info pages,
+    // and back.
+    if input.pressed_this_frame(Button::SELECT) {
+        state.rules_info_top = 0;
+        state.show_options = false;
+
+        match state.rules_info {
+            Some(RulesInfo::Reference) => state.rules_info = Some(RulesInfo::Timeline),
+            Some(RulesInfo::Timeline) => state.rules_info = None,
+            None if state.show_rules => {
+                state.show_rules = false;
+                state.rules_info = Some(RulesInfo::Reference);
+            }
+            None => state.show_rules = true,
+        }
+    }
+
+    if let Some(info) = state.rules_info {
+        show_rules_info_screen(framebuffer, state, input, speaker, info);
+        return;
+    } else if state.show_options {
+        show_options_screen(framebuffer, state, input, speaker);
+        return;
+    } else if state.show_rules {
         show_rules_screen(framebuffer, state, input, speaker);
         return;
+    } else if let Some(player) = state.handoff {
+        if show_handoff_screen(framebuffer, state, input, speaker, player) {
+            state.in_game.set_viewer(Some(player));
+            state.handoff = None;
+        }
+        return;
+    } else if state.show_card_inspector {
+        render_in_game(framebuffer, &state.in_game, Face::Down);
+        show_card_inspector(framebuffer, state, input, speaker);
+        return;
     } else {
         update(state, input, speaker);
 
-        render_in_game(framebuffer, &state.in_game);
+        let others = if state.watch.show_all {
+            Face::Up
+        } else {
+            Face::Down
+        };
+        render_in_game(framebuffer, &state.in_game, others);
+
+        if state.in_game.human_seats.is_empty() {
+            print_watch_status(framebuffer, &state.watch);
+        }
     }
 
     if state.round_is_over() {
+        if state.game_is_over() {
+            // Once the game is decided, there is nothing left to keep secret.
+            reveal_secret_rules(state);
+        }
+
         if let Some(()) = choose_play_again(state) {
-            reset(state);
+            if !state.game_is_over() {
+                reset(state);
+            } else {
+                if state.config.mode == GameMode::Daily {
+                    // So the summary can be copied from the log and shared.
+                    log(&state.daily_summary());
+                }
+
+                if let Some(config) = state.chosen_config.take() {
+                    state.config = config;
+                }
+                state.setup = Some(SetupPage::Main);
+                state.context.set_next_hot(1);
+            }
         }
     }
 
@@ -0,0 +1,250 @@
+//! Playing in a game hosted somewhere else. See the `net` crate.
+use super::{
+    draw_event_log, init_logging, load_config, load_weights, move_cursor, print_status_line,
+    render_in_game, update_event_log, Face,
+};
+use choices::{choose_option, choose_suit, do_choices, print_choice_header};
+use common::*;
+use game_state::{Choice, Config, GameState};
+use net::{Client, Move, Prompt, View};
+use platform_types::{Button, Input, Speaker, State, StateParams, SFX};
+use std::io;
+
+/// Hosts a game on `address` for `human_count` people to join, along with a bot for each
+/// of the `bots` commands, with the settings last used on the setup screen, until the
+/// process is stopped.
+pub fn host(
+    StateParams {
+        seed,
+        logger,
+        error_logger,
+        storage,
+        requested_seed,
+        today,
+    }: StateParams,
+    address: Option<&str>,
+    human_count: u8,
+    bots: &[String],
+) -> io::Result<()> {
+    init_logging(logger, error_logger);
+
+    let config = Config {
+        human_seats: HumanSeats::new(human_count.saturating_add(bots.len() as u8)),
+        ..load_config(storage, requested_seed, today)
+    };
+
+    net::serve(address, seed, config, load_weights(storage), bots)
+}
+
+/// The state of a game being played on a host somewhere else, from one player's seat.
+pub struct RemoteState {
+    client: Client,
+    view: View,
+    // Whether a move has been sent that the host has not answered yet.
+    sent_move: bool,
+    // Only used to show `view`, with the same code that shows local games.
+    game_state: GameState,
+    framebuffer: Framebuffer,
+    input: Input,
+    speaker: Speaker,
+}
+
+impl RemoteState {
+    pub fn new(
+        StateParams {
+            seed,
+            logger,
+            error_logger,
+            ..
+        }: StateParams,
+        address: &str,
+    ) -> io::Result<Self> {
+        init_logging(logger, error_logger);
+
+        Ok(RemoteState {
+            client: Client::connect(address)?,
+            view: View::default(),
+            sent_move: false,
+            game_state: GameState::new(seed, Config::default()),
+            framebuffer: Framebuffer::new(),
+            input: Input::new(),
+            speaker: Speaker::default(),
+        })
+    }
+
+    fn send(&mut self, player_move: Move) {
+        self.client.send(player_move);
+        self.sent_move = true;
+    }
+
+    fn update_and_render(&mut self) {
+        let input = self.input;
+        let state = &mut self.game_state;
+        let framebuffer = &mut self.framebuffer;
+        let speaker = &mut self.speaker;
+
+        state.context.frame_init();
+
+        framebuffer.clearTo(1 /* green */);
+
+        if let Some(view) = self.client.poll() {
+            // A choice that was up for the last prompt no longer applies.
+            if view.prompt != self.view.prompt {
+                state.choice = Choice::NoChoice;
+            }
+
+            show_view(state, &view);
+            self.view = view;
+            self.sent_move = false;
+        }
+
+        if !self.client.is_connected() {
+            framebuffer.full_window();
+            print_choice_header(framebuffer, b"lost the connection to the host.");
+            return;
+        }
+
+        let mut player_move = None;
+        if !update_event_log(state, input) && !self.sent_move {
+            match self.view.prompt {
+                Prompt::Play => {
+                    if move_cursor(&mut state.in_game, input, speaker) {
+                        //Already handled.
+                    } else if input.pressed_this_frame(Button::A) {
+                        player_move = Some(Move::Play(state.in_game.hand_index));
+                    } else if input.pressed_this_frame(Button::B) {
+                        player_move = Some(Move::Draw);
+                    }
+                }
+                Prompt::Suit => player_move = choose_suit(state).map(Move::Suit),
+                Prompt::Rule => {
+                    let count = self.view.options.len();
+                    player_move = choose_option(
+                        state,
+                        "pick a new rule",
+                        (1..=count).map(|i| i.to_string()).collect(),
+                        self.view
+                            .options
+                            .iter()
+                            .map(|option| vec![option.clone()])
+                            .collect(),
+                    )
+                    .map(Move::Rule);
+                }
+                Prompt::RoundOver => {
+                    player_move = choose_option(
+                        state,
+                        "round over",
+                        vec!["ready".to_owned()],
+                        vec![self.view.options.clone()],
+                    )
+                    .map(|_| Move::Ready);
+                }
+                Prompt::Wait => {}
+            }
+        }
+
+        // The other players' cards are only stand-ins, so they cannot be shown face up.
+        render_in_game(framebuffer, &state.in_game, Face::Down);
+        print_status(framebuffer, &self.view);
+
+        if state.log_height > 0 {
+            draw_event_log(framebuffer, state);
+        } else {
+            do_choices(framebuffer, state, input, speaker);
+        }
+
+        if let Some(player_move) = player_move {
+            self.send(player_move);
+        }
+    }
+}
+
+/// Makes `state` look like `view`, so it can be shown the same way a local game is.
+/// The cards no one in `view.seat` can see are all shown face down, so their values do
+/// not matter.
+fn show_view(state: &mut GameState, view: &View) {
+    let seats = HumanSeats::new(view.human_count);
+    state.config.human_seats = seats;
+    state.in_game.human_seats = seats;
+
+    let in_game = &mut state.in_game;
+    if in_game.viewer != Some(view.seat) {
+        in_game.set_viewer(Some(view.seat));
+    }
+
+    for player in all_player_ids() {
+        let hand = in_game.get_hand_mut(player);
+        *hand = Hand::new(hand.spread);
+        if player == view.seat {
+            hand.fill(view.hand.iter().cloned());
+        } else {
+            hand.fill(std::iter::repeat_n(
+                0,
+                view.hand_sizes[player as usize] as usize,
+            ));
+        }
+    }
+
+    in_game.deck = Hand::new(in_game.deck.spread);
+    in_game
+        .deck
+        .fill(std::iter::repeat_n(0, view.deck_size as usize));
+
+    in_game.discard = Hand::new(in_game.discard.spread);
+    in_game.discard.fill(std::iter::repeat_n(
+        0,
+        view.discard_size.saturating_sub(1) as usize,
+    ));
+    in_game.discard.fill(view.top.into_iter());
+
+    in_game.top_wild_declared_as = view.declared;
+    in_game.hand_index = in_game
+        .hand_index
+        .min(view.hand.len().saturating_sub(1) as u8);
+
+    state.event_log.clear();
+    for line in view.log.iter() {
+        state.event_log.push_line(line.as_bytes());
+    }
+    state.event_log.top_index = state.event_log.len().saturating_sub(1);
+}
+
+fn print_status(framebuffer: &mut Framebuffer, view: &View) {
+    let text = match (view.prompt, view.waiting_for) {
+        (Prompt::Play, _) => "a plays. b draws.".to_owned(),
+        (_, Some(player)) if player != view.seat => format!(
+            "waiting for {}",
+            player_name(HumanSeats::new(view.human_count), player)
+        ),
+        (Prompt::Wait, None) => "waiting for everyone".to_owned(),
+        _ => return,
+    };
+
+    print_status_line(framebuffer, &text);
+}
+
+impl State for RemoteState {
+    fn frame(&mut self) -> (&[platform_types::Command], &[SFX]) {
+        self.framebuffer.commands.clear();
+        self.speaker.clear();
+        self.update_and_render();
+
+        self.input.previous_gamepad = self.input.gamepad;
+
+        (&self.framebuffer.commands, self.speaker.slice())
+    }
+
+    fn press(&mut self, button: Button) {
+        if self.input.previous_gamepad.contains(button) {
+            //This is meant to pass along the key repeat, if any.
+            self.input.previous_gamepad.remove(button);
+        }
+
+        self.input.gamepad.insert(button);
+    }
+
+    fn release(&mut self, button: Button) {
+        self.input.gamepad.remove(button);
+    }
+}
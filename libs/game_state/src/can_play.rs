@@ -3,20 +3,20 @@ use std::fmt;
 
 #[derive(Clone)]
 pub struct Graph {
-    pub nodes: [CardFlags; DECK_SIZE as usize],
+    pub nodes: [CardFlags; FACE_COUNT as usize],
 }
 
 impl Graph {
     pub fn is_playable_on(&self, card: Card, top_of_discard: Card) -> bool {
-        self.nodes[card as usize].has_card(top_of_discard)
+        self.get_edges(card).has_card(top_of_discard)
     }
 
     pub fn get_edges(&self, card: Card) -> CardFlags {
-        self.nodes[card as usize]
+        self.nodes[get_face(card) as usize]
     }
 
     pub fn set_edges(&mut self, card: Card, edges: CardFlags) {
-        self.nodes[card as usize] = edges;
+        self.nodes[get_face(card) as usize] = edges;
     }
 }
 
@@ -26,13 +26,15 @@ impl Default for Graph {
         // the cards go from 0-51, in ascending rank order,
         // and in ♣ ♦ ♥ ♠ suit order (alphabetical)
         // A♣, 2♣, ... K♣, A♦, ..., A♥, ..., A♠, ..., K♠.
-        let mut nodes = [CardFlags::default(); DECK_SIZE as usize];
+        // The jokers come after those. They can be played on anything, and anything can be
+        // played on them.
+        let mut nodes = [CardFlags::new(ALL_FACE_FLAGS); FACE_COUNT as usize];
 
         for (suit_i, suit) in SUIT_FLAGS.iter().enumerate() {
             for (rank_i, rank) in RANK_FLAGS.iter().enumerate() {
                 let i = suit_i * RANK_COUNT as usize + rank_i;
 
-                nodes[i] = CardFlags::new(suit | rank);
+                nodes[i] = CardFlags::new(suit | rank | JOKER_FLAGS);
             }
         }
 
@@ -45,7 +47,7 @@ pub struct Change(u64);
 
 impl Change {
     pub fn new(edges: CardFlags, card: Card) -> Self {
-        Change(((card as u64) << DECK_SIZE) | edges.get_bits())
+        Change(((get_face(card) as u64) << FACE_COUNT) | edges.get_bits())
     }
 
     pub fn edges(&self) -> CardFlags {
@@ -53,7 +55,7 @@ impl Change {
     }
 
     pub fn card(&self) -> Card {
-        (self.0 >> DECK_SIZE as u64) as u8 & 0b0011_1111
+        (self.0 >> FACE_COUNT as u64) as u8 & 0b0011_1111
     }
 }
 
@@ -75,10 +77,65 @@ impl fmt::Debug for Change {
     }
 }
 
+/// How a `SetChange` combines the chosen edges with each card's current edges.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SetOperation {
+    #[default]
+    Add,
+    Remove,
+    Replace,
+}
+
+/// A change to the edges of every card in a set at once.
+#[derive(Clone, Copy, Debug)]
+pub struct SetChange {
+    pub cards: CardFlags,
+    pub operation: SetOperation,
+    pub edges: CardFlags,
+}
+
+impl SetChange {
+    pub fn apply_to_edges(&self, old_edges: CardFlags) -> CardFlags {
+        match self.operation {
+            SetOperation::Add => CardFlags::new(old_edges.get_bits() | self.edges.get_bits()),
+            SetOperation::Remove => CardFlags::new(old_edges.get_bits() & !self.edges.get_bits()),
+            SetOperation::Replace => self.edges,
+        }
+    }
+
+    /// Expands the set change into one `Change` per card, given each card's current edges.
+    pub fn expand(&self, get_edges: impl Fn(Card) -> CardFlags) -> Vec<Change> {
+        self.cards
+            .into_iter()
+            .map(|card| Change::new(self.apply_to_edges(get_edges(card)), card))
+            .collect()
+    }
+}
+
+/// Returns the union and the intersection of the edges of the given cards.
+pub fn get_union_and_intersection(
+    cards: CardFlags,
+    get_edges: impl Fn(Card) -> CardFlags,
+) -> (CardFlags, CardFlags) {
+    let mut union = 0;
+    let mut intersection = if cards.is_empty() { 0 } else { !0 };
+
+    for card in cards {
+        let edges = get_edges(card).get_bits();
+        union |= edges;
+        intersection &= edges;
+    }
+
+    (CardFlags::new(union), CardFlags::new(intersection))
+}
+
 #[derive(Debug, Clone)]
 pub enum Layer {
     Card,
     Edges,
+    Set,
+    SetOperation,
+    SetEdges,
     Done,
 }
 
@@ -97,6 +154,33 @@ pub struct ChoiceState {
     pub edges_card: Card,
     pub layer: Layer,
     pub scroll_card: Card,
+    pub set: CardFlags,
+    pub operation: SetOperation,
+    pub quick_select: QuickSelectState,
+}
+
+impl ChoiceState {
+    /// Returns the edges `card` will have if the changes chosen so far are applied.
+    pub fn get_current_edges(&self, graph: &Graph, card: Card) -> CardFlags {
+        self.changes
+            .iter()
+            .rev()
+            .find(|c| c.card() == card)
+            .map(|c| c.edges())
+            .unwrap_or_else(|| graph.get_edges(card))
+    }
+
+    /// Goes back to the layer before the current one, discarding anything chosen on the
+    /// current one. Returns `false` if there is no previous layer to go back to.
+    pub fn previous_layer(&mut self) -> bool {
+        self.layer = match self.layer {
+            Layer::Card | Layer::Done => return false,
+            Layer::Edges | Layer::Set => Layer::Card,
+            Layer::SetOperation => Layer::Set,
+            Layer::SetEdges => Layer::SetOperation,
+        };
+        true
+    }
 }
 
 implement!(BorrowMut<Card> for ChoiceState: s, s.card);
@@ -115,6 +199,11 @@ impl CardSubChoice for ChoiceState {
     fn next_layer(&mut self) {
         self.layer = Layer::Edges;
     }
+    fn next_set_layer(&mut self) {
+        self.edges = CardFlags::new(0);
+        self.reset_edges = self.edges;
+        self.layer = Layer::Set;
+    }
     fn get_status_lines(&self, _card: Card) -> StatusLines {
         let changes_len = self.changes.len();
         [
@@ -132,9 +221,18 @@ implement!(BorrowPairMut<Card, CardFlags> for ChoiceState: s, (s.edges_card, s.e
 
 impl CardFlagsSubChoice for ChoiceState {
     fn mark_done(&mut self) {
-        self.layer = Layer::Card;
+        self.layer = match self.layer {
+            Layer::Set if !self.edges.is_empty() => {
+                self.set = self.edges;
+                Layer::SetOperation
+            }
+            _ => Layer::Card,
+        };
     }
     fn reset(&mut self) {
         self.edges = self.reset_edges;
     }
+    fn quick_select_mut(&mut self) -> &mut QuickSelectState {
+        &mut self.quick_select
+    }
 }
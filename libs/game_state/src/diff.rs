@@ -0,0 +1,148 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edit<T> {
+    Same(T),
+    Add(T),
+    Remove(T),
+}
+
+/// Returns a shortest edit script that turns `old` into `new`, using the Myers diff algorithm.
+/// See https://blog.jcoglan.com/2017/02/12/the-myers-diff-algorithm-part-1/
+pub fn get_edits<T: Eq + Copy>(old: &[T], new: &[T]) -> Vec<Edit<T>> {
+    let n = old.len() as isize;
+    let m = new.len() as isize;
+    let max = n + m;
+
+    // `v[k + offset]` holds the furthest x reached so far on diagonal k.
+    let offset = max + 1;
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    let mut trace = Vec::new();
+
+    'outer: for d in 0..=max {
+        trace.push(v.clone());
+
+        for k in (-d..=d).step_by(2) {
+            let i = (k + offset) as usize;
+
+            let mut x = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+                v[i + 1]
+            } else {
+                v[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+
+            v[i] = x;
+
+            if x >= n && y >= m {
+                break 'outer;
+            }
+        }
+    }
+
+    let mut edits = Vec::with_capacity(max as usize);
+    let (mut x, mut y) = (n, m);
+
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let i = (k + offset) as usize;
+
+        let previous_k = if k == -d || (k != d && v[i - 1] < v[i + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[(previous_k + offset) as usize];
+        let previous_y = previous_x - previous_k;
+
+        while x > previous_x && y > previous_y {
+            edits.push(Edit::Same(old[(x - 1) as usize]));
+            x -= 1;
+            y -= 1;
+        }
+
+        if d > 0 {
+            if x == previous_x {
+                edits.push(Edit::Add(new[(y - 1) as usize]));
+            } else {
+                edits.push(Edit::Remove(old[(x - 1) as usize]));
+            }
+        }
+
+        x = previous_x;
+        y = previous_y;
+    }
+
+    edits.reverse();
+
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_to_old<T: Copy>(edits: &[Edit<T>]) -> (Vec<T>, Vec<T>) {
+        let mut old = Vec::new();
+        let mut new = Vec::new();
+        for &edit in edits {
+            match edit {
+                Edit::Same(t) => {
+                    old.push(t);
+                    new.push(t);
+                }
+                Edit::Add(t) => new.push(t),
+                Edit::Remove(t) => old.push(t),
+            }
+        }
+        (old, new)
+    }
+
+    #[test]
+    fn test_get_edits_finds_a_shortest_edit_script() {
+        let old = b"ABCABBA";
+        let new = b"CBABAC";
+
+        let edits = get_edits(old, new);
+
+        assert_eq!(apply_to_old(&edits), (old.to_vec(), new.to_vec()));
+        assert_eq!(
+            edits
+                .iter()
+                .filter(|e| !matches!(e, Edit::Same(_)))
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn test_get_edits_on_equal_sequences_only_produces_same() {
+        let old = [1, 2, 3];
+
+        assert_eq!(
+            get_edits(&old, &old),
+            vec![Edit::Same(1), Edit::Same(2), Edit::Same(3)]
+        );
+        assert_eq!(get_edits::<u8>(&[], &[]), vec![]);
+    }
+
+    #[test]
+    fn test_get_edits_keeps_a_common_suffix_after_a_mismatch() {
+        let edits = get_edits(&[1, 2, 3, 4], &[1, 5, 3, 4]);
+
+        assert_eq!(
+            edits,
+            vec![
+                Edit::Same(1),
+                Edit::Remove(2),
+                Edit::Add(5),
+                Edit::Same(3),
+                Edit::Same(4)
+            ]
+        );
+    }
+}
@@ -1,11 +1,16 @@
 use crate::{
     can_play,
+    diff,
     in_game,
+    weights::Weights,
 };
 use common::{bytes_lines, bytes_reflow, slice_until_first_0, CardFlags, UIContext, RANK_FLAGS, xs::{Xs, Seed}, *};
 
 use std::collections::VecDeque;
+use platform_types::{Input, Speaker};
+use std::any::Any;
 use std::cmp::min;
+use std::fmt;
 
 #[derive(Debug)]
 pub struct EventLog {
@@ -163,15 +168,22 @@ pub struct CardFlagsChoiceState {
     pub flags: CardFlags,
     pub card: Card,
     pub done: bool,
+    pub quick_select: QuickSelectState,
 }
 
 impl CardFlagsChoiceState {
     pub fn new(original_flags: CardFlags) -> Self {
+        CardFlagsChoiceState::with_flags(original_flags, original_flags)
+    }
+
+    /// Starts with `flags` selected, while still resetting to `original_flags`.
+    pub fn with_flags(original_flags: CardFlags, flags: CardFlags) -> Self {
         CardFlagsChoiceState {
             original_flags,
-            flags: original_flags,
+            flags,
             card: d!(),
             done: d!(),
+            quick_select: d!(),
         }
     }
 }
@@ -195,6 +207,30 @@ impl CardFlagsSubChoice for CardFlagsChoiceState {
     fn reset(&mut self) {
         self.flags = self.original_flags;
     }
+    fn quick_select_mut(&mut self) -> &mut QuickSelectState {
+        &mut self.quick_select
+    }
+}
+
+/// An in-progress choice made through the request API in the `choices` crate.
+/// This is object safe so that any request can be stored in `Choice::OfRequest`.
+pub trait ErasedRequest: fmt::Debug {
+    fn frame(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        context: &mut UIContext,
+        input: Input,
+        speaker: &mut Speaker,
+    );
+    fn is_finished(&self) -> bool;
+    fn clone_box(&self) -> Box<dyn ErasedRequest>;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl Clone for Box<dyn ErasedRequest> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -202,17 +238,18 @@ pub enum Choice {
     NoChoice,
     Already(Chosen),
     OfCanPlayGraph(can_play::ChoiceState),
-    OfCardFlags(CardFlagsChoiceState),
     OfInGameChanges(in_game::ChoiceState),
-    OfStatus,
-    OfSuit,
-    OfBool,
+    OfRequest(Box<dyn ErasedRequest>),
     OfUnit,
 }
 
 impl Choice {
     pub fn is_idle(&self) -> bool {
-        matches!(*self, Choice::NoChoice | Choice::Already(_))
+        match *self {
+            Choice::NoChoice | Choice::Already(_) => true,
+            Choice::OfRequest(ref request) => request.is_finished(),
+            _ => false,
+        }
     }
 }
 
@@ -232,10 +269,6 @@ impl Empty for Choice {
 pub enum Chosen {
     InGameChanges(in_game::ChoiceState),
     CanPlayGraph(Vec<can_play::Change>),
-    CardFlags(CardFlags),
-    Status(Status),
-    Suit(Suit),
-    Bool(bool),
     Unit(()),
 }
 
@@ -244,13 +277,15 @@ pub enum LogHeading {
     Down,
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Status {
     InGame,
     RuleSelection,
     RuleSelectionCanPlay,
     RuleSelectionWild,
     RuleSelectionWhenPlayed,
+    RuleSelectionDeal,
+    RuleSelectionConfirm,
 }
 
 impl Default for Status {
@@ -259,10 +294,11 @@ impl Default for Status {
     }
 }
 
-pub const RULE_TYPES: [Status; 3] = [
+pub const RULE_TYPES: [Status; 4] = [
     Status::RuleSelectionCanPlay,
     Status::RuleSelectionWild,
     Status::RuleSelectionWhenPlayed,
+    Status::RuleSelectionDeal,
 ];
 
 pub fn get_status_text(status: Status) -> &'static str {
@@ -272,6 +308,8 @@ pub fn get_status_text(status: Status) -> &'static str {
         Status::RuleSelectionCanPlay => "card playability",
         Status::RuleSelectionWild => "wildness",
         Status::RuleSelectionWhenPlayed => "when played",
+        Status::RuleSelectionDeal => "dealing",
+        Status::RuleSelectionConfirm => "RuleSelectionConfirm!?",
     }
 }
 
@@ -279,17 +317,876 @@ pub struct Rules {
     pub can_play_graph: can_play::Graph,
     pub wild: CardFlags,
     pub when_played: CardChangeTable,
+    pub deal: in_game::Deal,
+    pub provenance: ProvenanceTable,
+    pub secrets: Secrets,
+    pub history: RuleHistory,
+}
+
+/// The reason a card cannot currently be played.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Blocker {
+    /// The top of the discard pile is wild and was declared as a different suit.
+    DeclaredSuit(Suit),
+    /// The can-play graph does not have an edge from the card to the top of the discard pile.
+    CanPlayGraph { top: Card },
 }
 
 impl Rules {
     pub fn is_wild(&self, card: Card) -> bool {
         self.wild.has_card(card)
     }
+
+    pub fn can_play(&self, state: &in_game::State, card: Card) -> bool {
+        self.get_blocker(state, card).is_none()
+    }
+
+    /// Returns `None` if `card` can be played on the current discard pile.
+    pub fn get_blocker(&self, state: &in_game::State, card: Card) -> Option<Blocker> {
+        let &top = state.discard.last()?;
+
+        // TODO should a card that is wild allow a non-wild card of the same rank
+        // to be played on it?
+        if self.is_wild(card) {
+            return None;
+        }
+
+        if self.is_wild(top) {
+            match state.top_wild_declared_as {
+                //this can happen depending on the card movement rules
+                None => None,
+                Some(suit) if suit == get_suit(card) => None,
+                Some(suit) => Some(Blocker::DeclaredSuit(suit)),
+            }
+        } else if self.can_play_graph.is_playable_on(card, top) {
+            None
+        } else {
+            Some(Blocker::CanPlayGraph { top })
+        }
+    }
+
+    /// Returns the number of the secret rule that caused `blocker`, if any.
+    pub fn get_secret_cause(
+        &self,
+        state: &in_game::State,
+        card: Card,
+        blocker: Blocker,
+    ) -> Option<RuleNumber> {
+        let default_wild = Rules::default().wild;
+        let wild_was_changed =
+            |c: Card| default_wild.has_card(c) != self.is_wild(c);
+
+        let secret_wild = self.secret_number(self.provenance.get_wild());
+
+        if wild_was_changed(card) && secret_wild.is_some() {
+            return secret_wild;
+        }
+
+        match blocker {
+            Blocker::DeclaredSuit(_) => state
+                .discard
+                .last()
+                .filter(|&&top| wild_was_changed(top))
+                .and(secret_wild),
+            Blocker::CanPlayGraph { .. } => {
+                self.secret_number(self.provenance.get_can_play(card))
+            }
+        }
+    }
+
+    /// Returns the rule number if the rule with the given provenance is secret.
+    pub fn secret_number(&self, provenance: Option<Provenance>) -> Option<RuleNumber> {
+        provenance
+            .map(|p| p.number)
+            .filter(|&number| self.secrets.is_secret(number))
+    }
 }
 
-type Generation = u32;
+/// Formats the provenance to go after a description of the rule.
+fn provenance_suffix(provenance: Option<Provenance>, seats: HumanSeats) -> String {
+    match provenance {
+        Some(p) => format!(" ({})", p.describe(seats)),
+        None => String::new(),
+    }
+}
+
+impl Rules {
+    /// Describes the current rules in plain language, one paragraph per element, naming
+    /// the humans in `seats` as they sit. Parts of the rules that are secret are not
+    /// described.
+    pub fn get_reference(&self, seats: HumanSeats) -> Vec<String> {
+        let mut output = Vec::new();
+
+        let wild_provenance = self.provenance.get_wild();
+        match self.secret_number(wild_provenance) {
+            Some(number) => output.push(format!("which cards are wild is secret. (rule #{})", number)),
+            None if self.wild.is_empty() => output.push(format!(
+                "no cards are wild.{}",
+                provenance_suffix(wild_provenance, seats)
+            )),
+            None => output.push(format!(
+                "wild cards: {}.{}",
+                self.wild,
+                provenance_suffix(wild_provenance, seats)
+            )),
+        }
+
+        output.push(String::new());
+
+        // Group the cards whose edges differ from the default in the same way,
+        // due to the same rule, together.
+        let default_graph = can_play::Graph::default();
+        let mut groups: Vec<(CardFlags, CardFlags, CardFlags, Option<Provenance>)> = Vec::new();
+        let mut secret_numbers: Vec<RuleNumber> = Vec::new();
+
+        for card in 0..FACE_COUNT {
+            let provenance = self.provenance.get_can_play(card);
+
+            if let Some(number) = self.secret_number(provenance) {
+                if !secret_numbers.contains(&number) {
+                    secret_numbers.push(number);
+                }
+                continue;
+            }
+
+            let default_edges = default_graph.get_edges(card);
+            let edges = self.can_play_graph.get_edges(card);
+
+            if edges == default_edges {
+                continue;
+            }
+
+            let (additions, removals) = get_additions_and_removals(default_edges, edges);
+
+            match groups
+                .iter_mut()
+                .find(|(_, a, r, p)| *a == additions && *r == removals && *p == provenance)
+            {
+                Some((cards, _, _, _)) => cards.set_card(card),
+                None => {
+                    let mut cards = CardFlags::new(0);
+                    cards.set_card(card);
+                    groups.push((cards, additions, removals, provenance));
+                }
+            }
+        }
+
+        if groups.is_empty() && secret_numbers.is_empty() {
+            output.push(
+                "cards can be played on cards of the same suit or rank.".to_owned(),
+            );
+        } else {
+            output.push(
+                "cards can be played on cards of the same suit or rank, except:".to_owned(),
+            );
+
+            for (cards, additions, removals, provenance) in groups {
+                let mut line = format!("{}", cards);
+                if !additions.is_empty() {
+                    line.push_str(&format!(" can also be played on {}", additions));
+                }
+                if !removals.is_empty() {
+                    if !additions.is_empty() {
+                        line.push_str(" and");
+                    }
+                    line.push_str(&format!(" cannot be played on {}", removals));
+                }
+                line.push('.');
+                line.push_str(&provenance_suffix(provenance, seats));
+
+                output.push(line);
+            }
+
+            for number in secret_numbers {
+                output.push(format!("some cards are affected by secret rule #{}.", number));
+            }
+        }
+
+        output.push(String::new());
+
+        let mut any_when_played = false;
+        for (card_flags, changes) in self.when_played.entries() {
+            if changes.is_empty() {
+                continue;
+            }
+            any_when_played = true;
+
+            let provenance = self.provenance.get_when_played(card_flags);
+            match self.secret_number(provenance) {
+                Some(number) => output.push(format!(
+                    "something secret happens when {} is played. (rule #{})",
+                    card_flags, number
+                )),
+                None => {
+                    output.push(format!(
+                        "when {} is played{}:",
+                        card_flags,
+                        provenance_suffix(provenance, seats)
+                    ));
+                    for change in changes {
+                        output.push(format!(" * {}", change));
+                    }
+                }
+            }
+        }
+
+        if !any_when_played {
+            output.push("nothing special happens when cards are played.".to_owned());
+        }
+
+        output.push(String::new());
+
+        let deal_provenance = self.provenance.get_deal();
+        match self.secret_number(deal_provenance) {
+            Some(number) => output.push(format!(
+                "how the cards are dealt is secret. (rule #{})",
+                number
+            )),
+            None => {
+                let mut lines = self.deal.describe(seats);
+                if let Some(last) = lines.last_mut() {
+                    last.push_str(&provenance_suffix(deal_provenance, seats));
+                }
+                output.extend(lines);
+            }
+        }
+
+        output
+    }
+
+    /// Describes what `card` does under the current rules, one paragraph per element.
+    /// Parts of the rules that are secret are not described.
+    pub fn describe_card(&self, state: &in_game::State, card: Card) -> Vec<String> {
+        let mut output = Vec::new();
+        let seats = state.human_seats;
+
+        let wild_provenance = self.provenance.get_wild();
+        let wild_secret = self.secret_number(wild_provenance);
+        let can_play_provenance = self.provenance.get_can_play(card);
+        let can_play_secret = self.secret_number(can_play_provenance);
+
+        match wild_secret {
+            Some(number) => output.push(format!("whether it is wild is secret. (rule #{})", number)),
+            None if self.is_wild(card) => output.push(format!(
+                "it is wild.{}",
+                provenance_suffix(wild_provenance, seats)
+            )),
+            None => output.push(format!(
+                "it is not wild.{}",
+                provenance_suffix(wild_provenance, seats)
+            )),
+        }
+
+        match can_play_secret {
+            Some(number) => output.push(format!(
+                "what it can be played on is secret. (rule #{})",
+                number
+            )),
+            None => {
+                let edges = self.can_play_graph.get_edges(card);
+                let suffix = provenance_suffix(can_play_provenance, seats);
+                if edges.is_empty() {
+                    output.push(format!("it cannot be played on any card.{}", suffix));
+                } else {
+                    output.push(format!("it can be played on {}.{}", edges, suffix));
+                }
+            }
+        }
+
+        let mut any_when_played = false;
+        for card_flags in self.when_played.get_card_flags(card) {
+            let provenance = self.provenance.get_when_played(card_flags);
+
+            if let Some(number) = self.secret_number(provenance) {
+                any_when_played = true;
+                output.push(format!("when played, secret rule #{} triggers.", number));
+                continue;
+            }
+
+            let mut changes = self.when_played.get_card_flags_changes(card_flags).peekable();
+            if changes.peek().is_some() {
+                any_when_played = true;
+                output.push(format!(
+                    "when played{}:",
+                    provenance_suffix(provenance, seats)
+                ));
+            }
+            for change in changes {
+                output.push(format!(" * {}", change));
+            }
+        }
+
+        if !any_when_played {
+            output.push("nothing special happens when it is played.".to_owned());
+        }
+
+        if wild_secret.is_some() || can_play_secret.is_some() {
+            output.push("whether it can be played right now is secret.".to_owned());
+        } else {
+            match self.get_blocker(state, card) {
+                None => output.push("it can be played right now.".to_owned()),
+                Some(Blocker::DeclaredSuit(suit)) => output.push(format!(
+                    "it cannot be played right now, because the wild card on top was declared as {}.",
+                    get_suit_str(suit)
+                )),
+                Some(Blocker::CanPlayGraph { top }) => output.push(format!(
+                    "it cannot be played right now, because it cannot be played on the {}.",
+                    get_card_string(top)
+                )),
+            }
+        }
+
+        output
+    }
+}
+
+impl Rules {
+    /// Describes each round's rule changes as a diff against the rules before them,
+    /// one paragraph per element, naming the humans in `seats` as they sit. Secret rules
+    /// are only listed by number.
+    pub fn get_timeline(&self, seats: HumanSeats) -> Vec<String> {
+        let mut output = Vec::new();
+
+        let mut round = None;
+
+        for RuleHistoryEntry { provenance, edits } in self.history.entries() {
+            if round != Some(provenance.round) {
+                round = Some(provenance.round);
+                if !output.is_empty() {
+                    output.push(String::new());
+                }
+                output.push(format!("after round {}:", provenance.round));
+            }
+
+            if self.secrets.is_secret(provenance.number) {
+                output.push(format!(
+                    "rule #{} by {} is secret.",
+                    provenance.number,
+                    player_name(seats, provenance.author)
+                ));
+                continue;
+            }
+
+            output.push(format!(
+                "rule #{} by {}:",
+                provenance.number,
+                player_name(seats, provenance.author)
+            ));
+
+            push_edit_descriptions(&mut output, edits, seats);
+        }
+
+        if output.is_empty() {
+            output.push("the rules have not changed yet.".to_owned());
+        }
+
+        output
+    }
+
+    /// Describes what `pending` would change if it were applied to these rules, in the
+    /// same format as `get_timeline`.
+    pub fn describe_pending(&self, pending: &PendingRule, seats: HumanSeats) -> Vec<String> {
+        let mut edits = Vec::new();
+
+        match *pending {
+            PendingRule::CanPlay(ref changes) => {
+                // Later changes to a card replace earlier ones, as when they are applied.
+                let mut after = [None; FACE_COUNT as usize];
+                for change in changes.iter() {
+                    after[change.card() as usize] = Some(change.edges());
+                }
+
+                for (card, after) in after.iter().enumerate() {
+                    let card = card as Card;
+                    if let Some(after) = *after {
+                        let before = self.can_play_graph.get_edges(card);
+                        if before != after {
+                            edits.push(RuleEdit::CanPlay {
+                                card,
+                                before,
+                                after,
+                            });
+                        }
+                    }
+                }
+            }
+            PendingRule::Wild(after) => {
+                if self.wild != after {
+                    edits.push(RuleEdit::Wild {
+                        before: self.wild,
+                        after,
+                    });
+                }
+            }
+            PendingRule::WhenPlayed(card_flags, ref after) => {
+                let before: Vec<_> = self
+                    .when_played
+                    .get_card_flags_changes(card_flags)
+                    .collect();
+                if before != *after {
+                    edits.push(RuleEdit::WhenPlayed {
+                        card_flags,
+                        before,
+                        after: after.clone(),
+                    });
+                }
+            }
+            PendingRule::Deal(after) => {
+                if self.deal != after {
+                    edits.push(RuleEdit::Deal {
+                        before: self.deal,
+                        after,
+                    });
+                }
+            }
+        }
+
+        let mut output = Vec::new();
+        push_edit_descriptions(&mut output, &edits, seats);
+
+        if output.is_empty() {
+            output.push("nothing would change.".to_owned());
+        }
+
+        output
+    }
+}
+
+/// Pushes a diff-style description of `edits`, one line per element.
+fn push_edit_descriptions(output: &mut Vec<String>, edits: &[RuleEdit], seats: HumanSeats) {
+    // Group the cards whose edges changed the same way together.
+    let mut can_play_groups: Vec<(CardFlags, CardFlags, CardFlags)> = Vec::new();
+
+    for edit in edits {
+        match edit {
+            RuleEdit::Deal { before, after } => {
+                output.push(" dealing:".to_owned());
+                output.extend(in_game::Deal::describe_changes(before, after, seats));
+            }
+            RuleEdit::Wild { before, after } => {
+                let (additions, removals) = get_additions_and_removals(*before, *after);
+                if !additions.is_empty() {
+                    output.push(format!(" + wild: {}", additions));
+                }
+                if !removals.is_empty() {
+                    output.push(format!(" - wild: {}", removals));
+                }
+            }
+            RuleEdit::CanPlay {
+                card,
+                before,
+                after,
+            } => {
+                let (additions, removals) = get_additions_and_removals(*before, *after);
+                match can_play_groups
+                    .iter_mut()
+                    .find(|(_, a, r)| *a == additions && *r == removals)
+                {
+                    Some((cards, _, _)) => cards.set_card(*card),
+                    None => {
+                        let mut cards = CardFlags::new(0);
+                        cards.set_card(*card);
+                        can_play_groups.push((cards, additions, removals));
+                    }
+                }
+            }
+            RuleEdit::WhenPlayed {
+                card_flags,
+                before,
+                after,
+            } => {
+                output.push(format!(" when {} is played:", card_flags));
+                for edit in diff::get_edits(before, after) {
+                    output.push(match edit {
+                        diff::Edit::Same(c) => format!("    {}", c),
+                        diff::Edit::Add(c) => format!("  + {}", c),
+                        diff::Edit::Remove(c) => format!("  - {}", c),
+                    });
+                }
+            }
+        }
+    }
+
+    for (cards, additions, removals) in can_play_groups {
+        output.push(format!(" {} played on:", cards));
+        if !additions.is_empty() {
+            output.push(format!("  + {}", additions));
+        }
+        if !removals.is_empty() {
+            output.push(format!("  - {}", removals));
+        }
+    }
+}
+
+fn get_additions_and_removals(before: CardFlags, after: CardFlags) -> (CardFlags, CardFlags) {
+    (
+        CardFlags::new(after.get_bits() & !before.get_bits()),
+        CardFlags::new(before.get_bits() & !after.get_bits()),
+    )
+}
+
+pub type RuleNumber = u32;
+
+/// Where a rule came from: who made it, at the end of which round, and the
+/// sequence number used to refer to it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Provenance {
+    pub author: PlayerID,
+    pub round: u32,
+    pub number: RuleNumber,
+}
+
+impl Provenance {
+    /// Describes where the rule came from, naming its author as the humans in `seats`
+    /// would see them.
+    pub fn describe(&self, seats: HumanSeats) -> String {
+        format!(
+            "rule #{} by {}, round {}",
+            self.number,
+            player_name(seats, self.author),
+            self.round
+        )
+    }
+}
+
+/// Records the provenance of the latest rule that changed each part of the rules.
+#[derive(Clone)]
+pub struct ProvenanceTable {
+    /// The rule currently being applied, if any.
+    pub current: Option<Provenance>,
+    wild: Option<Provenance>,
+    deal: Option<Provenance>,
+    can_play: [Option<Provenance>; FACE_COUNT as usize],
+    when_played: HashMap<CardFlags, Provenance>,
+    next_number: RuleNumber,
+}
+
+impl Default for ProvenanceTable {
+    fn default() -> Self {
+        ProvenanceTable {
+            current: None,
+            wild: None,
+            deal: None,
+            can_play: [None; FACE_COUNT as usize],
+            when_played: HashMap::new(),
+            next_number: 1,
+        }
+    }
+}
+
+impl ProvenanceTable {
+    /// Starts a new rule, which the following `mark_*` calls will be attributed to.
+    pub fn begin_rule(&mut self, author: PlayerID, round: u32) -> Provenance {
+        let provenance = Provenance {
+            author,
+            round,
+            number: self.next_number,
+        };
+        self.next_number += 1;
+        self.current = Some(provenance);
+        provenance
+    }
+
+    /// Finishes the current rule, so nothing else is attributed to it.
+    pub fn end_rule(&mut self) -> Option<Provenance> {
+        self.current.take()
+    }
+
+    pub fn mark_wild(&mut self) {
+        self.wild = self.current;
+    }
+
+    pub fn mark_deal(&mut self) {
+        self.deal = self.current;
+    }
+
+    pub fn mark_can_play(&mut self, card: Card) {
+        self.can_play[get_face(card) as usize] = self.current;
+    }
+
+    pub fn mark_when_played(&mut self, card_flags: CardFlags) {
+        match self.current {
+            Some(provenance) => {
+                self.when_played.insert(card_flags, provenance);
+            }
+            None => {
+                self.when_played.remove(&card_flags);
+            }
+        }
+    }
+
+    pub fn get_wild(&self) -> Option<Provenance> {
+        self.wild
+    }
+
+    pub fn get_deal(&self) -> Option<Provenance> {
+        self.deal
+    }
+
+    pub fn get_can_play(&self, card: Card) -> Option<Provenance> {
+        self.can_play[get_face(card) as usize]
+    }
+
+    pub fn get_when_played(&self, card_flags: CardFlags) -> Option<Provenance> {
+        self.when_played.get(&card_flags).cloned()
+    }
+}
+
+/// A rule the player has finished editing, but has not yet confirmed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PendingRule {
+    CanPlay(Vec<can_play::Change>),
+    Wild(CardFlags),
+    WhenPlayed(CardFlags, Vec<in_game::Change>),
+    Deal(in_game::Deal),
+}
+
+impl PendingRule {
+    /// The status that this type of rule is made in. See `RULE_TYPES`.
+    pub fn status(&self) -> Status {
+        match self {
+            PendingRule::CanPlay(_) => Status::RuleSelectionCanPlay,
+            PendingRule::Wild(_) => Status::RuleSelectionWild,
+            PendingRule::WhenPlayed(..) => Status::RuleSelectionWhenPlayed,
+            PendingRule::Deal(_) => Status::RuleSelectionDeal,
+        }
+    }
+
+    /// Makes the change to `rules` directly, without logging it or recording where it
+    /// came from.
+    pub fn apply_to(&self, rules: &mut Rules) {
+        match self {
+            PendingRule::CanPlay(changes) => {
+                for change in changes.iter() {
+                    rules
+                        .can_play_graph
+                        .set_edges(change.card(), change.edges());
+                }
+            }
+            PendingRule::Wild(wild) => rules.wild = *wild,
+            PendingRule::WhenPlayed(card_flags, changes) => {
+                rules.when_played.set_changes(*card_flags, changes.clone());
+            }
+            PendingRule::Deal(deal) => rules.deal = *deal,
+        }
+    }
+}
+
+/// What happened over a number of simulated rounds.
+#[derive(Clone, Debug, Default)]
+pub struct SimulationStats {
+    pub rounds: u32,
+    /// How many rounds each player won, counting ties as wins. Indexed by `PlayerID`.
+    pub wins: [u32; PLAYER_ID_COUNT],
+    /// How many rounds did not end within the turn limit.
+    pub unfinished: u32,
+    pub turns: u32,
+    /// How many times a card was played that the previewed rule change made a
+    /// difference to.
+    pub fires: u32,
+}
+
+/// Rounds simulated with and without a pending rule, from the same deals, so the player
+/// can see what difference the rule would make. The rounds are run a few at a time, so
+/// the game does not freeze while they run.
+pub struct RulePreview {
+    pub rule: PendingRule,
+    pub without: Rules,
+    pub with: Rules,
+    pub rng: Xs,
+    pub stats_without: SimulationStats,
+    pub stats_with: SimulationStats,
+    /// Who is sitting where in the game being previewed.
+    pub human_seats: HumanSeats,
+    /// How many cpu players are dealt in to the game being previewed.
+    pub cpu_count: u8,
+}
+
+impl RulePreview {
+    /// How many rounds are simulated with, and without, the rule.
+    pub const ROUNDS: u32 = 200;
+
+    pub fn new(
+        rules: &Rules,
+        rule: PendingRule,
+        seed: Seed,
+        human_seats: HumanSeats,
+        cpu_count: u8,
+    ) -> Self {
+        let without = rules.for_simulation();
+        let mut with = rules.for_simulation();
+        rule.apply_to(&mut with);
+
+        RulePreview {
+            rule,
+            without,
+            with,
+            rng: xs::from_seed(seed),
+            stats_without: d!(),
+            stats_with: d!(),
+            human_seats,
+            cpu_count,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.stats_with.rounds >= RulePreview::ROUNDS
+    }
+
+    pub fn describe(&self) -> Vec<String> {
+        let mut output = vec![String::new()];
+
+        if !self.is_done() {
+            output.push(format!(
+                "simulating rounds: {} of {}...",
+                self.stats_with.rounds,
+                RulePreview::ROUNDS
+            ));
+            return output;
+        }
+
+        let (without, with) = (&self.stats_without, &self.stats_with);
+        let percent = |count: u32, stats: &SimulationStats| count * 100 / stats.rounds.max(1);
+        let average = |total: u32, stats: &SimulationStats| total / stats.rounds.max(1);
+
+        output.push(format!(
+            "over {} simulated rounds, without this change, and with it:",
+            with.rounds
+        ));
+
+        for player in all_player_ids() {
+            let i = player as usize;
+            output.push(format!(
+                "{} won {}% -> {}%",
+                player_name(self.human_seats, player),
+                percent(without.wins[i], without),
+                percent(with.wins[i], with)
+            ));
+        }
+
+        if without.unfinished > 0 || with.unfinished > 0 {
+            output.push(format!(
+                "no one won {}% -> {}%",
+                percent(without.unfinished, without),
+                percent(with.unfinished, with)
+            ));
+        }
+
+        output.push(format!(
+            "rounds took {} -> {} turns, on average.",
+            average(without.turns, without),
+            average(with.turns, with)
+        ));
+
+        output.push(format!(
+            "this change made a difference {}.{} times a round, on average.",
+            average(with.fires, with),
+            average(with.fires * 10, with) % 10
+        ));
+
+        output
+    }
+}
+
+/// A single edit to one part of the rules.
+#[derive(Clone, Debug)]
+pub enum RuleEdit {
+    Wild {
+        before: CardFlags,
+        after: CardFlags,
+    },
+    CanPlay {
+        card: Card,
+        before: CardFlags,
+        after: CardFlags,
+    },
+    WhenPlayed {
+        card_flags: CardFlags,
+        before: Vec<in_game::Change>,
+        after: Vec<in_game::Change>,
+    },
+    Deal {
+        before: in_game::Deal,
+        after: in_game::Deal,
+    },
+}
+
+pub struct RuleHistoryEntry {
+    pub provenance: Provenance,
+    pub edits: Vec<RuleEdit>,
+}
+
+/// Every edit made to the rules, grouped by the rule that made them, in order.
+#[derive(Default)]
+pub struct RuleHistory {
+    entries: Vec<RuleHistoryEntry>,
+}
 
+impl RuleHistory {
+    pub fn record(&mut self, provenance: Provenance, edit: RuleEdit) {
+        match self.entries.last_mut() {
+            Some(entry) if entry.provenance == provenance => entry.edits.push(edit),
+            _ => self.entries.push(RuleHistoryEntry {
+                provenance,
+                edits: vec![edit],
+            }),
+        }
+    }
+
+    pub fn entries(&self) -> &[RuleHistoryEntry] {
+        &self.entries
+    }
+}
+
+/// A rule that the human player has not been told about.
+pub struct SecretRule {
+    pub provenance: Provenance,
+    /// The log lines that would have described the rule change if it were public.
+    pub lines: Vec<Vec<u8>>,
+}
+
+/// The rules that are currently secret, for the hidden-rule mode. Since the provenance
+/// table records the latest rule that changed each part, a public change to the same
+/// part makes it public again.
 #[derive(Default)]
+pub struct Secrets {
+    pub rules: Vec<SecretRule>,
+}
+
+impl Secrets {
+    pub fn is_secret(&self, number: RuleNumber) -> bool {
+        self.rules.iter().any(|r| r.provenance.number == number)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Makes all the secret rules public, logging what they were, with their authors
+    /// named as the humans in `seats` would see them.
+    pub fn reveal(&mut self, event_log: &mut EventLog, seats: HumanSeats) {
+        if self.is_empty() {
+            return;
+        }
+
+        event_log.push_hr();
+        event_log.push(b"the secret rules were:");
+
+        for SecretRule { provenance, lines } in self.rules.drain(..) {
+            event_log.push_hr();
+            event_push!(event_log, provenance.describe(seats).as_bytes(), b":");
+            for line in lines {
+                event_log.push(&line);
+            }
+        }
+
+        event_log.push_hr();
+    }
+}
+
+type Generation = u32;
+
+#[derive(Clone, Default)]
 pub struct CardChanges {
     changes: Vec<in_game::Change>,
     generation: Generation,
@@ -297,6 +1194,7 @@ pub struct CardChanges {
 
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct CardChangeTable {
     map: HashMap<CardFlags, CardChanges>,
     index: HashMap<Card, Vec<CardFlags>>,
@@ -317,7 +1215,7 @@ impl CardChangeTable {
     pub fn get_card_changes(&self, card: Card) -> impl Iterator<Item = in_game::Change> {
         let output: Vec<in_game::Change> = self
             .index
-            .get(&card)
+            .get(&get_face(card))
             .map(|flags: &Vec<CardFlags>| {
                 //we assume tese flags are already in the right order.
                 flags
@@ -335,6 +1233,13 @@ impl CardChangeTable {
 
         output.into_iter()
     }
+    /// Returns the card flags with changes that apply to `card`, in the order they apply.
+    pub fn get_card_flags(&self, card: Card) -> impl Iterator<Item = CardFlags> + '_ {
+        self.index
+            .get(&get_face(card))
+            .into_iter()
+            .flat_map(|flags| flags.iter().cloned())
+    }
     pub fn get_card_flags_changes(
         &self,
         card_flags: CardFlags,
@@ -346,6 +1251,17 @@ impl CardChangeTable {
             .flat_map(|c| c.changes.iter())
             .cloned()
     }
+    /// Returns every entry in the table, in the order they were set.
+    pub fn entries(&self) -> Vec<(CardFlags, &[in_game::Change])> {
+        let mut entries: Vec<_> = self.map.iter().collect();
+
+        entries.sort_by_key(|(_, c)| c.generation);
+
+        entries
+            .into_iter()
+            .map(|(&flags, c)| (flags, c.changes.as_slice()))
+            .collect()
+    }
     pub fn set_changes(&mut self, card_flags: CardFlags, changes: Vec<in_game::Change>) {
         self.map.insert(
             card_flags,
@@ -399,6 +1315,332 @@ mod tests {
 
         assert_eq!(changes, actual);
     }
+
+    #[test]
+    fn test_a_card_blocked_by_a_secret_can_play_change_reports_that_rule() {
+        let mut rules = Rules::default();
+        let mut state = in_game::State::default();
+        let (card, top) = (0, 1);
+        state.discard.push(top);
+
+        assert_eq!(rules.get_blocker(&state, card), None);
+
+        let provenance = rules.provenance.begin_rule(0, 1);
+        rules.can_play_graph.set_edges(card, CardFlags::new(0));
+        rules.provenance.mark_can_play(card);
+        rules.secrets.rules.push(SecretRule {
+            provenance,
+            lines: Vec::new(),
+        });
+        let number = provenance.number;
+
+        let blocker = rules.get_blocker(&state, card);
+
+        assert_eq!(blocker, Some(Blocker::CanPlayGraph { top }));
+        assert_eq!(
+            rules.get_secret_cause(&state, card, blocker.unwrap()),
+            Some(number)
+        );
+    }
+
+    #[test]
+    fn test_pop_status_returns_to_the_choice_in_progress_when_the_status_was_pushed() {
+        let mut state = GameState::new([42; 16], d!());
+        state.status = Status::RuleSelectionCanPlay;
+        state.choice = Choice::OfCanPlayGraph(can_play::ChoiceState {
+            card: 7,
+            ..d!()
+        });
+
+        state.push_status(Status::RuleSelectionConfirm);
+
+        assert!(state.choice.is_idle());
+
+        assert!(state.pop_status());
+
+        match (state.status, &state.choice) {
+            (Status::RuleSelectionCanPlay, Choice::OfCanPlayGraph(choice_state)) => {
+                assert_eq!(choice_state.card, 7)
+            }
+            (status, choice) => panic!("{:?} {:?}", status, choice),
+        }
+
+        assert!(!state.pop_status());
+    }
+
+    #[test]
+    fn test_a_round_is_dealt_the_way_the_deal_says() {
+        let deal = in_game::Deal {
+            hand_sizes: [1, 3, 7, 10],
+            flip_starting_card: true,
+            rotate_dealer: true,
+            composition: d!(),
+        };
+
+        for round in 1..=PLAYER_ID_COUNT as u32 * 2 {
+            let mut rng = xs::from_seed([round as u8; 16]);
+            let state = in_game::State::new(&mut rng, &deal, round, d!(), CPU_COUNT as u8);
+
+            for player in all_player_ids() {
+                assert_eq!(
+                    state.get_hand(player).len(),
+                    deal.hand_sizes[player as usize]
+                );
+            }
+            assert_eq!(state.discard.len(), 1);
+            assert_eq!(state.missing_cards(), vec![0; 0]);
+            // The player after the dealer goes first.
+            assert_eq!(
+                state.incremented_current_player(),
+                (round as u8 + 1) % PLAYER_ID_COUNT as u8
+            );
+        }
+    }
+
+    #[test]
+    fn test_copies_of_a_card_from_different_decks_are_told_apart_but_play_the_same() {
+        let deal = in_game::Deal {
+            composition: DeckComposition {
+                decks: 2,
+                jokers: true,
+            },
+            ..d!()
+        };
+
+        let mut rng = xs::from_seed([41; 16]);
+        let state = in_game::State::new(&mut rng, &deal, 1, d!(), CPU_COUNT as u8);
+
+        let mut cards: Vec<Card> = state.deck.iter().cloned().collect();
+        for player in all_player_ids() {
+            cards.extend(state.get_hand(player).iter());
+        }
+        cards.sort_unstable();
+        cards.dedup();
+
+        assert_eq!(cards.len(), deal.composition.size() as usize);
+        assert_eq!(state.missing_cards(), vec![0; 0]);
+
+        let rules = Rules::default();
+        for card in 0..FACE_COUNT {
+            let copy = with_deck_index(card, 1);
+            assert_ne!(card, copy);
+
+            for top in [0, 13, RED_JOKER, BLACK_JOKER] {
+                assert_eq!(
+                    rules.can_play_graph.is_playable_on(copy, top),
+                    rules.can_play_graph.is_playable_on(card, top)
+                );
+            }
+        }
+
+        // Jokers can be played on anything, and anything can be played on them.
+        for card in 0..FACE_COUNT {
+            assert!(rules.can_play_graph.is_playable_on(card, RED_JOKER));
+            assert!(rules.can_play_graph.is_playable_on(BLACK_JOKER, card));
+        }
+    }
+
+    #[test]
+    fn test_config_survives_being_written_out_and_read_back() {
+        let mut config = Config {
+            rule_budget: 2,
+            cpu_chaos: [0, 3, Config::MAX_CHAOS, 1],
+            stalemate: in_game::StalemateResolution::Reshuffle,
+            cpu_count: 2,
+            difficulty: in_game::Difficulty::Hard,
+            hand_size: 9,
+            target_score: 5,
+            seed: Some(0x00c0ffee),
+            rule_types: [true, false, true, false],
+            ..d!()
+        };
+        config.set_mode(GameMode::SecretBartog);
+
+        assert_eq!(Config::parse(&config.to_text()), config);
+    }
+
+    #[test]
+    fn test_config_keeps_the_defaults_for_anything_it_cannot_read() {
+        let config = Config::parse("mode = chess\nhand_size = 200\ngarbage\ncpu_count = 2");
+
+        assert_eq!(
+            config,
+            Config {
+                cpu_count: 2,
+                ..d!()
+            }
+        );
+    }
+
+    #[test]
+    fn test_config_reads_chaos_saved_before_the_last_seat_had_a_setting() {
+        let config = Config::parse("cpu_chaos = 1 2 3");
+
+        let mut expected = Config::default().cpu_chaos;
+        expected[..CPU_COUNT].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(config.cpu_chaos, expected);
+    }
+
+    #[test]
+    fn test_date_string_gives_the_calendar_date() {
+        assert_eq!(date_string(0), "1970-01-01");
+        assert_eq!(date_string(11016), "2000-02-29");
+        assert_eq!(date_string(20745), "2026-10-19");
+    }
+
+    #[test]
+    fn test_seed_strings_read_back_as_the_same_seed() {
+        for number in [0, 1, 31, 32, 0x00c0ffee, u32::MAX - 1, u32::MAX] {
+            let text = seed_string(number);
+
+            assert_eq!(text.len(), SEED_STRING_LENGTH);
+            assert_eq!(parse_seed_string(&text), Some(number));
+        }
+    }
+
+    #[test]
+    fn test_seed_strings_copied_by_hand_still_read() {
+        let number = 0x00c0ffee;
+        let text = seed_string(number);
+
+        assert_eq!(parse_seed_string(&text.to_uppercase()), Some(number));
+        assert_eq!(
+            parse_seed_string(&format!("{}-{}", &text[..3], &text[3..])),
+            Some(number)
+        );
+        assert_eq!(parse_seed_string("o1l"), parse_seed_string("011"));
+
+        assert_eq!(parse_seed_string(""), None);
+        assert_eq!(parse_seed_string("u"), None);
+        assert_eq!(parse_seed_string(&seed_string(u32::MAX).replace('3', "4")), None);
+    }
+
+    #[test]
+    fn test_a_round_is_dealt_the_same_however_the_other_generators_were_used() {
+        let untouched = GameState::new([45; 16], d!());
+        let mut used = GameState::new([45; 16], d!());
+
+        for _ in 0..100 {
+            xs::range(&mut used.rngs.cpu, 0..10);
+            xs::range(&mut used.rngs.rules, 0..10);
+            xs::range(&mut used.rngs.reshuffle, 0..10);
+        }
+        used.redeal();
+
+        let cards = |state: &GameState| -> Vec<Vec<Card>> {
+            let mut cards: Vec<Vec<Card>> = all_player_ids()
+                .iter()
+                .map(|&player| state.in_game.get_hand(player).iter().cloned().collect())
+                .collect();
+            cards.push(state.in_game.deck.iter().cloned().collect());
+            cards
+        };
+
+        assert_eq!(cards(&used), cards(&untouched));
+
+        // The next round is not just a repeat of this one, though.
+        let mut next_round = GameState::new([45; 16], d!());
+        next_round.round_count = 1;
+        next_round.redeal();
+        assert_ne!(cards(&next_round), cards(&untouched));
+    }
+
+    #[test]
+    fn test_the_turn_order_skips_empty_seats() {
+        let mut state = in_game::State::new(&mut xs::from_seed([43; 16]), &d!(), 1, d!(), 1);
+
+        assert_eq!(state.seated_player_ids(), vec![0, MAX_PLAYER_ID]);
+        for player in 1..CPU_COUNT as PlayerID {
+            assert!(state.get_hand(player).is_empty());
+        }
+
+        state.current_player = 0;
+        assert_eq!(state.incremented_current_player(), MAX_PLAYER_ID);
+        state.current_player = MAX_PLAYER_ID;
+        assert_eq!(state.incremented_current_player(), 0);
+    }
+
+    #[test]
+    fn test_several_humans_sit_below_the_player_and_are_named_by_seat() {
+        let seats = HumanSeats::new(2);
+
+        let mut rng = xs::from_seed([47; 16]);
+        let state = in_game::State::new(&mut rng, &d!(), 1, seats, CPU_COUNT as u8);
+
+        // The humans take two of the cpu seats, so only two cpu players are dealt in.
+        assert_eq!(state.seated_player_ids(), vec![0, 1, 2, MAX_PLAYER_ID]);
+        assert!(is_cpu_player(seats, 1));
+        assert!(is_human_player(seats, 2));
+        assert_eq!(player_name(seats, 2), "player 2");
+        assert_eq!(player_name(seats, MAX_PLAYER_ID), "player 3");
+        assert_eq!(get_pronoun(seats, 2), "they");
+
+        let seats = HumanSeats::new(1);
+        assert_eq!(player_name(seats, PLAYER_ID), "you");
+        assert!(is_cpu_player(seats, 2));
+    }
+
+    #[test]
+    fn test_with_no_humans_every_seat_goes_to_a_cpu_player() {
+        let seats = HumanSeats::new(0);
+
+        let config = Config {
+            human_seats: seats,
+            cpu_count: 0,
+            ..d!()
+        };
+        // There have to be two players.
+        assert_eq!(config.cpu_count_bounds(), (2, PLAYER_ID_COUNT as u8));
+
+        let state = in_game::State::new(
+            &mut xs::from_seed([50; 16]),
+            &d!(),
+            1,
+            seats,
+            PLAYER_ID_COUNT as u8,
+        );
+
+        assert_eq!(state.seated_player_ids(), all_player_ids().to_vec());
+        for player in all_player_ids() {
+            assert!(is_cpu_player(seats, player));
+            assert!(!state.get_hand(player).is_empty());
+        }
+        assert_eq!(state.viewer, None);
+        assert_eq!(player_name(seats, PLAYER_ID), "cpu 3");
+    }
+
+    #[test]
+    fn test_a_paused_watch_only_lets_a_turn_start_when_stepped() {
+        let mut watch = Watch::default();
+        assert!(watch.next_turn_may_start());
+        assert_eq!(watch.animation_steps(), Some(1));
+
+        watch.paused = true;
+        assert!(!watch.next_turn_may_start());
+
+        watch.step = true;
+        assert!(watch.next_turn_may_start());
+        assert!(!watch.next_turn_may_start());
+
+        watch.speed = Watch::MAX_SPEED;
+        assert_eq!(watch.animation_steps(), None);
+    }
+}
+
+impl Rules {
+    /// A copy of the parts of the rules that affect how the game is played, for
+    /// simulating rounds. The history and secrets are left out.
+    pub fn for_simulation(&self) -> Rules {
+        Rules {
+            can_play_graph: self.can_play_graph.clone(),
+            wild: self.wild,
+            when_played: self.when_played.clone(),
+            deal: self.deal,
+            provenance: self.provenance.clone(),
+            secrets: d!(),
+            history: d!(),
+        }
+    }
 }
 
 impl Default for Rules {
@@ -407,6 +1649,10 @@ impl Default for Rules {
             wild: CardFlags::new(RANK_FLAGS[ranks::EIGHT as usize]),
             can_play_graph: d!(),
             when_played: d!(),
+            deal: d!(),
+            provenance: d!(),
+            secrets: d!(),
+            history: d!(),
         }
     }
 }
@@ -419,64 +1665,793 @@ impl Empty for Rules {
             can_play_graph: unsafe { mem::zeroed() },
             wild: unsafe { mem::zeroed() },
             when_played: d!(),
+            deal: d!(),
+            provenance: d!(),
+            secrets: d!(),
+            history: d!(),
         }
     }
 }
 
+/// How a game with no humans in it is being watched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Watch {
+    /// Whether new turns wait until `step` is set.
+    pub paused: bool,
+    /// Whether one turn should be taken while paused.
+    pub step: bool,
+    /// How fast the cards move, from 0 up to `Watch::MAX_SPEED`.
+    pub speed: u8,
+    /// Whether everyone's hands are shown face up.
+    pub show_all: bool,
+}
+
+impl Watch {
+    /// At this speed the cards skip straight to where they are going.
+    pub const MAX_SPEED: u8 = 4;
+
+    /// How many steps the card animations take each frame, or `None` if they should skip
+    /// straight to the end.
+    pub fn animation_steps(&self) -> Option<u8> {
+        if self.speed >= Watch::MAX_SPEED {
+            None
+        } else {
+            Some(1 << self.speed)
+        }
+    }
+
+    pub fn speed_label(&self) -> String {
+        match self.animation_steps() {
+            Some(steps) => format!("{}x", steps),
+            None => "max".to_owned(),
+        }
+    }
+
+    /// Whether the next turn can start now. Uses up the step, if that is why.
+    pub fn next_turn_may_start(&mut self) -> bool {
+        if !self.paused {
+            true
+        } else if self.step {
+            self.step = false;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// The pages of information about the rules, reachable with select.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RulesInfo {
+    Reference,
+    Timeline,
+}
+
+/// Which kind of game is being played, as chosen on the setup screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GameMode {
+    /// The winners of each round change the rules.
+    #[default]
+    Bartog,
+    /// Plain Crazy Eights. The rules never change.
+    CrazyEights,
+    /// Bartog, starting with this many random rules already made by the cpu players.
+    BartogWithRandomRules(u8),
+    /// Bartog, with the cpu players keeping their rules secret, as in Mao.
+    SecretBartog,
+    /// Bartog with the day's seed and starting rules, for a fixed number of rounds. See
+    /// `Config::daily`.
+    Daily,
+}
+
+impl GameMode {
+    /// The modes offered on the setup screen.
+    pub const PRESETS: [GameMode; 5] = [
+        GameMode::Bartog,
+        GameMode::CrazyEights,
+        GameMode::BartogWithRandomRules(3),
+        GameMode::SecretBartog,
+        GameMode::Daily,
+    ];
+
+    /// How many rounds a daily challenge lasts.
+    pub const DAILY_ROUNDS: u32 = 5;
+
+    pub fn label(&self) -> String {
+        match *self {
+            GameMode::Bartog => "bartog".to_owned(),
+            GameMode::CrazyEights => "crazy eights".to_owned(),
+            GameMode::BartogWithRandomRules(count) => format!("bartog, {} random rules", count),
+            GameMode::SecretBartog => "bartog, secret rules".to_owned(),
+            GameMode::Daily => "daily challenge".to_owned(),
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match *self {
+            GameMode::Bartog => "crazy eights, but each winner makes a new rule.",
+            GameMode::CrazyEights => "the plain game. the rules never change.",
+            GameMode::BartogWithRandomRules(_) => "bartog, but the cpu players start things off.",
+            GameMode::SecretBartog => "bartog, but cpu players keep their rules secret.",
+            GameMode::Daily => "the same deals and rules for all today. 5 rounds.",
+        }
+    }
+
+    /// Whether the winners of a round get to change the rules.
+    pub fn rules_change(&self) -> bool {
+        *self != GameMode::CrazyEights
+    }
+
+    /// How many random rules are made before the first round.
+    pub fn starting_rule_count(&self) -> u8 {
+        match *self {
+            GameMode::BartogWithRandomRules(count) => count,
+            GameMode::Daily => 3,
+            _ => 0,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::PRESETS.iter().position(|&m| m == self).unwrap_or(0);
+        Self::PRESETS[(index + 1) % Self::PRESETS.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let index = Self::PRESETS.iter().position(|&m| m == self).unwrap_or(0);
+        Self::PRESETS[(index + Self::PRESETS.len() - 1) % Self::PRESETS.len()]
+    }
+}
+
+/// Settings that persist between rounds, and are remembered between launches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// Which kind of game is being played.
+    pub mode: GameMode,
+    /// Whether the rules cpu players make are kept secret, as in Mao.
+    pub hidden_rules: bool,
+    /// How many rule changes each winner of a round gets to make.
+    pub rule_budget: u8,
+    /// How often each cpu player makes a random rule, rather than one they expect to
+    /// help them, out of `Config::MAX_CHAOS`. Indexed by `PlayerID`.
+    pub cpu_chaos: [u8; PLAYER_ID_COUNT],
+    /// What happens when no one can play or draw a card for a whole lap of turns.
+    pub stalemate: in_game::StalemateResolution,
+    /// Which seats the humans taking turns on this device sit in.
+    pub human_seats: HumanSeats,
+    /// How many cpu players a new game starts with.
+    pub cpu_count: u8,
+    /// How well the cpu players play their cards.
+    pub difficulty: in_game::Difficulty,
+    /// How many cards everyone is dealt in the first round. Rules can change it later.
+    pub hand_size: u8,
+    /// How many rounds a player has to win to win the game, or 0 to play on forever.
+    pub target_score: u8,
+    /// The seed a new game starts from, if a particular one was chosen.
+    pub seed: Option<u32>,
+    /// Which types of rules can be made. Indexed the same way as `RULE_TYPES`.
+    pub rule_types: [bool; RULE_TYPES.len()],
+    /// Not a setting, so it is not saved: the current day, counted from 1970-01-01, in
+    /// UTC. This picks the daily challenge.
+    pub today: u32,
+}
+
+impl Config {
+    pub const MAX_RULE_BUDGET: u8 = 9;
+    pub const MAX_CHAOS: u8 = 4;
+    pub const MAX_TARGET_SCORE: u8 = 20;
+
+    /// Switches to `mode`, along with the settings that go with it.
+    pub fn set_mode(&mut self, mode: GameMode) {
+        self.mode = mode;
+        self.hidden_rules = mode == GameMode::SecretBartog;
+    }
+
+    /// The settings for today's daily challenge. Everything that could change how the
+    /// game goes is fixed, so that everyone plays the same game.
+    pub fn daily(&self) -> Config {
+        Config {
+            mode: GameMode::Daily,
+            seed: Some(daily_seed_number(self.today)),
+            today: self.today,
+            ..Config::default()
+        }
+    }
+
+    /// Whether the winners of a round get to change the rules.
+    pub fn rules_change(&self) -> bool {
+        self.mode.rules_change() && self.rule_types.iter().any(|&enabled| enabled)
+    }
+
+    /// The rules a new game starts with.
+    pub fn starting_rules(&self) -> Rules {
+        let mut rules = Rules::default();
+        rules.deal.hand_sizes = [self.hand_size; PLAYER_ID_COUNT];
+        rules
+    }
+
+    /// Writes the settings out as `key = value` lines, which `Config::parse` reads.
+    pub fn to_text(&self) -> String {
+        let join = |values: Vec<String>| values.join(" ");
+
+        let lines = [
+            ("mode", self.mode.label()),
+            ("hidden_rules", self.hidden_rules.to_string()),
+            ("rule_budget", self.rule_budget.to_string()),
+            (
+                "cpu_chaos",
+                join(self.cpu_chaos.iter().map(u8::to_string).collect()),
+            ),
+            ("stalemate", self.stalemate.label().to_owned()),
+            ("human_count", self.human_seats.count().to_string()),
+            ("cpu_count", self.cpu_count.to_string()),
+            ("difficulty", self.difficulty.label().to_owned()),
+            ("hand_size", self.hand_size.to_string()),
+            ("target_score", self.target_score.to_string()),
+            (
+                "seed",
+                self.seed
+                    .map(seed_string)
+                    .unwrap_or_else(|| "random".to_owned()),
+            ),
+            (
+                "rule_types",
+                join(self.rule_types.iter().map(bool::to_string).collect()),
+            ),
+        ];
+
+        let mut output = String::new();
+        for (key, value) in lines.iter() {
+            output.push_str(key);
+            output.push_str(" = ");
+            output.push_str(value);
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Reads settings written by `Config::to_text`. Anything missing or unreadable is left
+    /// at its default, so settings saved by other versions still load.
+    pub fn parse(text: &str) -> Config {
+        let mut config = Config::default();
+
+        let number = |value: &str, (min, max): (u8, u8)| {
+            value
+                .parse::<u8>()
+                .ok()
+                .filter(|&n| min <= n && n <= max)
+        };
+
+        for line in text.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            match key {
+                "mode" => {
+                    if let Some(&mode) = GameMode::PRESETS.iter().find(|m| m.label() == value) {
+                        config.mode = mode;
+                    }
+                }
+                "hidden_rules" => {
+                    if let Ok(hidden_rules) = value.parse() {
+                        config.hidden_rules = hidden_rules;
+                    }
+                }
+                "rule_budget" => {
+                    if let Some(budget) = number(value, (1, Config::MAX_RULE_BUDGET)) {
+                        config.rule_budget = budget;
+                    }
+                }
+                "cpu_chaos" => {
+                    let chaos: Vec<u8> = value
+                        .split_whitespace()
+                        .filter_map(|v| number(v, (0, Config::MAX_CHAOS)))
+                        .collect();
+                    // Settings saved before the last seat could have a cpu player in it
+                    // leave that seat as it is.
+                    if chaos.len() == PLAYER_ID_COUNT || chaos.len() == CPU_COUNT {
+                        config.cpu_chaos[..chaos.len()].copy_from_slice(&chaos);
+                    }
+                }
+                "stalemate" => {
+                    let all = in_game::StalemateResolution::ALL;
+                    if let Some(&stalemate) = all.iter().find(|r| r.label() == value) {
+                        config.stalemate = stalemate;
+                    }
+                }
+                "human_count" => {
+                    if let Some(count) = number(value, (0, MAX_HUMAN_COUNT)) {
+                        config.human_seats = HumanSeats::new(count);
+                    }
+                }
+                "cpu_count" => {
+                    if let Some(count) = number(value, (0, PLAYER_ID_COUNT as u8)) {
+                        config.cpu_count = count;
+                    }
+                }
+                "difficulty" => {
+                    let all = in_game::Difficulty::ALL;
+                    if let Some(&difficulty) = all.iter().find(|d| d.label() == value) {
+                        config.difficulty = difficulty;
+                    }
+                }
+                "hand_size" => {
+                    let bounds = (in_game::Deal::MIN_HAND_SIZE, in_game::Deal::MAX_HAND_SIZE);
+                    if let Some(size) = number(value, bounds) {
+                        config.hand_size = size;
+                    }
+                }
+                "target_score" => {
+                    if let Some(score) = number(value, (0, Config::MAX_TARGET_SCORE)) {
+                        config.target_score = score;
+                    }
+                }
+                "seed" => {
+                    if value == "random" {
+                        config.seed = None;
+                    } else if let Some(seed) = parse_seed_string(value) {
+                        config.seed = Some(seed);
+                    }
+                }
+                "rule_types" => {
+                    let rule_types: Vec<bool> = value
+                        .split_whitespace()
+                        .filter_map(|v| v.parse().ok())
+                        .collect();
+                    if rule_types.len() == RULE_TYPES.len() {
+                        config.rule_types.copy_from_slice(&rule_types);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        config.fit_cpu_count();
+        config
+    }
+
+    /// The fewest and most cpu players there can be, given how many humans there are.
+    /// There have to be at least two players, and there are only so many seats.
+    pub fn cpu_count_bounds(&self) -> (u8, u8) {
+        let human_count = self.human_seats.count();
+        let max = PLAYER_ID_COUNT as u8 - human_count;
+        let min = 2u8.saturating_sub(human_count);
+        (min.min(max), max)
+    }
+
+    /// Changes `cpu_count` as little as possible to fit within `Config::cpu_count_bounds`.
+    pub fn fit_cpu_count(&mut self) {
+        let (min, max) = self.cpu_count_bounds();
+        self.cpu_count = self.cpu_count.clamp(min, max);
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: d!(),
+            hidden_rules: false,
+            rule_budget: 1,
+            // One of each kind of player: one who only looks out for themselves, one who
+            // does whatever, and one in between.
+            cpu_chaos: [
+                0,
+                Config::MAX_CHAOS / 2,
+                Config::MAX_CHAOS,
+                Config::MAX_CHAOS / 2,
+            ],
+            stalemate: d!(),
+            human_seats: d!(),
+            cpu_count: CPU_COUNT as u8,
+            difficulty: d!(),
+            hand_size: in_game::Deal::default().hand_sizes[0],
+            target_score: 0,
+            seed: None,
+            rule_types: [true; RULE_TYPES.len()],
+            today: 0,
+        }
+    }
+}
+
+/// Expands a seed number, which is short enough to type in, into a full seed.
+pub fn seed_from_number(number: u32) -> Seed {
+    let bytes = number.to_le_bytes();
+
+    let mut seed = [0; 16];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        // Mixing in the index keeps the parts of the seed from all being the same.
+        *byte = bytes[i % 4] ^ (i as u8).wrapping_mul(0x9d);
+    }
+    seed
+}
+
+/// The seed number for the daily challenge on `day`, counted from 1970-01-01.
+pub fn daily_seed_number(day: u32) -> u32 {
+    mix(day ^ 0xda11_5eed)
+}
+
+/// Writes `day`, counted from 1970-01-01, as a date like 2001-02-03.
+pub fn date_string(day: u32) -> String {
+    // From Howard Hinnant's `civil_from_days`, simplified since `day` is never negative.
+    let z = day + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day_of_month = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day_of_month)
+}
+
+/// The characters seed strings are written with, in order of value. This is Crockford's
+/// base 32, which leaves out letters that are easily mistaken for digits, or for each other.
+pub const SEED_ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+
+/// How many characters it takes to write any seed number.
+pub const SEED_STRING_LENGTH: usize = 7;
+
+/// Writes a seed number in a short form that is easy to read out and type back in.
+pub fn seed_string(number: u32) -> String {
+    (0..SEED_STRING_LENGTH)
+        .rev()
+        .map(|i| SEED_ALPHABET[(number >> (5 * i)) as usize & 0x1f] as char)
+        .collect()
+}
+
+/// Reads a seed number written by `seed_string`. Upper case, dashes, spaces, and the
+/// usual stand-ins for the left out letters are all accepted, so a seed copied by hand
+/// still reads the same.
+pub fn parse_seed_string(text: &str) -> Option<u32> {
+    let mut number: u64 = 0;
+    let mut length = 0;
+
+    for c in text.chars() {
+        let c = match c.to_ascii_lowercase() {
+            '-' | ' ' => continue,
+            'o' => '0',
+            'i' | 'l' => '1',
+            c => c,
+        };
+
+        let value = SEED_ALPHABET.iter().position(|&a| a as char == c)?;
+        number = (number << 5) | value as u64;
+        length += 1;
+
+        if number > u32::MAX as u64 {
+            return None;
+        }
+    }
+
+    if length == 0 {
+        None
+    } else {
+        Some(number as u32)
+    }
+}
+
+// The finalizer from MurmurHash3, which spreads each bit of the input over the output.
+fn mix(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    h
+}
+
+// Makes a seed for stream number `stream`, that has nothing obvious in common with the
+// seeds for the other streams made from the same seed.
+fn stream_seed(seed: Seed, stream: u32) -> Seed {
+    let mut output = [0; 16];
+    for (i, out) in output.chunks_mut(4).enumerate() {
+        let mut h = mix(stream.wrapping_mul(0x9e37_79b9) ^ i as u32);
+        for word in seed.chunks(4) {
+            h = mix(h ^ u32::from_le_bytes([word[0], word[1], word[2], word[3]]));
+        }
+        out.copy_from_slice(&h.to_le_bytes());
+    }
+    output
+}
+
+/// Separate random number generators, all made from the game's seed, so that what
+/// happens in one part of the game does not change what happens in the others. Most
+/// importantly, each deal depends only on the seed and the round number, so everyone
+/// playing from the same seed gets the same deals, however they play.
+#[derive(Clone)]
+pub struct Rngs {
+    seed: Seed,
+    /// Used when cpu players decide which card to play.
+    pub cpu: Xs,
+    /// Used to make rules, and to decide which rules cpu players make.
+    pub rules: Xs,
+    /// Used to shuffle the discard pile back into the deck, and to pick any cards that
+    /// rules move around at random.
+    pub reshuffle: Xs,
+}
+
+impl Rngs {
+    const CPU_STREAM: u32 = 0;
+    const RULES_STREAM: u32 = 1;
+    const RESHUFFLE_STREAM: u32 = 2;
+    const DEAL_STREAM: u32 = 3;
+
+    pub fn new(seed: Seed) -> Self {
+        Rngs {
+            seed,
+            cpu: xs::from_seed(stream_seed(seed, Rngs::CPU_STREAM)),
+            rules: xs::from_seed(stream_seed(seed, Rngs::RULES_STREAM)),
+            reshuffle: xs::from_seed(stream_seed(seed, Rngs::RESHUFFLE_STREAM)),
+        }
+    }
+
+    /// The generator round number `round` is dealt with. This is the same every time
+    /// it is asked for, so dealing a round again deals the same cards.
+    pub fn deal(&self, round: u32) -> Xs {
+        let deal_seed = stream_seed(self.seed, Rngs::DEAL_STREAM);
+        xs::from_seed(stream_seed(deal_seed, round))
+    }
+}
+
+/// Which page of the setup screen is showing, before a game starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SetupPage {
+    Main,
+    /// Choosing which types of rules can be made.
+    RuleTypes,
+    /// Entering a seed, with the number entered so far.
+    Seed { number: u32 },
+}
+
 pub struct GameState {
     pub in_game: in_game::State,
     pub choice: Choice,
     pub rules: Rules,
     pub status: Status,
     pub context: UIContext,
-    pub rng: Xs,
+    pub rngs: Rngs,
     pub event_log: EventLog,
     pub log_heading: LogHeading,
     pub log_height: u8,
     pub round_count: u32,
     pub show_rules: bool,
+    pub show_options: bool,
+    /// The page of the setup screen that is showing, if it is.
+    pub setup: Option<SetupPage>,
+    pub rules_info: Option<RulesInfo>,
+    pub rules_info_top: usize,
+    pub show_card_inspector: bool,
+    pub card_inspector_top: usize,
+    pub config: Config,
+    /// The statuses, and the choices in progress on them, that `Button::B` returns to
+    /// during rule selection. The current status is not included.
+    pub navigation: Vec<(Status, Choice)>,
+    pub pending_rule: Option<PendingRule>,
+    /// How many more rule changes the player can make before the next round starts.
+    pub rule_budget_left: u8,
+    /// Simulated rounds showing what difference `pending_rule` would make, if the player
+    /// asked for them.
+    pub rule_preview: Option<RulePreview>,
+    /// How many rounds each player has won this game, not counting the current round.
+    pub scores: [u32; PLAYER_ID_COUNT],
+    /// The seed number this game started from. See `seed_string`.
+    pub seed_number: u32,
+    /// The points the player has scored this game, not counting the current round. See
+    /// `GameState::round_points`.
+    pub points: u32,
+    /// The settings chosen on the setup screen, if the current game is using different
+    /// ones, as the daily challenge does.
+    pub chosen_config: Option<Config>,
+    /// The human the device should be passed to before their turn, while the screen that
+    /// asks for that is showing.
+    pub handoff: Option<PlayerID>,
+    /// The humans who won the last round and still have rules to make, in the order they
+    /// make them. The first one is making a rule now.
+    pub rule_makers: Vec<PlayerID>,
+    /// The controls for watching a game with no humans in it.
+    pub watch: Watch,
+    /// How likely the cpu players are to make each kind of rule.
+    pub weights: Weights,
+}
+
+// Deals round number `round`, with the settings from `config` that apply during a round.
+fn deal_round(rngs: &Rngs, rules: &Rules, round: u32, config: &Config) -> in_game::State {
+    let mut in_game = in_game::State::new(
+        &mut rngs.deal(round),
+        &rules.deal,
+        round,
+        config.human_seats,
+        config.cpu_count,
+    );
+    in_game.stalemate_resolution = config.stalemate;
+    in_game.difficulty = config.difficulty;
+    in_game
 }
 
 impl GameState {
-    pub fn new(seed: Seed) -> GameState {
-        GameState::new_with_previous(seed, d!(), d!(), d!(), 0, true)
+    pub fn new(seed: Seed, config: Config) -> GameState {
+        // We always want to log the seed, if there is a logger available, so use the function,
+        // not the macro.
+        log(&format!("{:?}", seed));
+
+        let rules = config.starting_rules();
+
+        GameState::new_with_previous(Rngs::new(seed), d!(), rules, d!(), 0, true, config)
     }
 
     pub fn new_with_previous(
-        seed: Seed,
+        rngs: Rngs,
         status: Status,
         rules: Rules,
         event_log: EventLog,
         round_count: u32,
         show_rules: bool,
+        config: Config,
     ) -> GameState {
-        // We always want to log the seed, if there is a logger available, so use the function,
-        // not the macro.
-        log(&format!("{:?}", seed));
-
-        let mut rng = xs::from_seed(seed);
+        // `round_count` is the number of the round that just ended, if any.
+        let in_game = deal_round(&rngs, &rules, round_count + 1, &config);
 
         GameState {
-            in_game: in_game::State::new(&mut rng),
+            in_game,
             choice: Choice::NoChoice,
             rules,
             status,
             context: UIContext::default(),
-            rng,
+            rngs,
             event_log,
             log_heading: LogHeading::Up,
             log_height: 0,
             round_count,
             show_rules,
+            show_options: false,
+            setup: None,
+            rules_info: None,
+            rules_info_top: 0,
+            show_card_inspector: false,
+            card_inspector_top: 0,
+            config,
+            navigation: Vec::new(),
+            pending_rule: None,
+            rule_budget_left: config.rule_budget,
+            rule_preview: None,
+            scores: [0; PLAYER_ID_COUNT],
+            seed_number: 0,
+            points: 0,
+            chosen_config: None,
+            handoff: None,
+            rule_makers: Vec::new(),
+            watch: d!(),
+            weights: Weights::bundled(),
         }
     }
 
+    /// Deals the upcoming round again, for when the deal has changed since it was dealt.
+    pub fn redeal(&mut self) {
+        self.in_game = deal_round(&self.rngs, &self.rules, self.round_count + 1, &self.config);
+    }
+
+    /// How many rounds each player has won, counting this one if it is over.
+    pub fn current_scores(&self) -> [u32; PLAYER_ID_COUNT] {
+        let mut scores = self.scores;
+        if self.in_game.round_has_ended() {
+            for &winner in self.winners() {
+                scores[winner as usize] += 1;
+            }
+        }
+        scores
+    }
+
+    /// The human making a rule now, if any are.
+    pub fn rule_maker(&self) -> PlayerID {
+        self.rule_makers.first().cloned().unwrap_or(PLAYER_ID)
+    }
+
+    /// Moves on to the next human who gets to make rules, if there is one, and returns
+    /// whether there was.
+    pub fn next_rule_maker(&mut self) -> bool {
+        if !self.rule_makers.is_empty() {
+            self.rule_makers.remove(0);
+        }
+
+        self.rule_budget_left = self.config.rule_budget;
+        !self.rule_makers.is_empty()
+    }
+
+    /// The points the player scored this round: if they won, one for each card left in
+    /// everyone else's hands.
+    pub fn round_points(&self) -> u32 {
+        if !self.in_game.round_has_ended() || !self.winners().contains(&PLAYER_ID) {
+            return 0;
+        }
+
+        self.in_game
+            .seated_player_ids()
+            .iter()
+            .filter(|&&player| player != PLAYER_ID)
+            .map(|&player| self.in_game.get_hand(player).len() as u32)
+            .sum()
+    }
+
+    /// The points the player has scored this game, counting this round if it is over.
+    pub fn current_points(&self) -> u32 {
+        self.points + self.round_points()
+    }
+
+    /// Whether the game has been decided, either by someone reaching the target score, or
+    /// by playing all the rounds of a daily challenge.
+    pub fn game_is_over(&self) -> bool {
+        let all_rounds_played = self.config.mode == GameMode::Daily
+            && self.in_game.round_has_ended()
+            && self.round_count >= GameMode::DAILY_ROUNDS;
+
+        all_rounds_played || !self.game_winners().is_empty()
+    }
+
+    /// A short summary of how the daily challenge went, for sharing.
+    pub fn daily_summary(&self) -> String {
+        let scores = self.current_scores();
+        format!(
+            "bartog daily {}: {}/{} rounds, {} points",
+            date_string(self.config.today),
+            scores[PLAYER_ID as usize],
+            GameMode::DAILY_ROUNDS,
+            self.current_points()
+        )
+    }
+
+    /// The players who have reached the target score, if there is one.
+    pub fn game_winners(&self) -> Vec<PlayerID> {
+        let target = self.config.target_score as u32;
+        if target == 0 {
+            return Vec::new();
+        }
+
+        let scores = self.current_scores();
+        all_player_ids()
+            .iter()
+            .cloned()
+            .filter(|&player| scores[player as usize] >= target)
+            .collect()
+    }
+
     pub fn winners(&self) -> &Vec<PlayerID> {
         &self.in_game.winners
     }
 
+    /// Moves to `status`, keeping the current status and choice so `pop_status` can
+    /// return to them.
+    pub fn push_status(&mut self, status: Status) {
+        let choice = self.choice.take();
+        self.navigation.push((self.status, choice));
+        self.status = status;
+    }
+
+    /// Returns to the previous status and its choice. Returns `false`, without changing
+    /// anything, if there is nothing to go back to.
+    pub fn pop_status(&mut self) -> bool {
+        match self.navigation.pop() {
+            Some((status, choice)) => {
+                self.status = status;
+                self.choice = choice;
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn start_new_round(&mut self) {
         self.status = Status::InGame;
+        self.navigation.clear();
+        self.pending_rule = None;
+        self.rule_preview = None;
 
         self.event_log.push_hr();
 
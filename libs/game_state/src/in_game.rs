@@ -15,56 +15,240 @@ pub struct State {
     pub top_wild_declared_as: Option<Suit>,
     pub winners: Vec<PlayerID>,
     pub card_animations: Vec<CardAnimation>,
+    /// Which cards were shuffled into the deck this round.
+    pub composition: DeckComposition,
+    /// How many seats, counting back from the last one, have no cpu player in them. The
+    /// humans sit in the last of these, and the rest are empty: no one is dealt in there,
+    /// and turns pass them by.
+    pub empty_seats: u8,
+    /// Which seats humans are sitting in.
+    pub human_seats: HumanSeats,
+    /// How well the cpu players play their cards.
+    pub difficulty: Difficulty,
+    /// What happens when no one can play or draw for a whole lap of turns.
+    pub stalemate_resolution: StalemateResolution,
+    /// How many turns in a row have passed without anyone playing or drawing a card.
+    pub idle_turns: u8,
+    /// How the last stalemate this round was resolved, if there was one.
+    pub stalemate: Option<StalemateResolution>,
+    /// The human whose hand is shown face up, at the bottom of the screen, if any. When
+    /// several humans share the screen, no one's hand is shown until it is passed to them.
+    pub viewer: Option<PlayerID>,
     // control state
     pub hand_index: u8,
 }
 
+/// Where `player`'s hand goes on the screen when `viewer` is sitting at the bottom.
+pub fn seat_spread(player: PlayerID, viewer: PlayerID) -> Spread {
+    let seats_from_viewer =
+        (player + PLAYER_ID_COUNT as PlayerID - viewer) % PLAYER_ID_COUNT as PlayerID;
+
+    match seats_from_viewer {
+        0 => Spread::LTR(TOP_AND_BOTTOM_HAND_EDGES, PLAYER_HAND_HEIGHT),
+        1 => Spread::TTB(LEFT_AND_RIGHT_HAND_EDGES, LEFT_CPU_HAND_X),
+        2 => Spread::LTR(TOP_AND_BOTTOM_HAND_EDGES, MIDDLE_CPU_HAND_HEIGHT),
+        _ => Spread::TTB(LEFT_AND_RIGHT_HAND_EDGES, RIGHT_CPU_HAND_X),
+    }
+}
+
 macro_rules! dealt_hand {
-    ($deck:expr, $spread:expr) => {{
+    ($deck:expr, $spread:expr, $size:expr) => {{
         let mut hand = Hand::new($spread);
 
-        hand.draw_from($deck);
-        hand.draw_from($deck);
-        hand.draw_from($deck);
-        hand.draw_from($deck);
-        hand.draw_from($deck);
+        for _ in 0..$size {
+            hand.draw_from($deck);
+        }
 
         hand
     }};
 }
 
-impl State {
-    pub fn new(rng: &mut Xs) -> Self {
-        let mut deck = Hand::new_shuffled_deck(rng);
+/// How the cards are dealt at the start of each round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Deal {
+    /// How many cards each player is dealt. Indexed by `PlayerID`.
+    pub hand_sizes: [u8; PLAYER_ID_COUNT],
+    /// Whether the top card of the deck is turned over to start the discard pile.
+    pub flip_starting_card: bool,
+    /// Whether the deal passes to the next player each round, with the player after the
+    /// dealer going first. Otherwise a random player goes first.
+    pub rotate_dealer: bool,
+    /// Which cards are shuffled into the deck.
+    pub composition: DeckComposition,
+}
+
+impl Default for Deal {
+    fn default() -> Self {
+        Deal {
+            hand_sizes: [5; PLAYER_ID_COUNT],
+            flip_starting_card: false,
+            rotate_dealer: false,
+            composition: d!(),
+        }
+    }
+}
+
+impl Deal {
+    pub const MIN_HAND_SIZE: u8 = 1;
+    pub const MAX_HAND_SIZE: u8 = 10;
+
+    /// Who deals `round`, if the deal rotates.
+    pub fn dealer(&self, round: u32) -> Option<PlayerID> {
+        if self.rotate_dealer {
+            Some((round % PLAYER_ID_COUNT as u32) as PlayerID)
+        } else {
+            None
+        }
+    }
+
+    /// Describes how the cards are dealt to the humans in `seats` and the cpu players,
+    /// one line per element.
+    pub fn describe(&self, seats: HumanSeats) -> Vec<String> {
+        let mut output = Vec::with_capacity(4);
+
+        let decks = match self.composition.decks {
+            1 => "one deck".to_owned(),
+            n => format!("{} decks", n),
+        };
+        output.push(if self.composition.jokers {
+            format!("the cards are {}, jokers included.", decks)
+        } else {
+            format!("the cards are {}, without jokers.", decks)
+        });
+
+        let first = self.hand_sizes[0];
+        if self.hand_sizes.iter().all(|&size| size == first) {
+            output.push(format!("each player is dealt {} cards.", first));
+        } else {
+            let sizes: Vec<_> = all_player_ids()
+                .iter()
+                .map(|&player| {
+                    format!(
+                        "{} {}",
+                        player_name(seats, player),
+                        self.hand_sizes[player as usize]
+                    )
+                })
+                .collect();
+            output.push(format!("players are dealt: {}.", sizes.join(", ")));
+        }
+
+        output.push(if self.flip_starting_card {
+            "a card is turned over to start the discard pile.".to_owned()
+        } else {
+            "the discard pile starts empty.".to_owned()
+        });
+
+        output.push(if self.rotate_dealer {
+            "the deal passes on each round, and the player after the dealer goes first.".to_owned()
+        } else {
+            "a random player goes first.".to_owned()
+        });
+
+        output
+    }
+
+    /// Describes the difference between `before` and `after`, one line per element.
+    pub fn describe_changes(before: &Deal, after: &Deal, seats: HumanSeats) -> Vec<String> {
+        let mut output = Vec::new();
+
+        if before.composition.decks != after.composition.decks {
+            output.push(format!(
+                "  {} -> {} decks",
+                before.composition.decks, after.composition.decks
+            ));
+        }
+
+        if before.composition.jokers != after.composition.jokers {
+            output.push(if after.composition.jokers {
+                "  + jokers are in the deck".to_owned()
+            } else {
+                "  - jokers are in the deck".to_owned()
+            });
+        }
+
+        for player in all_player_ids() {
+            let i = player as usize;
+            if before.hand_sizes[i] != after.hand_sizes[i] {
+                output.push(format!(
+                    "  {} is dealt {} -> {} cards",
+                    player_name(seats, player),
+                    before.hand_sizes[i],
+                    after.hand_sizes[i]
+                ));
+            }
+        }
+
+        if before.flip_starting_card != after.flip_starting_card {
+            output.push(if after.flip_starting_card {
+                "  + a card starts the discard pile".to_owned()
+            } else {
+                "  - a card starts the discard pile".to_owned()
+            });
+        }
+
+        if before.rotate_dealer != after.rotate_dealer {
+            output.push(if after.rotate_dealer {
+                "  + the deal rotates".to_owned()
+            } else {
+                "  - the deal rotates".to_owned()
+            });
+        }
 
-        let discard = Hand::new(Spread::stack(DISCARD_X, DISCARD_Y));
+        output
+    }
+}
+
+impl State {
+    /// Deals round number `round` according to `deal`, to the humans in `human_seats` and
+    /// `cpu_count` cpu players. The rest of the cpu players' seats are empty.
+    pub fn new(
+        rng: &mut Xs,
+        deal: &Deal,
+        round: u32,
+        human_seats: HumanSeats,
+        cpu_count: u8,
+    ) -> Self {
+        let mut deck = Hand::new_shuffled_deck(rng, deal.composition);
+
+        let mut discard = Hand::new(Spread::stack(DISCARD_X, DISCARD_Y));
+
+        // The humans sit from `PLAYER_ID` down, so the cpu players only get the seats
+        // below them, or all of them if there are no humans.
+        let cpu_count = (cpu_count as usize).min(PLAYER_ID_COUNT - human_seats.count() as usize);
+
+        // No one is dealt in to an empty seat.
+        let mut sizes = deal.hand_sizes;
+        for player in all_player_ids() {
+            if is_cpu_player(human_seats, player) && player as usize >= cpu_count {
+                sizes[player as usize] = 0;
+            }
+        }
 
         let hand = dealt_hand!(
             &mut deck,
-            Spread::LTR(TOP_AND_BOTTOM_HAND_EDGES, PLAYER_HAND_HEIGHT)
+            seat_spread(PLAYER_ID, PLAYER_ID),
+            sizes[PLAYER_ID as usize]
         );
 
         let cpu_hands = [
-            dealt_hand!(
-                &mut deck,
-                Spread::TTB(LEFT_AND_RIGHT_HAND_EDGES, LEFT_CPU_HAND_X)
-            ),
-            dealt_hand!(
-                &mut deck,
-                Spread::LTR(TOP_AND_BOTTOM_HAND_EDGES, MIDDLE_CPU_HAND_HEIGHT,)
-            ),
-            dealt_hand!(
-                &mut deck,
-                Spread::TTB(LEFT_AND_RIGHT_HAND_EDGES, RIGHT_CPU_HAND_X)
-            ),
+            dealt_hand!(&mut deck, seat_spread(0, PLAYER_ID), sizes[0]),
+            dealt_hand!(&mut deck, seat_spread(1, PLAYER_ID), sizes[1]),
+            dealt_hand!(&mut deck, seat_spread(2, PLAYER_ID), sizes[2]),
         ];
 
+        if deal.flip_starting_card {
+            discard.draw_from(&mut deck);
+        }
+
         //The player whose turn comes after this player will go first.
-        let current_player = xs::range(rng, 0..cpu_hands.len() as u32 + 1) as u8;
+        let current_player = deal
+            .dealer(round)
+            .unwrap_or_else(|| xs::range(rng, 0..cpu_hands.len() as u32 + 1) as u8);
 
         invariant_assert!(current_player <= cpu_hands.len() as u8);
 
-        let card_animations = Vec::with_capacity(DECK_SIZE as _);
+        let card_animations = Vec::with_capacity(deal.composition.size() as _);
 
         //We expect this to be replaced with a new vector when ever it it changed.
         let winners = Vec::with_capacity(0);
@@ -78,10 +262,40 @@ impl State {
             winners,
             top_wild_declared_as: None,
             card_animations,
+            composition: deal.composition,
+            empty_seats: (PLAYER_ID_COUNT - cpu_count) as u8,
+            human_seats,
+            difficulty: d!(),
+            stalemate_resolution: d!(),
+            idle_turns: 0,
+            stalemate: None,
+            viewer: if human_seats.count() == 1 {
+                Some(PLAYER_ID)
+            } else {
+                None
+            },
             hand_index: 0,
         }
     }
 
+    /// Shows `viewer`'s hand face up, at the bottom of the screen, with everyone else's
+    /// hands moved around to match, or hides every hand if `viewer` is `None`.
+    pub fn set_viewer(&mut self, viewer: Option<PlayerID>) {
+        if let Some(viewer) = viewer {
+            for player in all_player_ids() {
+                self.get_hand_mut(player).spread = seat_spread(player, viewer);
+            }
+        }
+
+        self.viewer = viewer;
+        self.hand_index = 0;
+    }
+
+    /// The hand the cursor is in, if anyone's hand is face up.
+    pub fn viewer_hand(&self) -> Option<&Hand> {
+        self.viewer.map(|viewer| self.get_hand(viewer))
+    }
+
     pub fn reshuffle_discard(&mut self, rng: &mut Xs) -> Option<()> {
         let top_card = self.discard.draw()?;
 
@@ -93,6 +307,54 @@ impl State {
         Some(())
     }
 
+    /// Records that a turn passed, and whether the player whose turn it was played or drew
+    /// a card. If no one has for a whole lap of turns, the round is stuck, so the stalemate
+    /// is resolved and the resolution returned.
+    pub fn end_turn(&mut self, acted: bool, rng: &mut Xs) -> Option<StalemateResolution> {
+        if acted {
+            self.idle_turns = 0;
+            return None;
+        }
+
+        self.idle_turns = self.idle_turns.saturating_add(1);
+
+        let seated = self.seated_player_ids();
+
+        if (self.idle_turns as usize) < seated.len() || !self.no_winners_yet() {
+            return None;
+        }
+
+        self.idle_turns = 0;
+
+        let resolution = self.stalemate_resolution;
+        match resolution {
+            StalemateResolution::Draw => {}
+            StalemateResolution::FewestCards => {
+                let fewest = seated
+                    .iter()
+                    .map(|&player| self.get_hand(player).len())
+                    .min()
+                    .unwrap_or(0);
+
+                self.winners = seated
+                    .iter()
+                    .filter(|&&player| self.get_hand(player).len() == fewest)
+                    .cloned()
+                    .collect();
+            }
+            StalemateResolution::Reshuffle => {
+                // With the top card gone, the next player can play anything, so the
+                // round can get moving again.
+                self.deck.fill(self.discard.drain());
+                self.deck.shuffle(rng);
+                self.top_wild_declared_as = None;
+            }
+        }
+        self.stalemate = Some(resolution);
+
+        Some(resolution)
+    }
+
     pub fn remove_positioned_card(
         &mut self,
         playerId: PlayerID,
@@ -131,10 +393,21 @@ impl State {
     }
 
     pub fn get_winner_text(&self) -> String {
+        let mut prefix = String::new();
+        match self.stalemate {
+            Some(StalemateResolution::Draw) if self.no_winners_yet() => {
+                return "stalemate! the round is a draw.".to_owned();
+            }
+            Some(StalemateResolution::FewestCards) => {
+                prefix.push_str("stalemate! fewest cards wins. ");
+            }
+            _ => {}
+        }
+
         let winner_names: Vec<_> = self
             .winners
             .iter()
-            .map(|&player| player_name(player))
+            .map(|&player| player_name(self.human_seats, player))
             .collect();
 
         let mut winner_text = get_sentence_list(&winner_names);
@@ -149,13 +422,15 @@ impl State {
 
         winner_text.push_str(suffix);
 
-        winner_text
+        prefix.push_str(&winner_text);
+
+        prefix
     }
 
     pub fn missing_cards(&self) -> Vec<Card> {
         use std::collections::BTreeSet;
 
-        let example_deck: BTreeSet<Card> = fresh_deck().into_iter().collect();
+        let example_deck: BTreeSet<Card> = fresh_deck(self.composition).into_iter().collect();
 
         let mut observed_deck = BTreeSet::new();
 
@@ -178,19 +453,78 @@ impl State {
         self.card_animations.is_empty()
     }
 
+    /// The player whose turn comes after the current player's.
+    pub fn incremented_current_player(&self) -> PlayerID {
+        self.next_seated_player(self.current_player)
+    }
+
+    /// Whether `player` is in the game, rather than in an empty seat.
+    pub fn is_seated(&self, player: PlayerID) -> bool {
+        !is_cpu_player(self.human_seats, player)
+            || (player as usize) + (self.empty_seats as usize) < PLAYER_ID_COUNT
+    }
+
+    pub fn seated_player_ids(&self) -> Vec<PlayerID> {
+        all_player_ids()
+            .iter()
+            .cloned()
+            .filter(|&player| self.is_seated(player))
+            .collect()
+    }
+
+    fn next_seated_player(&self, player: PlayerID) -> PlayerID {
+        for offset in 1..=PLAYER_ID_COUNT as PlayerID {
+            let next = (player + offset) % PLAYER_ID_COUNT as PlayerID;
+            if self.is_seated(next) {
+                return next;
+            }
+        }
+
+        invariant_violation!({ player }, "no one is seated")
+    }
+
+    /// The player `relative` to `player`, passing by any empty seats.
+    pub fn resolve(&self, relative: RelativePlayer, player: PlayerID) -> PlayerID {
+        let steps = match relative {
+            RelativePlayer::Same => 0,
+            RelativePlayer::Next => 1,
+            RelativePlayer::Across => 2,
+            RelativePlayer::Previous => self.seated_player_ids().len() - 1,
+        };
+
+        let mut output = player;
+        for _ in 0..steps {
+            output = self.next_seated_player(output);
+        }
+        output
+    }
+
+    /// The seated players who have run out of cards.
+    pub fn empty_handed_players(&self) -> Vec<PlayerID> {
+        self.seated_player_ids()
+            .into_iter()
+            .filter(|&player| self.get_hand(player).is_empty())
+            .collect()
+    }
+
     pub fn no_winners_yet(&self) -> bool {
         self.winners.is_empty()
     }
 
+    /// Whether someone won, or the round ended in a draw.
+    pub fn round_has_ended(&self) -> bool {
+        !self.no_winners_yet() || self.stalemate == Some(StalemateResolution::Draw)
+    }
+
     pub fn round_is_over(&self) -> bool {
-        !self.no_winners_yet() && self.animations_settled()
+        self.round_has_ended() && self.animations_settled()
     }
 
     pub fn get_new_card_position(&self, hand: RelativeHand, player: PlayerID) -> (u8, u8) {
         match hand {
             RelativeHand::Deck => (DECK_X, DECK_Y),
             RelativeHand::Discard => (DISCARD_X, DISCARD_Y),
-            RelativeHand::Player(p) => self.get_player_new_card_position(p.apply(player)),
+            RelativeHand::Player(p) => self.get_player_new_card_position(self.resolve(p, player)),
         }
     }
 
@@ -209,14 +543,86 @@ impl State {
         match hand {
             RelativeHand::Deck => &mut self.deck,
             RelativeHand::Discard => &mut self.discard,
-            RelativeHand::Player(p) => match p.apply(player) {
-                id if is_player(id) => &mut self.hand,
-                id => &mut self.cpu_hands[id as usize],
-            },
+            RelativeHand::Player(p) => {
+                let id = self.resolve(p, player);
+                self.get_hand_mut(id)
+            }
         }
     }
 }
 
+/// What happens when the deck and discard pile run out, and no one can play a card.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum StalemateResolution {
+    /// The round ends with no winners.
+    Draw,
+    /// The players with the fewest cards in their hands win.
+    #[default]
+    FewestCards,
+    /// The whole discard pile, top card included, is shuffled into the deck.
+    Reshuffle,
+}
+
+/// How well the cpu players play their cards.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    /// The cpu players play any card they can.
+    Easy,
+    /// The cpu players play whichever card looks best in a quick simulation.
+    #[default]
+    Normal,
+    /// As `Normal`, but the cpu players hold on to their wild cards when they can.
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&d| d == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let index = Self::ALL.iter().position(|&d| d == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
+impl StalemateResolution {
+    pub const ALL: [StalemateResolution; 3] = [
+        StalemateResolution::Draw,
+        StalemateResolution::FewestCards,
+        StalemateResolution::Reshuffle,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            StalemateResolution::Draw => "draw",
+            StalemateResolution::FewestCards => "fewest wins",
+            StalemateResolution::Reshuffle => "reshuffle",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&r| r == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn previous(self) -> Self {
+        let index = Self::ALL.iter().position(|&r| r == self).unwrap_or(0);
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Change {
     CurrentPlayer(RelativePlayer),
@@ -357,12 +763,15 @@ impl fmt::Display for RelativePlayer {
         }
         writeln!(f, "{:?}", self)?;
 
+        // Where each hand goes does not depend on who is sitting where, so this is shown
+        // as it would be for a lone human.
+        let seats = HumanSeats::default();
         for id in all_player_ids() {
             write!(
                 f,
                 "{}->{}",
-                player_1_char_name(id),
-                player_1_char_name(self.apply(id))
+                player_1_char_name(seats, id),
+                player_1_char_name(seats, self.apply(id))
             )?;
 
             if id != MAX_PLAYER_ID {
@@ -385,7 +794,8 @@ implement!(
 );
 
 //This relies on MAX_PLAYER_ID being 3, and will require structural changes if it changes!
-#[derive(Copy, Clone, PartialEq, Eq)]
+/// Defaults to no one.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub struct RelativePlayerSet(u8);
 
 const SAME_FLAG: u8 = 1;
@@ -459,8 +869,8 @@ impl Iterator for RelativePlayerSet {
 }
 
 impl RelativePlayerSet {
-    pub fn absolute_players(&self, player: PlayerID) -> Vec<PlayerID> {
-        self.map(|p| p.apply(player)).collect()
+    pub fn absolute_players(&self, state: &State, player: PlayerID) -> Vec<PlayerID> {
+        self.map(|p| state.resolve(p, player)).collect()
     }
 }
 
@@ -557,46 +967,60 @@ pub struct CardMovement {
     pub selection: CardSelection,
 }
 
+impl CardMovement {
+    /// The `(source, target)` pairs of hands that cards can be moved between.
+    pub fn hand_pairs() -> Vec<(RelativeHand, RelativeHand)> {
+        let hands = RelativeHand::all_values();
+
+        let mut output = Vec::with_capacity(hands.len() * hands.len());
+
+        for &source in hands.iter() {
+            for &target in hands.iter() {
+                if source == target {
+                    continue;
+                }
+
+                if loops_allowed!() {
+                    //allow all combinations
+                } else {
+                    if target == RelativeHand::Player(RelativePlayer::Same) {
+                        continue;
+                    }
+
+                    if source == RelativeHand::Discard && target != RelativeHand::Deck {
+                        continue;
+                    }
+
+                    if source == RelativeHand::Deck && target != RelativeHand::Discard {
+                        continue;
+                    }
+                }
+
+                output.push((source, target));
+            }
+        }
+
+        output
+    }
+}
+
 impl AllValues for CardMovement {
     fn all_values() -> Vec<CardMovement> {
         let sets = RelativePlayerSet::all_non_empty_values();
-        let hands = RelativeHand::all_values();
+        let hand_pairs = CardMovement::hand_pairs();
         let selections = CardSelection::all_values();
 
-        let mut output =
-            Vec::with_capacity(sets.len() * hands.len() * hands.len() * selections.len());
+        let mut output = Vec::with_capacity(sets.len() * hand_pairs.len() * selections.len());
 
         for selection in selections {
             for &affected in sets.iter() {
-                for &source in hands.iter() {
-                    for &target in hands.iter() {
-                        if source == target {
-                            continue;
-                        }
-
-                        if loops_allowed!() {
-                            //allow all combinations
-                        } else {
-                            if target == RelativeHand::Player(RelativePlayer::Same) {
-                                continue;
-                            }
-
-                            if source == RelativeHand::Discard && target != RelativeHand::Deck {
-                                continue;
-                            }
-
-                            if source == RelativeHand::Deck && target != RelativeHand::Discard {
-                                continue;
-                            }
-                        }
-
-                        output.push(CardMovement {
-                            affected,
-                            source,
-                            target,
-                            selection,
-                        });
-                    }
+                for &(source, target) in hand_pairs.iter() {
+                    output.push(CardMovement {
+                        affected,
+                        source,
+                        target,
+                        selection,
+                    });
                 }
             }
         }
@@ -710,28 +1134,22 @@ pub enum AbsoluteHand {
 }
 
 impl RelativeHand {
-    pub fn apply(self, player: PlayerID) -> AbsoluteHand {
+    pub fn apply(self, state: &State, player: PlayerID) -> AbsoluteHand {
         match self {
-            RelativeHand::Player(p) => AbsoluteHand::Player(p.apply(player)),
+            RelativeHand::Player(p) => AbsoluteHand::Player(state.resolve(p, player)),
             RelativeHand::Deck => AbsoluteHand::Deck,
             RelativeHand::Discard => AbsoluteHand::Discard,
         }
     }
 }
 
-impl fmt::Display for AbsoluteHand {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if f.alternate() {
-            return match *self {
-                AbsoluteHand::Player(p) => write!(f, "{}", player_1_char_name(p)),
-                AbsoluteHand::Deck => write!(f, "deck"),
-                AbsoluteHand::Discard => write!(f, "discard"),
-            };
-        }
-        match *self {
-            AbsoluteHand::Player(p) => write!(f, "{}", player_name(p)),
-            AbsoluteHand::Deck => write!(f, "the deck"),
-            AbsoluteHand::Discard => write!(f, "the discard pile"),
+impl AbsoluteHand {
+    /// What to call the hand, when the humans are sitting in `seats`.
+    pub fn name(self, seats: HumanSeats) -> String {
+        match self {
+            AbsoluteHand::Player(p) => player_name(seats, p),
+            AbsoluteHand::Deck => "the deck".to_owned(),
+            AbsoluteHand::Discard => "the discard pile".to_owned(),
         }
     }
 }
@@ -760,6 +1178,24 @@ pub struct ChoiceState {
     pub card_set: CardFlags,
     pub layer: Layer,
     pub description: Vec<u8>,
+    pub quick_select: QuickSelectState,
+}
+
+impl ChoiceState {
+    /// Returns whether the changes are the same as the ones already in the rules.
+    pub fn is_unchanged(&self) -> bool {
+        self.changes == self.reset_changes
+    }
+
+    /// Goes back to the layer before the current one. Returns `false` if there is no
+    /// previous layer to go back to.
+    pub fn previous_layer(&mut self) -> bool {
+        self.layer = match self.layer {
+            Layer::Card | Layer::Done => return false,
+            Layer::Changes => Layer::Card,
+        };
+        true
+    }
 }
 
 pub struct ChoiceStateAndRules<'a> {
@@ -774,6 +1210,14 @@ implement!(
 
 impl<'a> CardFlagsSubChoice for ChoiceStateAndRules<'a> {
     fn mark_done(&mut self) {
+        let changes: Vec<_> = self
+            .rules
+            .when_played
+            .get_card_flags_changes(self.choice_state.card_set)
+            .collect();
+
+        self.choice_state.reset_changes = changes.clone();
+        self.choice_state.changes = changes;
         self.choice_state.layer = Layer::Changes;
     }
     fn reset(&mut self) {
@@ -793,4 +1237,7 @@ impl<'a> CardFlagsSubChoice for ChoiceStateAndRules<'a> {
             bytes_to_status_line(if len == 1 { b"change. " } else { b"changes." }),
         ]
     }
+    fn quick_select_mut(&mut self) -> &mut QuickSelectState {
+        &mut self.choice_state.quick_select
+    }
 }
@@ -7,3 +7,7 @@ pub use self::game_state::*;
 pub mod can_play;
 
 pub mod in_game;
+
+pub mod diff;
+
+pub mod weights;
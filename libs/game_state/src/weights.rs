@@ -0,0 +1,209 @@
+//! The weights that decide which kinds of rules the cpu players tend to make. They are
+//! read from a data file with one `key = weight` pair per line, so they can be tuned
+//! without touching the code. See `rule_changes::generation` for how they are used.
+use crate::in_game::{self, CardMovement, RelativeHand};
+use crate::RULE_TYPES;
+use common::*;
+use std::sync::OnceLock;
+
+pub const BUNDLED_WEIGHTS: &str = include_str!("../weights.txt");
+/// The key that weights to use instead of `BUNDLED_WEIGHTS` are kept under in storage.
+pub const WEIGHTS_KEY: &str = "weights.txt";
+
+pub const RULE_TYPE_KEYS: [&str; 4] = ["can_play", "wild", "when_played", "deal"];
+pub const SET_SIZE_KEYS: [&str; 6] = ["single", "few", "some", "many", "most", "all"];
+pub const SET_KIND_KEYS: [&str; 2] = ["structured", "scattered"];
+pub const CHANGE_KEYS: [&str; 2] = ["turn", "movement"];
+pub const HAND_KIND_KEYS: [&str; 3] = ["player", "deck", "discard"];
+pub const AFFECTED_KEYS: [&str; 4] = ["1", "2", "3", "4"];
+pub const SELECTION_KEYS: [&str; 4] = ["1", "2", "3", "other"];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Weights {
+    pub rule_type: [u32; RULE_TYPES.len()],
+    pub set_size: [u32; 6],
+    pub set_kind: [u32; 2],
+    pub change: [u32; 2],
+    /// Indexed by the kinds of the source and target hands, in `HAND_KIND_KEYS` order.
+    pub movement: [[u32; 3]; 3],
+    pub affected: [u32; 4],
+    pub selection: [u32; 4],
+}
+
+impl Default for Weights {
+    /// Every option is equally likely.
+    fn default() -> Self {
+        Weights {
+            rule_type: [1; RULE_TYPES.len()],
+            set_size: [1; 6],
+            set_kind: [1; 2],
+            change: [1; 2],
+            movement: [[1; 3]; 3],
+            affected: [1; 4],
+            selection: [1; 4],
+        }
+    }
+}
+
+impl Weights {
+    /// The weights from the bundled data file.
+    pub fn bundled() -> Weights {
+        static BUNDLED: OnceLock<Weights> = OnceLock::new();
+
+        BUNDLED
+            .get_or_init(|| {
+                Weights::parse(BUNDLED_WEIGHTS).unwrap_or_else(|_e| {
+                    invariant_violation!({ Weights::default() }, "bad bundled weights: {}", _e)
+                })
+            })
+            .clone()
+    }
+
+    /// Parses lines of the form `group.option = weight`. Blank lines and text after a `#`
+    /// are ignored. Options that are not mentioned keep their default weight of 1.
+    pub fn parse(text: &str) -> Result<Weights, String> {
+        let mut weights = Weights::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => return Err(format!("line {}: expected `key = weight`", line_number)),
+            };
+
+            let weight: u32 = value
+                .parse()
+                .map_err(|_| format!("line {}: \"{}\" is not a weight", line_number, value))?;
+
+            match weights.slot(key) {
+                Some(slot) => *slot = weight,
+                None => return Err(format!("line {}: unknown key \"{}\"", line_number, key)),
+            }
+        }
+
+        weights.validate()?;
+
+        Ok(weights)
+    }
+
+    fn slot(&mut self, key: &str) -> Option<&mut u32> {
+        let (group, option) = key.split_once('.')?;
+
+        macro_rules! find {
+            ($keys:expr, $array:expr) => {
+                $keys
+                    .iter()
+                    .position(|&k| k == option)
+                    .map(move |i| &mut $array[i])
+            };
+        }
+
+        match group {
+            "rule_type" => find!(RULE_TYPE_KEYS, self.rule_type),
+            "set_size" => find!(SET_SIZE_KEYS, self.set_size),
+            "set_kind" => find!(SET_KIND_KEYS, self.set_kind),
+            "change" => find!(CHANGE_KEYS, self.change),
+            "affected" => find!(AFFECTED_KEYS, self.affected),
+            "selection" => find!(SELECTION_KEYS, self.selection),
+            "movement" => {
+                let (source, target) = option.split_once("_to_")?;
+                let source = HAND_KIND_KEYS.iter().position(|&k| k == source)?;
+                let target = HAND_KIND_KEYS.iter().position(|&k| k == target)?;
+                Some(&mut self.movement[source][target])
+            }
+            _ => None,
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        // The weight for turn changes only counts if there are any to make.
+        let first_change = if turn_changes_allowed() { 0 } else { 1 };
+
+        let groups: [(&str, u32); 7] = [
+            ("rule_type", self.rule_type.iter().sum()),
+            ("set_size", self.set_size.iter().sum()),
+            ("set_kind", self.set_kind.iter().sum()),
+            ("change", self.change[first_change..].iter().sum()),
+            ("affected", self.affected.iter().sum()),
+            ("selection", self.selection.iter().sum()),
+            (
+                "movement",
+                allowed_hand_kinds()
+                    .iter()
+                    .map(|&(s, t)| self.movement[s][t])
+                    .sum(),
+            ),
+        ];
+
+        for (group, total) in groups.iter() {
+            if *total == 0 {
+                return Err(format!("every option in \"{}\" has a weight of 0", group));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The index of the kind of `hand` in `HAND_KIND_KEYS`.
+pub fn hand_kind(hand: RelativeHand) -> usize {
+    match hand {
+        RelativeHand::Player(_) => 0,
+        RelativeHand::Deck => 1,
+        RelativeHand::Discard => 2,
+    }
+}
+
+// The kinds of hands that cards can be moved between, in the format used to index
+// `Weights::movement`.
+pub fn allowed_hand_kinds() -> &'static [(usize, usize)] {
+    static KINDS: OnceLock<Vec<(usize, usize)>> = OnceLock::new();
+
+    KINDS.get_or_init(|| {
+        let mut kinds: Vec<_> = CardMovement::hand_pairs()
+            .into_iter()
+            .map(|(s, t)| (hand_kind(s), hand_kind(t)))
+            .collect();
+        kinds.sort_unstable();
+        kinds.dedup();
+        kinds
+    })
+}
+
+/// Whether any of the changes a rule can make move the turn to another player.
+pub fn turn_changes_allowed() -> bool {
+    static ALLOWED: OnceLock<bool> = OnceLock::new();
+
+    *ALLOWED.get_or_init(|| {
+        in_game::ALL_CHANGES
+            .iter()
+            .any(|change| matches!(change, in_game::Change::CurrentPlayer(_)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_the_bundled_weights_parse() {
+        assert!(Weights::parse(BUNDLED_WEIGHTS).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        assert!(Weights::parse("set_size.enormous = 3").is_err());
+        assert!(Weights::parse("movement.deck_to_nowhere = 3").is_err());
+    }
+
+    #[test]
+    fn test_weights_that_rule_out_every_option_in_a_group_are_rejected() {
+        assert!(Weights::parse("change.turn = 0\nchange.movement = 0").is_err());
+        assert!(Weights::parse("set_kind.structured = 0\nset_kind.scattered = 0").is_err());
+    }
+}
@@ -129,9 +129,75 @@ pub const SPADE_CHAR: u8 = 28;
 pub const RANK_COUNT: u8 = 13;
 pub const SUIT_COUNT: u8 = 4;
 pub const DECK_SIZE: u8 = RANK_COUNT * SUIT_COUNT;
+pub const JOKER_COUNT: u8 = 2;
+/// How many different faces a card can have, counting the jokers. The rules only care
+/// about a card's face, so the copies of a card from each deck in play act the same.
+pub const FACE_COUNT: u8 = DECK_SIZE + JOKER_COUNT;
 
+pub const RED_JOKER: Card = DECK_SIZE;
+pub const BLACK_JOKER: Card = DECK_SIZE + 1;
+
+pub const JOKER_CHAR: u8 = b'*';
+
+/// A card's face is in the low bits, and which deck it came from is in the rest, so the
+/// copies of a card from different decks can be told apart.
 pub type Card = u8;
 
+const FACE_BITS: u8 = 6;
+const FACE_MASK: Card = (1 << FACE_BITS) - 1;
+
+pub fn get_face(card: Card) -> Card {
+    card & FACE_MASK
+}
+
+pub fn get_deck_index(card: Card) -> u8 {
+    card >> FACE_BITS
+}
+
+pub fn with_deck_index(face: Card, deck_index: u8) -> Card {
+    get_face(face) | (deck_index << FACE_BITS)
+}
+
+pub fn is_joker(card: Card) -> bool {
+    get_face(card) >= DECK_SIZE
+}
+
+/// Which cards make up the deck.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DeckComposition {
+    /// How many standard decks are shuffled together.
+    pub decks: u8,
+    /// Whether each deck comes with its two jokers.
+    pub jokers: bool,
+}
+
+impl Default for DeckComposition {
+    fn default() -> Self {
+        DeckComposition {
+            decks: 1,
+            jokers: false,
+        }
+    }
+}
+
+impl DeckComposition {
+    /// As many decks as there are ways to tell copies of a card apart.
+    pub const MAX_DECKS: u8 = 1 << (8 - FACE_BITS);
+
+    /// How many different faces the cards in the deck have.
+    pub fn face_count(&self) -> u8 {
+        if self.jokers {
+            FACE_COUNT
+        } else {
+            DECK_SIZE
+        }
+    }
+
+    pub fn size(&self) -> u8 {
+        self.decks.saturating_mul(self.face_count())
+    }
+}
+
 pub fn gen_cards(rng: &mut Xs, count: usize) -> Vec<Card> {
     let mut cards = Vec::with_capacity(count);
     for _ in 0..count {
@@ -145,7 +211,11 @@ pub fn gen_card(rng: &mut Xs) -> Card {
 }
 
 pub fn get_card_string(card: Card) -> String {
-    format!("{} of {}", get_rank_str(card), get_suit_str(get_suit(card)))
+    match get_face(card) {
+        RED_JOKER => "red joker".to_owned(),
+        BLACK_JOKER => "black joker".to_owned(),
+        _ => format!("{} of {}", get_rank_str(card), get_suit_str(get_suit(card))),
+    }
 }
 
 pub const RANK_SUIT_PAIR_WITH_IN_CHARS: u8 = 4;
@@ -153,7 +223,7 @@ pub const RANK_SUIT_PAIR_WITH_IN_CHARS: u8 = 4;
 pub fn get_suit_rank_pair(card: Card) -> String {
     let mut output = String::with_capacity(RANK_SUIT_PAIR_WITH_IN_CHARS as usize);
 
-    let (colour, suit_char) = get_suit_colour_and_char(get_suit(card));
+    let (colour, suit_char) = get_card_colour_and_suit_char(card);
 
     output.push(RANK_SUIT_PAIR_LAYOUT_CHAR as char);
     output.push(get_rank_char(card) as char);
@@ -164,7 +234,7 @@ pub fn get_suit_rank_pair(card: Card) -> String {
 }
 
 pub fn get_short_card_string_and_colour(card: Card) -> (String, u8) {
-    let (colour, ch) = get_suit_colour_and_char(get_suit(card));
+    let (colour, ch) = get_card_colour_and_suit_char(card);
 
     let mut output = String::with_capacity(2);
 
@@ -242,19 +312,21 @@ where
     }
 }
 
+/// The card `offset` cards after `current`, going round the faces in `composition`.
 #[inline]
-pub fn nth_next_card(current: Card, offset: u8) -> Card {
+pub fn nth_next_card(current: Card, offset: u8, composition: DeckComposition) -> Card {
     next_mod(ModOffset {
-        modulus: DECK_SIZE,
+        modulus: composition.face_count(),
         current,
         offset,
     })
 }
 
+/// The card `offset` cards before `current`, going round the faces in `composition`.
 #[inline]
-pub fn nth_previous_card(current: Card, offset: u8) -> Card {
+pub fn nth_previous_card(current: Card, offset: u8, composition: DeckComposition) -> Card {
     previous_mod(ModOffset {
-        modulus: DECK_SIZE,
+        modulus: composition.face_count(),
         current,
         offset,
     })
@@ -271,10 +343,22 @@ pub mod suits {
     pub const SPADES: Suit = 3;
 
     pub const ALL: [Suit; SUIT_COUNT as usize] = [CLUBS, DIAMONDS, HEARTS, SPADES];
+
+    /// What `get_suit` returns for the jokers.
+    pub const NONE: Suit = SUIT_COUNT;
 }
 
 pub fn get_suit(card: Card) -> Suit {
-    card / RANK_COUNT
+    get_face(card) / RANK_COUNT
+}
+
+/// The colour to draw `card` in, and the character that goes where its suit would.
+pub fn get_card_colour_and_suit_char(card: Card) -> (u8, u8) {
+    match get_face(card) {
+        RED_JOKER => (RED_INDEX, JOKER_CHAR),
+        BLACK_JOKER => (BLACK_INDEX, JOKER_CHAR),
+        _ => get_suit_colour_and_char(get_suit(card)),
+    }
 }
 
 pub fn get_suit_colour_and_char(suit: Suit) -> (u8, u8) {
@@ -319,10 +403,17 @@ pub mod ranks {
     pub const ALL: [Rank; RANK_COUNT as usize] = [
         ACE, TWO, THREE, FOUR, FIVE, SIX, SEVEN, EIGHT, NINE, TEN, JACK, QUEEN, KING,
     ];
+
+    /// What `get_rank` returns for the jokers.
+    pub const JOKER: Rank = RANK_COUNT;
 }
 
 pub fn get_rank(card: Card) -> Rank {
-    card % RANK_COUNT
+    if is_joker(card) {
+        ranks::JOKER
+    } else {
+        get_face(card) % RANK_COUNT
+    }
 }
 
 pub fn get_rank_char(card: Card) -> u8 {
@@ -344,6 +435,7 @@ pub fn get_rank_char_from_rank(rank: Rank) -> u8 {
         10 => b'j',
         11 => b'q',
         12 => b'k',
+        ranks::JOKER => JOKER_CHAR,
         _ => b'!',
     }
 }
@@ -363,6 +455,7 @@ pub fn get_rank_str(card: Card) -> &'static str {
         10 => "jack",
         11 => "queen",
         12 => "king",
+        ranks::JOKER => "joker",
         _ => "unknown rank",
     }
 }
@@ -379,6 +472,8 @@ pub type PlayerID = u8;
 pub const MAX_PLAYER_ID: PlayerID = 3;
 pub const PLAYER_ID_COUNT: usize = (MAX_PLAYER_ID + 1) as _;
 pub const PLAYER_ID: PlayerID = MAX_PLAYER_ID;
+/// The cpu players have the ids below `PLAYER_ID`.
+pub const CPU_COUNT: usize = PLAYER_ID as _;
 
 pub fn all_player_ids() -> [PlayerID; PLAYER_ID_COUNT] {
     let mut output = [0; PLAYER_ID_COUNT];
@@ -388,41 +483,85 @@ pub fn all_player_ids() -> [PlayerID; PLAYER_ID_COUNT] {
     output
 }
 
+/// The most humans that can play at once, taking turns on the same device.
+pub const MAX_HUMAN_COUNT: u8 = PLAYER_ID_COUNT as u8;
+
+/// Which seats humans are sitting in, as a bit for each `PlayerID`. The rest of the seats
+/// are for cpu players, so with no humans, every seat is, and the game can only be watched.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HumanSeats(u8);
+
+impl Default for HumanSeats {
+    fn default() -> Self {
+        HumanSeats::new(1)
+    }
+}
+
+impl HumanSeats {
+    /// Seats `count` humans, from `PLAYER_ID` downwards.
+    pub fn new(count: u8) -> Self {
+        let count = count.min(MAX_HUMAN_COUNT);
+        HumanSeats((0..count).fold(0, |seats, i| seats | 1 << (PLAYER_ID - i)))
+    }
+
+    pub fn count(self) -> u8 {
+        self.0.count_ones() as u8
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    #[inline]
+    pub fn contains(self, player_id: PlayerID) -> bool {
+        player_id < PLAYER_ID_COUNT as PlayerID && self.0 & (1 << player_id) != 0
+    }
+}
+
+#[inline]
+pub fn is_human_player(seats: HumanSeats, player_id: PlayerID) -> bool {
+    seats.contains(player_id)
+}
+
 #[inline]
-pub fn is_cpu_player(player_id: PlayerID) -> bool {
-    player_id < PLAYER_ID
+pub fn is_cpu_player(seats: HumanSeats, player_id: PlayerID) -> bool {
+    player_id < PLAYER_ID_COUNT as PlayerID && !seats.contains(player_id)
 }
 
 // Having invalid ids treated as player ids curruntly works fine most places, and it's nice if
 // `is_cpu_player` and `is_player` cover all the cases. we can make `is_strictly_player` or
 // something if we need to.
 #[inline]
-pub fn is_player(player_id: PlayerID) -> bool {
-    player_id >= PLAYER_ID
+pub fn is_player(seats: HumanSeats, player_id: PlayerID) -> bool {
+    !is_cpu_player(seats, player_id)
 }
 
-pub fn player_name(player_id: PlayerID) -> String {
-    if is_cpu_player(player_id) {
+// When there is only one human, they are "you". When several humans are taking turns,
+// "you" would be ambiguous, so everyone is named by their seat.
+pub fn player_name(seats: HumanSeats, player_id: PlayerID) -> String {
+    if is_cpu_player(seats, player_id) {
         format!("cpu {}", player_id)
-    } else if player_id == MAX_PLAYER_ID {
+    } else if player_id > MAX_PLAYER_ID {
+        "???".to_owned()
+    } else if seats.count() == 1 {
         "you".to_owned()
     } else {
-        "???".to_owned()
+        format!("player {}", player_id)
     }
 }
 
-pub fn player_1_char_name(player_id: PlayerID) -> String {
-    if is_cpu_player(player_id) {
-        format!("{}", player_id)
-    } else if player_id == MAX_PLAYER_ID {
+pub fn player_1_char_name(seats: HumanSeats, player_id: PlayerID) -> String {
+    if player_id > MAX_PLAYER_ID {
+        "?".to_owned()
+    } else if is_human_player(seats, player_id) && seats.count() == 1 {
         "u".to_owned()
     } else {
-        "?".to_owned()
+        format!("{}", player_id)
     }
 }
 
-pub fn get_pronoun(player_id: PlayerID) -> String {
-    if player_id == MAX_PLAYER_ID {
+pub fn get_pronoun(seats: HumanSeats, player_id: PlayerID) -> String {
+    if is_human_player(seats, player_id) && seats.count() == 1 {
         "you".to_string()
     } else {
         "they".to_string()
@@ -0,0 +1,50 @@
+// A bot that plays the first card it can, and otherwise draws. See the `bot` module for
+// how bots talk to the host.
+//
+// Usage: bartog --bot "cargo run -q -p net --example first_card_bot"
+use common::*;
+use net::{Move, Prompt, View};
+use std::io::{self, BufRead, Write};
+
+fn main() {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    let mut text = String::new();
+    for line in stdin.lock().lines().map_while(Result::ok) {
+        if line != View::END {
+            text.push_str(&line);
+            text.push('\n');
+            continue;
+        }
+
+        let view = View::parse(&text);
+        text.clear();
+
+        let player_move = match view.prompt {
+            Prompt::Play => match view.playable.first() {
+                Some(&index) => Move::Play(index),
+                None => Move::Draw,
+            },
+            Prompt::Suit => {
+                let mut counts = [0; SUIT_COUNT as usize];
+                for &card in view.hand.iter().filter(|&&card| !is_joker(card)) {
+                    counts[get_suit(card) as usize] += 1;
+                }
+                let most_common = (0..SUIT_COUNT).max_by_key(|&suit| counts[suit as usize]);
+                Move::Suit(most_common.unwrap_or(suits::CLUBS))
+            }
+            Prompt::Rule => Move::Rule(0),
+            Prompt::RoundOver => Move::Ready,
+            Prompt::Wait => continue,
+        };
+
+        if stdout
+            .write_all(player_move.to_text().as_bytes())
+            .and_then(|()| stdout.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
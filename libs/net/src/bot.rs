@@ -0,0 +1,83 @@
+//! Seats played by other programs, so bots can be written in any language.
+//!
+//! The host starts each bot as a separate process, and plays the bot's seat by talking to
+//! it over its standard input and output, with the same lines of text that are sent to
+//! and from players over the network:
+//!
+//! * Each time what the bot can see changes, the host writes a `View` to the bot's
+//!   standard input, as `key = value` lines ending with a line that says `end`. The keys
+//!   are the names of `View`'s fields, and the lists are separated by spaces. `option`,
+//!   `rule` and `log` lines can repeat, with one line per element.
+//! * Whenever the view's `prompt` is anything but `wait`, the bot should write one line
+//!   to its standard output with a `Move`, and the host will answer with a new view:
+//!   * `play` means it is the bot's turn, so it can send `play <index>`, with the index of
+//!     a card in its hand, or `draw`. The `playable` line lists the indexes that can be
+//!     played, unless the rules are secret.
+//!   * `suit` means the bot played a wild card, so it should send `suit <suit>`, where the
+//!     suit is `clubs`, `diamonds`, `hearts` or `spades`.
+//!   * `rule` means the bot won the round, so it should pick one of the `option`s with
+//!     `rule <index>`. The options are made the same way the cpu players make their rules.
+//!     Or it can make a rule change of its own with `make`, followed by one of these:
+//!     * `wild <cards>` makes exactly those cards wild.
+//!     * `can_play <cards> on <cards>` means each of the first cards can be played on
+//!       exactly the second ones. More of these can follow, separated by `;`.
+//!     * `when_played <cards> then <change>` sets what happens when any of the cards are
+//!       played. More `then <change>`s can follow. A change is `turn <player>`, to say
+//!       whose turn it is next, or `move <players> <from> <to> <n>`, where each of the
+//!       players, separated by commas, moves the `n`th card, counting from 1 and wrapping
+//!       around, from one hand to another. A player is `same`, `next`, `across` or
+//!       `previous`, from the point of view of whoever played the card, and a hand is a
+//!       player's hand, `deck` or `discard`.
+//!     * `deal <hand sizes> <decks>`, with a hand size for each seat, can be followed by
+//!       `flip`, to start the discard pile with the top card of the deck, `rotate`, to
+//!       pass the deal around, and `jokers`, to add the jokers to each deck.
+//!
+//!     If the host cannot read the rule change, or the game's settings do not allow that
+//!     type of rule, it answers with the same view, as it does for any other move that
+//!     cannot be made.
+//!   * `round over` means the host is waiting for the bot to send `ready`.
+//!
+//! Cards are numbers, as in the rest of the game. The lowest six bits are the card's
+//! face, and the rest say which deck it came from, when there is more than one. Faces
+//! from 0 to 51 are the suits in the order above, 13 ranks each starting with the ace,
+//! and 52 and 53 are the red and black jokers.
+//!
+//! Anything the bot writes to its standard error is passed along to the host's. If the
+//! bot exits, or takes longer than `Host::bot_timeout` to answer, a cpu player takes over
+//! its seat for the rest of the game.
+//!
+//! A bot plays a whole seat, like a person who joined over the network would, rather
+//! than standing in for the functions the cpu players decide with, like
+//! `simulation::cpu_would_play` and `add_cpu_rule` in `rule_changes`. That way, a bot only
+//! ever sees what its seat could see, and a slow or broken bot cannot stall anything
+//! but its own turns.
+//!
+//! See `examples/first_card_bot.rs` for a bot that plays the first card it can.
+use crate::Connection;
+use std::io;
+use std::process::{Child, Command, Stdio};
+
+/// Starts the bot `command`, which is a program followed by any arguments, separated by
+/// spaces.
+pub(crate) fn spawn(command: &str) -> io::Result<(Child, Connection)> {
+    let mut words = command.split_whitespace();
+    let program = words.next().ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, "a bot needs a program to run")
+    })?;
+
+    let mut child = Command::new(program)
+        .args(words)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let connection = match (child.stdout.take(), child.stdin.take()) {
+        (Some(stdout), Some(stdin)) => Connection::from_halves(stdout, stdin),
+        _ => {
+            let _ = child.kill();
+            return Err(io::Error::other("could not talk to the bot"));
+        }
+    };
+
+    Ok((child, connection))
+}
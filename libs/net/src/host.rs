@@ -0,0 +1,1010 @@
+use crate::{bot, with_default_port, Connection, Move, Prompt, View};
+use animations::{draw_on_turn, finish_turn, play_from_hand};
+use common::{xs::Seed, *};
+use game_state::{weights::Weights, Config, GameMode, GameState, PendingRule, Status};
+use rule_changes::{apply_rule, reset, rule_candidates, rule_type_enabled, start};
+use simulation::{settle_until_wild, take_cpu_turn};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many rules someone who won a round gets to pick between.
+pub const RULE_OPTION_COUNT: usize = 3;
+
+/// How many of the latest event log lines are sent with each view.
+pub const VIEW_LOG_LINES: usize = 64;
+
+/// The game as the host sees it. Moves from the humans come in through `HostGame::act`,
+/// and everything else, like the cpu players' turns, happens in `HostGame::step`.
+pub struct HostGame {
+    pub state: GameState,
+    /// Which human seats no one is sitting in, because no one has joined yet or they left.
+    /// The cpu players take those seats' turns.
+    pub absent: [bool; PLAYER_ID_COUNT],
+    // The suit each human declared for the wild card they are playing, if they have.
+    suits: [Option<Suit>; PLAYER_ID_COUNT],
+    // Which humans are ready for the next round.
+    ready: [bool; PLAYER_ID_COUNT],
+    // The rules the current rule maker can pick between.
+    candidates: Vec<PendingRule>,
+}
+
+impl HostGame {
+    pub fn new(seed: Seed, mut config: Config, weights: Weights) -> HostGame {
+        // The daily challenge is the same for everyone, so there is no one to play it with.
+        if config.mode == GameMode::Daily {
+            config.mode = GameMode::default();
+        }
+
+        let mut state = GameState::new(seed, config);
+        state.weights = weights;
+        start(&mut state);
+
+        HostGame {
+            state,
+            absent: [false; PLAYER_ID_COUNT],
+            suits: [None; PLAYER_ID_COUNT],
+            ready: [false; PLAYER_ID_COUNT],
+            candidates: Vec::new(),
+        }
+    }
+
+    /// The seats humans sit in, in the order they are filled.
+    pub fn human_seats(&self) -> Vec<PlayerID> {
+        all_player_ids()
+            .iter()
+            .rev()
+            .cloned()
+            .filter(|&player| is_human_player(self.state.in_game.human_seats, player))
+            .collect()
+    }
+
+    fn is_present(&self, player: PlayerID) -> bool {
+        is_human_player(self.state.in_game.human_seats, player) && !self.absent[player as usize]
+    }
+
+    // The human who played the wild card that is waiting for a suit, if there is one.
+    fn wild_player(&self) -> Option<PlayerID> {
+        self.state
+            .in_game
+            .card_animations
+            .iter()
+            .find_map(|animation| match animation.completion_action {
+                Action::SelectWild(player) if self.is_present(player) => Some(player),
+                _ => None,
+            })
+    }
+
+    /// Who the game is waiting on, if it is waiting on one player.
+    pub fn waiting_for(&self) -> Option<PlayerID> {
+        let state = &self.state;
+
+        if state.round_is_over() {
+            None
+        } else if state.status != Status::InGame {
+            Some(state.rule_maker())
+        } else if let Some(player) = self.wild_player() {
+            Some(player)
+        } else if state.in_game.round_has_ended() {
+            None
+        } else {
+            Some(state.in_game.incremented_current_player())
+        }
+    }
+
+    pub fn prompt(&self, player: PlayerID) -> Prompt {
+        let state = &self.state;
+
+        if !self.is_present(player) {
+            Prompt::Wait
+        } else if state.round_is_over() {
+            if self.ready[player as usize] {
+                Prompt::Wait
+            } else {
+                Prompt::RoundOver
+            }
+        } else if state.status != Status::InGame {
+            if state.rule_maker() == player {
+                Prompt::Rule
+            } else {
+                Prompt::Wait
+            }
+        } else if let Some(wild_player) = self.wild_player() {
+            if wild_player == player {
+                Prompt::Suit
+            } else {
+                Prompt::Wait
+            }
+        } else if state.animations_settled()
+            && !state.in_game.round_has_ended()
+            && state.in_game.incremented_current_player() == player
+        {
+            Prompt::Play
+        } else {
+            Prompt::Wait
+        }
+    }
+
+    fn options(&self, prompt: Prompt) -> Vec<String> {
+        let state = &self.state;
+
+        match prompt {
+            Prompt::Rule => self
+                .candidates
+                .iter()
+                .map(|rule| {
+                    state
+                        .rules
+                        .describe_pending(rule, state.in_game.human_seats)
+                        .join(" ")
+                })
+                .collect(),
+            Prompt::RoundOver => {
+                let scores = state.current_scores();
+
+                let mut options = vec![state.in_game.get_winner_text()];
+                for player in state.in_game.seated_player_ids() {
+                    let score = scores[player as usize];
+                    options.push(format!(
+                        "{}: {} win{}",
+                        player_name(state.in_game.human_seats, player),
+                        score,
+                        if score == 1 { "" } else { "s" }
+                    ));
+                }
+                if state.game_is_over() {
+                    options
+                        .push("game over! the next game starts when everyone is ready.".to_owned());
+                }
+                options
+            }
+            Prompt::Wait | Prompt::Play | Prompt::Suit => Vec::new(),
+        }
+    }
+
+    /// What `player` can see of the game.
+    pub fn view(&self, player: PlayerID) -> View {
+        let in_game = &self.state.in_game;
+        let prompt = self.prompt(player);
+
+        let mut hand_sizes = [0; PLAYER_ID_COUNT];
+        for (size, &id) in hand_sizes.iter_mut().zip(all_player_ids().iter()) {
+            *size = in_game.get_hand(id).len();
+        }
+
+        let hand = in_game.get_hand(player);
+        let playable = if self.state.config.hidden_rules {
+            Vec::new()
+        } else {
+            (0..hand.len())
+                .filter(|&index| {
+                    hand.get(index)
+                        .is_some_and(|&card| self.state.rules.can_play(in_game, card))
+                })
+                .collect()
+        };
+
+        let log: Vec<String> = self
+            .state
+            .event_log
+            .iter()
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect();
+
+        View {
+            seat: player,
+            human_count: self.state.in_game.human_seats.count(),
+            prompt,
+            waiting_for: self.waiting_for(),
+            hand: hand.iter().cloned().collect(),
+            playable,
+            hand_sizes,
+            top: in_game.discard.last().cloned(),
+            declared: in_game.top_wild_declared_as,
+            deck_size: in_game.deck.len(),
+            discard_size: in_game.discard.len(),
+            options: self.options(prompt),
+            rules: self
+                .state
+                .rules
+                .get_reference(self.state.in_game.human_seats),
+            log: log[log.len().saturating_sub(VIEW_LOG_LINES)..].to_vec(),
+        }
+    }
+
+    /// Does what `player` asked, if it is something they can do right now. Returns whether
+    /// anything changed.
+    pub fn act(&mut self, player: PlayerID, player_move: Move) -> bool {
+        match (self.prompt(player), player_move) {
+            (Prompt::Play, Move::Play(index)) => {
+                match play_from_hand(&mut self.state, player, index) {
+                    Some(acted) => {
+                        finish_turn(&mut self.state, Some(acted));
+                        true
+                    }
+                    None => false,
+                }
+            }
+            (Prompt::Play, Move::Draw) => {
+                let acted = draw_on_turn(&mut self.state, player);
+                finish_turn(&mut self.state, Some(acted));
+                true
+            }
+            (Prompt::Suit, Move::Suit(suit)) => {
+                self.suits[player as usize] = Some(suit);
+                self.settle()
+            }
+            (Prompt::Rule, Move::Rule(index)) => match self.candidates.get(index as usize) {
+                Some(rule) => {
+                    let rule = rule.clone();
+                    self.make_rule(player, rule);
+                    true
+                }
+                None => false,
+            },
+            // Only the types of rules the game allows can be made.
+            (Prompt::Rule, Move::Make(rule)) if rule_type_enabled(&self.state, rule.status()) => {
+                self.make_rule(player, rule);
+                true
+            }
+            (Prompt::RoundOver, Move::Ready) => {
+                self.ready[player as usize] = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the next thing `HostGame::step` will do is take a cpu player's turn.
+    pub fn cpu_is_next(&self) -> bool {
+        let state = &self.state;
+        let in_game = &state.in_game;
+
+        state.status == Status::InGame
+            && state.animations_settled()
+            && !in_game.round_has_ended()
+            && !self.is_present(in_game.incremented_current_player())
+    }
+
+    /// Does the next thing that does not need a human to do it, if there is one. Returns
+    /// whether anything changed.
+    pub fn step(&mut self) -> bool {
+        if self.state.round_is_over() {
+            // Once the game is decided, there is nothing left to keep secret.
+            if self.state.game_is_over() && !self.state.rules.secrets.is_empty() {
+                let seats = self.state.in_game.human_seats;
+                self.state
+                    .rules
+                    .secrets
+                    .reveal(&mut self.state.event_log, seats);
+                return true;
+            }
+
+            let everyone_is_ready = self
+                .human_seats()
+                .iter()
+                .all(|&player| self.ready[player as usize] || !self.is_present(player));
+            if !everyone_is_ready {
+                return false;
+            }
+
+            self.ready = [false; PLAYER_ID_COUNT];
+            if self.state.game_is_over() {
+                if let Some(config) = self.state.chosen_config.take() {
+                    self.state.config = config;
+                }
+                start(&mut self.state);
+            } else {
+                reset(&mut self.state);
+            }
+            return true;
+        }
+
+        if self.state.status != Status::InGame {
+            if self.candidates.is_empty() {
+                self.candidates = rule_candidates(&mut self.state, RULE_OPTION_COUNT);
+                return true;
+            }
+
+            let player = self.state.rule_maker();
+            if !self.is_present(player) {
+                let rule = self.candidates[0].clone();
+                self.make_rule(player, rule);
+                return true;
+            }
+
+            return false;
+        }
+
+        if !self.state.animations_settled() {
+            return self.settle();
+        }
+
+        if self.cpu_is_next() {
+            let player = self.state.in_game.incremented_current_player();
+            let acted = take_cpu_turn(&mut self.state, player);
+            finish_turn(&mut self.state, Some(acted));
+            return true;
+        }
+
+        false
+    }
+
+    fn make_rule(&mut self, player: PlayerID, rule: PendingRule) {
+        let state = &mut self.state;
+
+        apply_rule(state, rule, player);
+        self.candidates.clear();
+
+        state.rule_budget_left = state.rule_budget_left.saturating_sub(1);
+        if state.rule_budget_left == 0 && !state.next_rule_maker() {
+            state.start_new_round();
+        }
+    }
+
+    // Completes the card animations all at once, since no one is watching them here.
+    // Returns whether any were completed.
+    fn settle(&mut self) -> bool {
+        let present = all_player_ids().map(|player| self.is_present(player));
+        let suits = &mut self.suits;
+        let state = &mut self.state;
+
+        let settled_any = settle_until_wild(
+            &mut state.in_game,
+            &state.rules,
+            &mut state.rngs.reshuffle,
+            &mut Some(&mut state.event_log),
+            |in_game, player| {
+                if present[player as usize] {
+                    suits[player as usize].take().map(Some)
+                } else {
+                    Some(in_game.get_hand(player).most_common_suit())
+                }
+            },
+        );
+
+        // A rule that moves cards around can empty someone's hand.
+        finish_turn(&mut self.state, None);
+
+        settled_any
+    }
+}
+
+/// How long a bot has to answer before a cpu player takes over its seat, by default.
+pub const DEFAULT_BOT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Hosts a game, seating each player who joins, and each bot, in one of the human seats.
+pub struct Host {
+    // `None` when the game is only for bots, so no one can join.
+    listener: Option<TcpListener>,
+    pub game: HostGame,
+    connections: Vec<Option<Connection>>,
+    // The bots' processes, by seat, which are stopped along with the host.
+    bots: Vec<Option<Child>>,
+    // When each seat was asked for a move that has not come in yet.
+    asked_at: [Option<Instant>; PLAYER_ID_COUNT],
+    /// How long a bot can take to answer before a cpu player takes over its seat.
+    pub bot_timeout: Duration,
+    // The last view sent to each seat, so unchanged views are not sent again.
+    sent: Vec<String>,
+    /// How long the cpu players wait before each turn, so the humans can follow along.
+    pub cpu_delay: Duration,
+    last_cpu_turn: Instant,
+    // Whether everyone has joined at some point, after which the game goes on without
+    // anyone who leaves.
+    started: bool,
+}
+
+impl Host {
+    /// Starts listening on `address`, for a game with the settings in `config`. The cpu
+    /// players fill any seats the humans do not, and make rules with `weights`.
+    pub fn bind(address: &str, seed: Seed, config: Config, weights: Weights) -> io::Result<Host> {
+        let listener = TcpListener::bind(with_default_port(address))?;
+        listener.set_nonblocking(true)?;
+
+        let mut host = Host::local(seed, config, weights);
+        host.listener = Some(listener);
+        Ok(host)
+    }
+
+    /// A host that no one can join, so its human seats can only be played by bots.
+    pub fn local(seed: Seed, mut config: Config, weights: Weights) -> Host {
+        config.fit_cpu_count();
+        config.cpu_count = config.cpu_count_bounds().1;
+
+        let mut game = HostGame::new(seed, config, weights);
+        for player in game.human_seats() {
+            game.absent[player as usize] = true;
+        }
+
+        Host {
+            listener: None,
+            game,
+            connections: (0..PLAYER_ID_COUNT).map(|_| None).collect(),
+            bots: (0..PLAYER_ID_COUNT).map(|_| None).collect(),
+            asked_at: [None; PLAYER_ID_COUNT],
+            bot_timeout: DEFAULT_BOT_TIMEOUT,
+            sent: vec![String::new(); PLAYER_ID_COUNT],
+            cpu_delay: Duration::ZERO,
+            last_cpu_turn: Instant::now(),
+            started: false,
+        }
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        match self.listener.as_ref() {
+            Some(listener) => listener.local_addr(),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "the host is not listening",
+            )),
+        }
+    }
+
+    /// How many of the human seats have someone in them.
+    pub fn player_count(&self) -> usize {
+        self.connections.iter().filter(|c| c.is_some()).count()
+    }
+
+    /// Starts the bot `command` and seats it in the first free human seat. See the `bot`
+    /// module for how the host talks to it.
+    pub fn add_bot(&mut self, command: &str) -> io::Result<PlayerID> {
+        let seat = self.free_seat().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "there is no seat for the bot")
+        })?;
+
+        let (child, connection) = bot::spawn(command)?;
+        self.bots[seat as usize] = Some(child);
+
+        self.seat(seat, connection);
+        self.game.state.event_log.push(
+            format!(
+                "{} is played by a bot.",
+                player_name(self.game.state.in_game.human_seats, seat)
+            )
+            .as_bytes(),
+        );
+
+        Ok(seat)
+    }
+
+    fn free_seat(&self) -> Option<PlayerID> {
+        self.game
+            .human_seats()
+            .into_iter()
+            .find(|&player| self.connections[player as usize].is_none())
+    }
+
+    fn seat(&mut self, seat: PlayerID, connection: Connection) {
+        self.connections[seat as usize] = Some(connection);
+        self.sent[seat as usize].clear();
+        self.asked_at[seat as usize] = None;
+        self.game.absent[seat as usize] = false;
+    }
+
+    // Empties `seat`, for a cpu player to take over, saying `why` in the log.
+    fn unseat(&mut self, seat: PlayerID, why: &str) {
+        self.connections[seat as usize] = None;
+        self.game.absent[seat as usize] = true;
+        if let Some(mut bot) = self.bots[seat as usize].take() {
+            // The bot may have already exited, which is fine.
+            let _ = bot.kill();
+            let _ = bot.wait();
+        }
+
+        self.game.state.event_log.push(
+            format!(
+                "{} {}. a cpu player takes over.",
+                player_name(self.game.state.in_game.human_seats, seat),
+                why
+            )
+            .as_bytes(),
+        );
+    }
+
+    /// Seats anyone who has joined, applies any moves that have come in, moves the game
+    /// along, and sends everyone their view of the result.
+    pub fn update(&mut self) {
+        let mut changed = [false; PLAYER_ID_COUNT];
+
+        self.accept();
+
+        for player in all_player_ids() {
+            let connection = match self.connections[player as usize].as_mut() {
+                Some(connection) => connection,
+                None => continue,
+            };
+
+            for line in connection.receive() {
+                if let Some(player_move) = Move::parse(&line) {
+                    self.game.act(player, player_move);
+                }
+                // Everyone who sends something gets an answer, even if nothing changed,
+                // so they know to try something else.
+                changed[player as usize] = true;
+                self.asked_at[player as usize] = None;
+            }
+
+            // People can take as long as they like, but a bot that stops answering
+            // would hold everyone up.
+            let is_open = connection.is_open;
+            let timed_out = self.bots[player as usize].is_some()
+                && self.asked_at[player as usize]
+                    .is_some_and(|asked_at| asked_at.elapsed() > self.bot_timeout);
+
+            if !is_open {
+                self.unseat(player, "left");
+            } else if timed_out {
+                self.unseat(player, "took too long");
+            }
+        }
+
+        // The game waits until everyone has joined, and after that the cpu players play
+        // for anyone who has left.
+        if self.player_count() == self.game.human_seats().len() {
+            self.started = true;
+        }
+
+        if self.started {
+            // There is only so much to do at once, but this makes sure it cannot go forever.
+            for _ in 0..DECK_SIZE {
+                if self.game.cpu_is_next() {
+                    if self.last_cpu_turn.elapsed() < self.cpu_delay {
+                        break;
+                    }
+                    self.last_cpu_turn = Instant::now();
+                }
+
+                if !self.game.step() {
+                    break;
+                }
+            }
+        }
+
+        for player in all_player_ids() {
+            if let Some(connection) = self.connections[player as usize].as_mut() {
+                let view = self.game.view(player);
+                let text = view.to_text();
+                if changed[player as usize] || text != self.sent[player as usize] {
+                    connection.send(&text);
+                    self.sent[player as usize] = text;
+                }
+
+                let asked_at = &mut self.asked_at[player as usize];
+                if view.prompt == Prompt::Wait {
+                    *asked_at = None;
+                } else if asked_at.is_none() {
+                    *asked_at = Some(Instant::now());
+                }
+            }
+        }
+    }
+
+    fn accept(&mut self) {
+        loop {
+            let accepted = match self.listener.as_ref() {
+                Some(listener) => listener.accept(),
+                None => return,
+            };
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => {
+                    log(&format!("could not accept a player: {}", e));
+                    break;
+                }
+            };
+
+            // Anyone who joins a full game is disconnected straight away.
+            let (seat, connection) = match (self.free_seat(), Connection::new(stream)) {
+                (Some(seat), Ok(connection)) => (seat, connection),
+                _ => continue,
+            };
+
+            self.seat(seat, connection);
+            self.game.state.event_log.push(
+                format!(
+                    "{} joined.",
+                    player_name(self.game.state.in_game.human_seats, seat)
+                )
+                .as_bytes(),
+            );
+        }
+    }
+}
+
+impl Drop for Host {
+    fn drop(&mut self) {
+        for bot in self.bots.iter_mut().flatten() {
+            // The bot may have already exited, which is fine.
+            let _ = bot.kill();
+            let _ = bot.wait();
+        }
+    }
+}
+
+/// How long `serve` waits between updates.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Hosts a game until the process is stopped, with a seat for each of the `bots`
+/// commands. Players can join on `address`, if there is one, and otherwise the game is
+/// only for the bots. See `Host::bind`, `Host::local` and `Host::add_bot`.
+pub fn serve(
+    address: Option<&str>,
+    seed: Seed,
+    config: Config,
+    weights: Weights,
+    bots: &[String],
+) -> io::Result<()> {
+    let mut host = match address {
+        Some(address) => Host::bind(address, seed, config, weights)?,
+        None => Host::local(seed, config, weights),
+    };
+    host.cpu_delay = Duration::from_millis(600);
+
+    if address.is_some() {
+        println!(
+            "hosting on {}, for {} player(s).",
+            host.local_addr()?,
+            host.game.human_seats().len().saturating_sub(bots.len())
+        );
+    }
+
+    for command in bots.iter() {
+        let seat = host.add_bot(command)?;
+        let name = player_name(host.game.state.in_game.human_seats, seat);
+        println!("{} is played by `{}`.", name, command);
+    }
+
+    let mut log_len = 0;
+    loop {
+        host.update();
+
+        // The host's window onto the game is the event log.
+        let event_log = &host.game.state.event_log;
+        // A new game starts a new log.
+        if event_log.len() < log_len {
+            log_len = 0;
+        }
+        for line in event_log.iter().skip(log_len) {
+            println!("{}", String::from_utf8_lossy(line));
+        }
+        log_len = event_log.len();
+
+        // Once the log is full, new lines push old ones out, so they could not be told
+        // apart from lines that were already printed.
+        if host.game.state.event_log.is_full() {
+            host.game.state.event_log.clear();
+            log_len = 0;
+        }
+
+        thread::sleep(UPDATE_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use game_state::{Provenance, SecretRule};
+
+    // Plays like someone who does not know the rules: tries each card in turn, then draws.
+    fn respond(client: &mut Client, view: &View, tries: &mut u8) {
+        match view.prompt {
+            Prompt::Play => {
+                if (*tries as usize) < view.hand.len() {
+                    client.send(Move::Play(*tries));
+                    *tries += 1;
+                } else {
+                    client.send(Move::Draw);
+                    *tries = 0;
+                }
+            }
+            Prompt::Suit => client.send(Move::Suit(suits::HEARTS)),
+            Prompt::Rule => client.send(Move::Rule(view.options.len() as u8 - 1)),
+            Prompt::RoundOver => client.send(Move::Ready),
+            Prompt::Wait => *tries = 0,
+        }
+    }
+
+    #[test]
+    fn test_the_secret_rules_are_revealed_when_the_game_is_over() {
+        let config = Config {
+            human_seats: HumanSeats::new(0),
+            hidden_rules: true,
+            target_score: 1,
+            ..Config::default()
+        };
+        let mut game = HostGame::new([52; 16], config, Weights::bundled());
+        game.state.rules.secrets.rules.push(SecretRule {
+            provenance: Provenance {
+                author: 0,
+                round: 0,
+                number: 1,
+            },
+            lines: vec![b"cpu 0 made jacks wild.".to_vec()],
+        });
+
+        // The first round decides the game.
+        while !game.state.round_is_over() {
+            assert!(game.step(), "the round stopped before it was over");
+            assert!(!game.state.rules.secrets.is_empty());
+        }
+        assert!(game.state.game_is_over());
+        assert!(game.step());
+
+        assert!(game.state.rules.secrets.is_empty());
+        let log: Vec<&[u8]> = game.state.event_log.iter().collect();
+        assert!(log.contains(&&b"the secret rules were:"[..]));
+        assert!(log.contains(&&b"cpu 0 made jacks wild."[..]));
+    }
+
+    #[test]
+    fn test_the_rule_maker_can_state_a_rule_change_of_their_own() {
+        let config = Config {
+            human_seats: HumanSeats::new(1),
+            rule_types: [false, true, false, false],
+            ..Config::default()
+        };
+        let mut game = HostGame::new([53; 16], config, Weights::bundled());
+        let player = game.human_seats()[0];
+        game.state.status = Status::RuleSelection;
+        game.state.rule_makers = vec![player];
+        assert_eq!(game.prompt(player), Prompt::Rule);
+
+        assert!(!game.act(player, Move::Make(PendingRule::Deal(d!()))));
+
+        let wild = CardFlags::new(RANK_FLAGS[6]);
+        assert!(game.act(player, Move::Make(PendingRule::Wild(wild))));
+        assert_eq!(game.state.rules.wild, wild);
+    }
+
+    #[test]
+    fn test_two_players_on_localhost_can_play_through_rounds_with_a_cpu_player() {
+        let config = Config {
+            human_seats: HumanSeats::new(2),
+            ..Config::default()
+        };
+        let mut host = Host::bind("127.0.0.1:0", [48; 16], config, Weights::bundled()).unwrap();
+        let address = host.local_addr().unwrap().to_string();
+
+        let mut clients = [
+            Client::connect(&address).unwrap(),
+            Client::connect(&address).unwrap(),
+        ];
+        let mut tries = [0; 2];
+        let mut views: Vec<Option<View>> = vec![None, None];
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while host.game.state.round_count < 3 {
+            assert!(
+                Instant::now() < deadline,
+                "the rounds did not finish in time"
+            );
+
+            host.update();
+
+            for (i, client) in clients.iter_mut().enumerate() {
+                if let Some(view) = client.poll() {
+                    respond(client, &view, &mut tries[i]);
+                    views[i] = Some(view);
+                }
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // The two humans sit below the player's usual seat, and the cpu players fill the
+        // rest.
+        let seats: Vec<PlayerID> = views.iter().flatten().map(|view| view.seat).collect();
+        assert_eq!(seats, vec![PLAYER_ID, PLAYER_ID - 1]);
+        assert_eq!(
+            host.game.state.in_game.seated_player_ids().len(),
+            PLAYER_ID_COUNT
+        );
+
+        // Each player is sent their own cards, and only how many everyone else has.
+        for &seat in seats.iter() {
+            let view = host.game.view(seat);
+            let hand: Vec<Card> = host
+                .game
+                .state
+                .in_game
+                .get_hand(seat)
+                .iter()
+                .cloned()
+                .collect();
+
+            assert_eq!(view.hand, hand);
+            assert_eq!(view.to_text().matches("hand = ").count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_the_game_goes_on_when_a_player_leaves_in_the_middle_of_a_round() {
+        let config = Config {
+            human_seats: HumanSeats::new(2),
+            ..Config::default()
+        };
+        let mut host = Host::bind("127.0.0.1:0", [50; 16], config, Weights::bundled()).unwrap();
+        let address = host.local_addr().unwrap().to_string();
+
+        let mut staying = Client::connect(&address).unwrap();
+        let mut leaving = Some(Client::connect(&address).unwrap());
+        let mut tries = [0; 2];
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        let mut left_during = None;
+        while left_during.is_none_or(|round| host.game.state.round_count < round + 2) {
+            assert!(
+                Instant::now() < deadline,
+                "the rounds did not finish after a player left"
+            );
+
+            host.update();
+
+            if let Some(view) = staying.poll() {
+                respond(&mut staying, &view, &mut tries[0]);
+            }
+
+            if let Some(client) = leaving.as_mut() {
+                if let Some(view) = client.poll() {
+                    if view.prompt == Prompt::Play {
+                        // Leave on their turn, so the round cannot go on without them.
+                        leaving = None;
+                        left_during = Some(host.game.state.round_count);
+                    } else {
+                        respond(client, &view, &mut tries[1]);
+                    }
+                }
+            }
+
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert_eq!(host.player_count(), 1);
+    }
+
+    // Plays the first card it can, with nothing but the shell, so the test does not need
+    // anything else to be built first.
+    #[cfg(unix)]
+    const SHELL_BOT: &str = r#"
+        while read -r line; do
+            case "$line" in
+                "prompt = "*) prompt="${line#prompt = }" ;;
+                "playable ="*) set -- ${line#playable =}; first="$1" ;;
+                end) case "$prompt" in
+                    play) if [ -n "$first" ]; then echo "play $first"; else echo draw; fi ;;
+                    suit) echo "suit hearts" ;;
+                    rule) echo "rule 0" ;;
+                    "round over") echo ready ;;
+                esac ;;
+            esac
+        done
+    "#;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bots_can_play_through_rounds_over_their_standard_input_and_output() {
+        let script = std::env::temp_dir().join(format!("bartog_bot_{}.sh", std::process::id()));
+        std::fs::write(&script, SHELL_BOT).unwrap();
+        let command = format!("sh {}", script.display());
+
+        let config = Config {
+            human_seats: HumanSeats::new(2),
+            ..Config::default()
+        };
+        let mut host = Host::local([49; 16], config, Weights::bundled());
+        assert!(host.local_addr().is_err());
+
+        let seats = vec![
+            host.add_bot(&command).unwrap(),
+            host.add_bot(&command).unwrap(),
+        ];
+        assert_eq!(seats, vec![PLAYER_ID, PLAYER_ID - 1]);
+        assert!(host.add_bot(&command).is_err());
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while host.game.state.round_count < 3 {
+            assert!(
+                Instant::now() < deadline,
+                "the rounds did not finish in time"
+            );
+
+            host.update();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // Both bots answered in time, so neither was replaced by a cpu player.
+        assert_eq!(host.player_count(), 2);
+
+        drop(host);
+        let _ = std::fs::remove_file(script);
+    }
+
+    // Plays the bot `command` in one seat until it has made a few moves, then calls
+    // `stop` on the host, and checks that a cpu player finishes the round in its place.
+    #[cfg(unix)]
+    fn assert_a_cpu_player_takes_over(seed: Seed, command: &str, stop: impl Fn(&mut Host)) {
+        let config = Config {
+            human_seats: HumanSeats::new(1),
+            ..Config::default()
+        };
+        let mut host = Host::local(seed, config, Weights::bundled());
+        host.bot_timeout = Duration::from_millis(200);
+        let seat = host.add_bot(command).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while host.game.state.in_game.discard.len() < 4 {
+            assert!(Instant::now() < deadline, "the bot did not play");
+            host.update();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        stop(&mut host);
+        let round = host.game.state.round_count;
+        while host.game.state.round_count == round {
+            assert!(
+                Instant::now() < deadline,
+                "the round did not finish without the bot"
+            );
+            host.update();
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(host.game.absent[seat as usize]);
+        assert_eq!(host.player_count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_a_cpu_player_takes_over_from_a_bot_that_is_killed_mid_game() {
+        let script = std::env::temp_dir().join(format!("bartog_killed_{}.sh", std::process::id()));
+        std::fs::write(&script, SHELL_BOT).unwrap();
+
+        assert_a_cpu_player_takes_over([51; 16], &format!("sh {}", script.display()), |host| {
+            for bot in host.bots.iter_mut().flatten() {
+                bot.kill().unwrap();
+            }
+        });
+
+        let _ = std::fs::remove_file(script);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_a_cpu_player_takes_over_from_a_bot_that_stops_answering() {
+        // Answers until it is told to stop, then reads everything but never answers.
+        const STOPPING_BOT: &str = r#"
+            while read -r line; do
+                if [ -f "$1" ]; then continue; fi
+                case "$line" in
+                    "prompt = "*) prompt="${line#prompt = }" ;;
+                    "playable ="*) set -- "$1" ${line#playable =}; first="$2" ;;
+                    end) case "$prompt" in
+                        play) if [ -n "$first" ]; then echo "play $first"; else echo draw; fi ;;
+                        suit) echo "suit hearts" ;;
+                        rule) echo "rule 0" ;;
+                        "round over") echo ready ;;
+                    esac ;;
+                esac
+            done
+        "#;
+
+        let dir = std::env::temp_dir();
+        let script = dir.join(format!("bartog_stopping_{}.sh", std::process::id()));
+        let stop_file = dir.join(format!("bartog_stop_{}", std::process::id()));
+        std::fs::write(&script, STOPPING_BOT).unwrap();
+        let _ = std::fs::remove_file(&stop_file);
+
+        let command = format!("sh {} {}", script.display(), stop_file.display());
+        assert_a_cpu_player_takes_over([52; 16], &command, |_| {
+            std::fs::write(&stop_file, "").unwrap();
+        });
+
+        let _ = std::fs::remove_file(script);
+        let _ = std::fs::remove_file(stop_file);
+    }
+}
@@ -0,0 +1,708 @@
+//! Playing with people on other machines, over TCP.
+//!
+//! One process hosts the game, running the only `GameState`, and everyone else joins it.
+//! Players send `Move`s, which say what they want to do, like playing the third card in
+//! their hand, rather than which buttons they pressed. The host checks each move against
+//! the game and sends every player back a `View` of the game, which leaves out anything
+//! that player should not be able to see, like the cards in everyone else's hands.
+//!
+//! Both are sent as lines of text, so a session can be followed with a tool like `nc`.
+//!
+//! Seats can also be played by bots, which are other programs that the host starts and
+//! talks to with the same lines of text, over their standard input and output. See the
+//! `bot` module.
+use common::*;
+use game_state::{
+    can_play,
+    in_game::{self, CardMovement, Deal, RelativeHand, RelativePlayer, RelativePlayerSet},
+    PendingRule,
+};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::num::NonZeroU8;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+
+pub mod bot;
+mod host;
+pub use self::host::*;
+
+/// The port used when an address does not include one.
+pub const DEFAULT_PORT: u16 = 7393;
+
+/// Something a player asks the host to do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Move {
+    /// Play the card at this index in their hand.
+    Play(u8),
+    Draw,
+    /// Declare the wild card they just played to be this suit.
+    Suit(Suit),
+    /// Make the rule at this index in `View::options`.
+    Rule(u8),
+    /// Make this rule change instead of any of the options.
+    Make(PendingRule),
+    /// Start the next round, once everyone is ready.
+    Ready,
+}
+
+impl Move {
+    pub fn to_text(&self) -> String {
+        match *self {
+            Move::Play(index) => format!("play {}\n", index),
+            Move::Draw => "draw\n".to_owned(),
+            Move::Suit(suit) => format!("suit {}\n", get_suit_str(suit)),
+            Move::Rule(index) => format!("rule {}\n", index),
+            Move::Make(ref rule) => format!("make {}\n", rule_to_text(rule)),
+            Move::Ready => "ready\n".to_owned(),
+        }
+    }
+
+    /// Reads a line written by `Move::to_text`.
+    pub fn parse(line: &str) -> Option<Move> {
+        if let Some(rule) = line.trim().strip_prefix("make ") {
+            return parse_rule(rule).map(Move::Make);
+        }
+
+        let mut words = line.split_whitespace();
+        let name = words.next()?;
+        let argument = words.next();
+
+        match (name, argument) {
+            ("play", Some(index)) => index.parse().ok().map(Move::Play),
+            ("draw", None) => Some(Move::Draw),
+            ("suit", Some(suit)) => suits::ALL
+                .iter()
+                .cloned()
+                .find(|&s| get_suit_str(s) == suit)
+                .map(Move::Suit),
+            ("rule", Some(index)) => index.parse().ok().map(Move::Rule),
+            ("ready", None) => Some(Move::Ready),
+            _ => None,
+        }
+    }
+}
+
+// The words for each `RelativePlayer` in the text of a rule change.
+const PLAYER_WORDS: [(RelativePlayer, &str); 4] = [
+    (RelativePlayer::Same, "same"),
+    (RelativePlayer::Next, "next"),
+    (RelativePlayer::Across, "across"),
+    (RelativePlayer::Previous, "previous"),
+];
+
+fn player_word(player: RelativePlayer) -> &'static str {
+    PLAYER_WORDS
+        .iter()
+        .find(|&&(p, _)| p == player)
+        .map(|&(_, word)| word)
+        .unwrap_or_default()
+}
+
+fn parse_player(word: &str) -> Option<RelativePlayer> {
+    PLAYER_WORDS
+        .iter()
+        .find(|&&(_, w)| w == word)
+        .map(|&(player, _)| player)
+}
+
+fn hand_word(hand: RelativeHand) -> &'static str {
+    match hand {
+        RelativeHand::Player(player) => player_word(player),
+        RelativeHand::Deck => "deck",
+        RelativeHand::Discard => "discard",
+    }
+}
+
+fn parse_hand(word: &str) -> Option<RelativeHand> {
+    match word {
+        "deck" => Some(RelativeHand::Deck),
+        "discard" => Some(RelativeHand::Discard),
+        _ => parse_player(word).map(RelativeHand::Player),
+    }
+}
+
+fn cards_text(flags: CardFlags) -> String {
+    let cards: Vec<String> = flags.cards().iter().map(Card::to_string).collect();
+    cards.join(" ")
+}
+
+// Reads the faces in `words`, which must all be numbers below `FACE_COUNT`.
+fn parse_cards(words: &[&str]) -> Option<CardFlags> {
+    let mut cards = Vec::with_capacity(words.len());
+    for word in words {
+        let card: Card = word.parse().ok()?;
+        if card >= FACE_COUNT {
+            return None;
+        }
+        cards.push(card);
+    }
+
+    Some(CardFlags::from_cards(cards))
+}
+
+fn change_text(change: &in_game::Change) -> String {
+    match *change {
+        in_game::Change::CurrentPlayer(player) => format!("turn {}", player_word(player)),
+        in_game::Change::CardLocation(movement) => {
+            let players: Vec<&str> = movement.affected.map(player_word).collect();
+            let CardSelection::NthModuloCount(n) = movement.selection;
+            format!(
+                "move {} {} {} {}",
+                players.join(","),
+                hand_word(movement.source),
+                hand_word(movement.target),
+                n
+            )
+        }
+    }
+}
+
+// Reads the words of a change written by `change_text`, if it is one the rules allow.
+fn parse_change(words: &[&str]) -> Option<in_game::Change> {
+    match *words {
+        ["turn", player] => Some(in_game::Change::CurrentPlayer(parse_player(player)?))
+            .filter(|change| in_game::ALL_CHANGES.contains(change)),
+        ["move", players, source, target, n] => {
+            let mut affected = RelativePlayerSet::default();
+            for word in players.split(',') {
+                affected = affected.insert(parse_player(word)?);
+            }
+
+            let source = parse_hand(source)?;
+            let target = parse_hand(target)?;
+            if !CardMovement::hand_pairs().contains(&(source, target)) {
+                return None;
+            }
+
+            Some(in_game::Change::CardLocation(CardMovement {
+                affected,
+                source,
+                target,
+                selection: CardSelection::NthModuloCount(n.parse::<NonZeroU8>().ok()?),
+            }))
+        }
+        _ => None,
+    }
+}
+
+fn rule_to_text(rule: &PendingRule) -> String {
+    match rule {
+        PendingRule::CanPlay(changes) => {
+            let groups: Vec<String> = changes
+                .iter()
+                .map(|change| format!("{} on {}", change.card(), cards_text(change.edges())))
+                .collect();
+            format!("can_play {}", groups.join(" ; "))
+        }
+        PendingRule::Wild(flags) => format!("wild {}", cards_text(*flags)),
+        PendingRule::WhenPlayed(flags, changes) => {
+            let mut text = format!("when_played {}", cards_text(*flags));
+            for change in changes.iter() {
+                text.push_str(" then ");
+                text.push_str(&change_text(change));
+            }
+            text
+        }
+        PendingRule::Deal(deal) => {
+            let mut words: Vec<String> = deal.hand_sizes.iter().map(u8::to_string).collect();
+            words.push(deal.composition.decks.to_string());
+            for (word, set) in [
+                ("flip", deal.flip_starting_card),
+                ("rotate", deal.rotate_dealer),
+                ("jokers", deal.composition.jokers),
+            ] {
+                if set {
+                    words.push(word.to_owned());
+                }
+            }
+            format!("deal {}", words.join(" "))
+        }
+    }
+}
+
+// Reads a rule change written by `rule_to_text`, if it is one the rules allow. See the
+// `bot` module for the format.
+fn parse_rule(text: &str) -> Option<PendingRule> {
+    let (kind, rest) = text.trim().split_once(' ').unwrap_or((text.trim(), ""));
+
+    match kind {
+        "can_play" => {
+            let mut changes = Vec::new();
+            for group in rest.split(';') {
+                let words: Vec<&str> = group.split_whitespace().collect();
+                let on = words.iter().position(|&word| word == "on")?;
+                let cards = parse_cards(&words[..on])?;
+                let edges = parse_cards(&words[on + 1..])?;
+                if cards.is_empty() {
+                    return None;
+                }
+
+                changes.extend(
+                    cards
+                        .cards()
+                        .into_iter()
+                        .map(|card| can_play::Change::new(edges, card)),
+                );
+            }
+            Some(PendingRule::CanPlay(changes))
+        }
+        "wild" => {
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            parse_cards(&words).map(PendingRule::Wild)
+        }
+        "when_played" => {
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            let mut parts = words.split(|&word| word == "then");
+            let flags = parse_cards(parts.next()?)?;
+            let changes = parts.map(parse_change).collect::<Option<Vec<_>>>()?;
+            if flags.is_empty() || changes.is_empty() {
+                return None;
+            }
+
+            Some(PendingRule::WhenPlayed(flags, changes))
+        }
+        "deal" => {
+            let words: Vec<&str> = rest.split_whitespace().collect();
+            if words.len() < PLAYER_ID_COUNT + 1 {
+                return None;
+            }
+
+            let mut deal = Deal::default();
+            for (size, word) in deal.hand_sizes.iter_mut().zip(words.iter()) {
+                *size = word.parse().ok()?;
+                if !(Deal::MIN_HAND_SIZE..=Deal::MAX_HAND_SIZE).contains(size) {
+                    return None;
+                }
+            }
+
+            deal.composition.decks = words[PLAYER_ID_COUNT].parse().ok()?;
+            if !(1..=DeckComposition::MAX_DECKS).contains(&deal.composition.decks) {
+                return None;
+            }
+
+            for &word in words[PLAYER_ID_COUNT + 1..].iter() {
+                match word {
+                    "flip" => deal.flip_starting_card = true,
+                    "rotate" => deal.rotate_dealer = true,
+                    "jokers" => deal.composition.jokers = true,
+                    _ => return None,
+                }
+            }
+
+            Some(PendingRule::Deal(deal))
+        }
+        _ => None,
+    }
+}
+
+/// What, if anything, the host is waiting for a player to do.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Prompt {
+    /// Someone else is doing something, or the game is waiting for everyone to join.
+    #[default]
+    Wait,
+    /// It is their turn, so they should send `Move::Play` or `Move::Draw`.
+    Play,
+    /// They played a wild card, so they should send `Move::Suit`.
+    Suit,
+    /// They won the round, so they should send `Move::Rule`.
+    Rule,
+    /// The round is over, and the host is waiting for them to send `Move::Ready`.
+    RoundOver,
+}
+
+impl Prompt {
+    const ALL: [Prompt; 5] = [
+        Prompt::Wait,
+        Prompt::Play,
+        Prompt::Suit,
+        Prompt::Rule,
+        Prompt::RoundOver,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match *self {
+            Prompt::Wait => "wait",
+            Prompt::Play => "play",
+            Prompt::Suit => "suit",
+            Prompt::Rule => "rule",
+            Prompt::RoundOver => "round over",
+        }
+    }
+}
+
+/// What one player can see of the game.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct View {
+    /// The seat the player is sitting in.
+    pub seat: PlayerID,
+    /// How many humans are playing, which decides how players are named.
+    pub human_count: u8,
+    pub prompt: Prompt,
+    /// Who the game is waiting on, if it is waiting on one player.
+    pub waiting_for: Option<PlayerID>,
+    /// The cards in the player's own hand.
+    pub hand: Vec<Card>,
+    /// The indexes of the cards in `hand` that can be played right now. This is always
+    /// empty when the rules are secret.
+    pub playable: Vec<u8>,
+    /// How many cards each player has, indexed by `PlayerID`.
+    pub hand_sizes: [u8; PLAYER_ID_COUNT],
+    /// The card on top of the discard pile, if any.
+    pub top: Option<Card>,
+    /// The suit the top card was declared as, if it is wild.
+    pub declared: Option<Suit>,
+    pub deck_size: u8,
+    pub discard_size: u8,
+    /// The rules the player can pick between, or how the round went, depending on `prompt`.
+    pub options: Vec<String>,
+    /// The current rules, as shown on the current rules screen, leaving out secret ones.
+    pub rules: Vec<String>,
+    /// The end of the event log.
+    pub log: Vec<String>,
+}
+
+impl View {
+    /// The line that ends each view, since views can have any number of lines.
+    pub const END: &'static str = "end";
+
+    /// Writes the view out as `key = value` lines, ending with `View::END`, which
+    /// `View::parse` reads.
+    pub fn to_text(&self) -> String {
+        let join = |values: Vec<String>| values.join(" ");
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_owned());
+
+        let mut lines = vec![
+            ("seat", self.seat.to_string()),
+            ("human_count", self.human_count.to_string()),
+            ("prompt", self.prompt.label().to_owned()),
+            (
+                "waiting_for",
+                or_none(self.waiting_for.map(|player| player.to_string())),
+            ),
+            (
+                "hand",
+                join(self.hand.iter().map(Card::to_string).collect()),
+            ),
+            (
+                "playable",
+                join(self.playable.iter().map(u8::to_string).collect()),
+            ),
+            (
+                "hand_sizes",
+                join(self.hand_sizes.iter().map(u8::to_string).collect()),
+            ),
+            ("top", or_none(self.top.map(|card| card.to_string()))),
+            (
+                "declared",
+                or_none(self.declared.map(|suit| get_suit_str(suit).to_owned())),
+            ),
+            ("deck_size", self.deck_size.to_string()),
+            ("discard_size", self.discard_size.to_string()),
+        ];
+        lines.extend(self.options.iter().map(|option| ("option", option.clone())));
+        lines.extend(self.rules.iter().map(|rule| ("rule", rule.clone())));
+        lines.extend(self.log.iter().map(|line| ("log", line.clone())));
+
+        let mut output = String::new();
+        for (key, value) in lines.iter() {
+            output.push_str(key);
+            output.push_str(" = ");
+            output.push_str(value);
+            output.push('\n');
+        }
+        output.push_str(View::END);
+        output.push('\n');
+        output
+    }
+
+    /// Reads a view written by `View::to_text`. Anything missing or unreadable is left at
+    /// its default.
+    pub fn parse(text: &str) -> View {
+        let mut view = View::default();
+
+        let numbers = |value: &str| -> Vec<u8> {
+            value
+                .split_whitespace()
+                .filter_map(|number| number.parse().ok())
+                .collect()
+        };
+
+        for line in text.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+
+            match key {
+                "seat" => view.seat = value.parse().unwrap_or_default(),
+                "human_count" => view.human_count = value.parse().unwrap_or_default(),
+                "prompt" => {
+                    if let Some(&prompt) = Prompt::ALL.iter().find(|p| p.label() == value) {
+                        view.prompt = prompt;
+                    }
+                }
+                "waiting_for" => view.waiting_for = value.parse().ok(),
+                "hand" => view.hand = numbers(value),
+                "playable" => view.playable = numbers(value),
+                "hand_sizes" => {
+                    for (size, number) in view.hand_sizes.iter_mut().zip(numbers(value)) {
+                        *size = number;
+                    }
+                }
+                "top" => view.top = value.parse().ok(),
+                "declared" => {
+                    view.declared = suits::ALL
+                        .iter()
+                        .cloned()
+                        .find(|&suit| get_suit_str(suit) == value)
+                }
+                "deck_size" => view.deck_size = value.parse().unwrap_or_default(),
+                "discard_size" => view.discard_size = value.parse().unwrap_or_default(),
+                "option" => view.options.push(value.to_owned()),
+                "rule" => view.rules.push(value.to_owned()),
+                "log" => view.log.push(value.to_owned()),
+                _ => {}
+            }
+        }
+
+        view
+    }
+}
+
+/// A TCP stream, or a bot's standard input and output, that lines of text can be read
+/// from without waiting for them.
+struct Connection {
+    writer: Box<dyn Write + Send>,
+    incoming: Receiver<String>,
+    is_open: bool,
+    // The reading thread holds its own copy of a TCP stream, so dropping the writer is
+    // not enough to close it.
+    stream: Option<TcpStream>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Connection> {
+        // A listener that does not block can hand out streams that do not block either.
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+
+        let reader = stream.try_clone()?;
+        let mut connection = Connection::from_halves(reader, stream.try_clone()?);
+        connection.stream = Some(stream);
+        Ok(connection)
+    }
+
+    fn from_halves(
+        reader: impl Read + Send + 'static,
+        writer: impl Write + Send + 'static,
+    ) -> Connection {
+        // The lines are read on their own thread, so nothing else waits for them.
+        let reader = BufReader::new(reader);
+        let (sender, incoming) = channel();
+        thread::spawn(move || {
+            for line in reader.lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Connection {
+            writer: Box::new(writer),
+            incoming,
+            is_open: true,
+            stream: None,
+        }
+    }
+
+    /// The lines that have arrived since the last call.
+    fn receive(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        loop {
+            match self.incoming.try_recv() {
+                Ok(line) => lines.push(line),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.is_open = false;
+                    break;
+                }
+            }
+        }
+        lines
+    }
+
+    fn send(&mut self, text: &str) {
+        if self.writer.write_all(text.as_bytes()).is_err() {
+            self.is_open = false;
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Some(stream) = self.stream.as_ref() {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+/// Adds the default port to `address` if it does not have one.
+pub fn with_default_port(address: &str) -> String {
+    if address.contains(':') {
+        address.to_owned()
+    } else {
+        format!("{}:{}", address, DEFAULT_PORT)
+    }
+}
+
+/// A player's connection to a host.
+pub struct Client {
+    connection: Connection,
+    // The lines of a view that has not fully arrived yet.
+    partial: String,
+}
+
+impl Client {
+    pub fn connect(address: &str) -> io::Result<Client> {
+        let stream = TcpStream::connect(with_default_port(address))?;
+
+        Ok(Client {
+            connection: Connection::new(stream)?,
+            partial: String::new(),
+        })
+    }
+
+    pub fn send(&mut self, player_move: Move) {
+        self.connection.send(&player_move.to_text());
+    }
+
+    /// The latest view the host has sent, if it has sent one since the last call.
+    pub fn poll(&mut self) -> Option<View> {
+        let mut latest = None;
+        for line in self.connection.receive() {
+            if line == View::END {
+                latest = Some(View::parse(&self.partial));
+                self.partial.clear();
+            } else {
+                self.partial.push_str(&line);
+                self.partial.push('\n');
+            }
+        }
+        latest
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connection.is_open
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves_survive_being_written_out_and_read_back() {
+        let moves = [
+            Move::Play(0),
+            Move::Play(12),
+            Move::Draw,
+            Move::Suit(suits::HEARTS),
+            Move::Rule(2),
+            Move::Make(PendingRule::Wild(CardFlags::new(RANK_FLAGS[0]))),
+            Move::Make(PendingRule::CanPlay(vec![
+                can_play::Change::new(CardFlags::new(SUIT_FLAGS[1]), 0),
+                can_play::Change::new(CardFlags::new(JOKER_FLAGS), 53),
+            ])),
+            Move::Make(PendingRule::WhenPlayed(
+                CardFlags::from_cards(vec![10, 23]),
+                vec![
+                    in_game::Change::CardLocation(CardMovement {
+                        affected: RelativePlayerSet::default()
+                            .insert(RelativePlayer::Next)
+                            .insert(RelativePlayer::Across),
+                        source: RelativeHand::Deck,
+                        target: RelativeHand::Discard,
+                        selection: CardSelection::NthModuloCount(nu8!(3)),
+                    }),
+                    in_game::Change::CardLocation(CardMovement {
+                        affected: RelativePlayerSet::default().insert(RelativePlayer::Same),
+                        source: RelativeHand::Player(RelativePlayer::Same),
+                        target: RelativeHand::Player(RelativePlayer::Previous),
+                        selection: CardSelection::NthModuloCount(nu8!(1)),
+                    }),
+                ],
+            )),
+            Move::Make(PendingRule::Deal(Deal {
+                hand_sizes: [3, 7, 7, 10],
+                flip_starting_card: true,
+                rotate_dealer: false,
+                composition: DeckComposition {
+                    decks: 2,
+                    jokers: true,
+                },
+            })),
+            Move::Ready,
+        ];
+
+        for player_move in moves.iter() {
+            assert_eq!(
+                Move::parse(&player_move.to_text()),
+                Some(player_move.clone())
+            );
+        }
+
+        assert_eq!(Move::parse("play"), None);
+        assert_eq!(Move::parse("suit stars"), None);
+        assert_eq!(Move::parse("fold"), None);
+    }
+
+    #[test]
+    fn test_rule_changes_that_could_not_be_made_are_not_read() {
+        assert_eq!(Move::parse("make wild 54"), None);
+        assert_eq!(Move::parse("make can_play on 1 2"), None);
+        assert_eq!(Move::parse("make when_played 0"), None);
+        assert_eq!(
+            Move::parse("make when_played 0 then move next deck deck 1"),
+            None
+        );
+        assert_eq!(
+            Move::parse("make when_played 0 then move next deck discard 0"),
+            None
+        );
+        assert_eq!(Move::parse("make deal 5 5 5 0 1"), None);
+        assert_eq!(Move::parse("make deal 5 5 5 5 1 shuffle"), None);
+        assert_eq!(Move::parse("make peace"), None);
+    }
+
+    #[test]
+    fn test_views_survive_being_written_out_and_read_back() {
+        let view = View {
+            seat: 2,
+            human_count: 2,
+            prompt: Prompt::RoundOver,
+            waiting_for: None,
+            hand: vec![0, 13, 51],
+            playable: vec![1, 2],
+            hand_sizes: [4, 0, 3, 1],
+            top: Some(7),
+            declared: Some(suits::SPADES),
+            deck_size: 30,
+            discard_size: 12,
+            options: vec!["player 3 wins!".to_owned(), "player 3: 1 win".to_owned()],
+            rules: vec!["all aces are wild. (rule #1)".to_owned()],
+            log: vec![
+                "player 3 played a 2 of clubs.".to_owned(),
+                "-----".to_owned(),
+            ],
+        };
+
+        let text = view.to_text();
+        assert!(text.ends_with("\nend\n"));
+        assert_eq!(View::parse(&text), view);
+
+        let empty = View::default();
+        assert_eq!(View::parse(&empty.to_text()), empty);
+    }
+}
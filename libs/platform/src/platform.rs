@@ -227,16 +227,46 @@ pub fn get_state_params() -> StateParams {
     }
 
     let time = Date::new_0().get_time();
+    const MILLISECONDS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
 
     let seed = unsafe {
         core::mem::transmute::<[f64; 2], [u8; 16]>([time, 1.0 / time])
     };
 
-    (
+    fn local_storage() -> Option<web_sys::Storage> {
+        web_sys::window()?.local_storage().ok()?
+    }
+
+    fn load(key: &str) -> Option<String> {
+        local_storage()?.get_item(key).ok()?
+    }
+
+    fn save(key: &str, value: &str) {
+        if let Some(storage) = local_storage() {
+            // If this fails, the settings are just not remembered.
+            let _ = storage.set_item(key, value);
+        }
+    }
+
+    // As in `?seed=3k9z0qa`.
+    let requested_seed = web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .and_then(|search| {
+            search
+                .trim_start_matches('?')
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("seed="))
+                .map(str::to_owned)
+        });
+
+    StateParams {
         seed,
-        Some(logger),
-        Some(error_logger),
-    )
+        logger: Some(logger),
+        error_logger: Some(error_logger),
+        storage: Some((load, save)),
+        requested_seed,
+        today: (time / MILLISECONDS_PER_DAY) as u32,
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -249,11 +279,67 @@ pub fn get_state_params() -> StateParams {
         eprintln!("{}", s);
     }
 
-    (
-        new_seed(),
-        Some(logger),
-        Some(error_logger),
-    )
+    StateParams {
+        seed: new_seed(),
+        logger: Some(logger),
+        error_logger: Some(error_logger),
+        storage: Some((load, save)),
+        requested_seed: requested_seed(std::env::args().skip(1)),
+        today: today(),
+    }
+}
+
+/// The number of days since 1970-01-01, in UTC.
+#[cfg(not(target_arch = "wasm32"))]
+fn today() -> u32 {
+    let time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+
+    (time.as_secs() / (24 * 60 * 60)) as u32
+}
+
+/// Finds the seed string passed as `--seed 3k9z0qa` or `--seed=3k9z0qa`, if any.
+#[cfg(not(target_arch = "wasm32"))]
+fn requested_seed(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            return args.next();
+        } else if let Some(seed) = arg.strip_prefix("--seed=") {
+            return Some(seed.to_owned());
+        }
+    }
+
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn storage_path(key: &str) -> Option<std::path::PathBuf> {
+    use std::{env::var_os, path::PathBuf};
+
+    let base = var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(base.join("bartog").join(key))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(storage_path(key)?).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save(key: &str, value: &str) {
+    if let Some(path) = storage_path(key) {
+        // If this fails, the settings are just not remembered.
+        let _ = path
+            .parent()
+            .map(std::fs::create_dir_all)
+            .unwrap_or(Ok(()))
+            .and_then(|()| std::fs::write(&path, value));
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
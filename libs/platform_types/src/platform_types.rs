@@ -149,7 +149,21 @@ bitflags! {
 
 pub type Logger = Option<fn(&str) -> ()>;
 
-pub type StateParams = ([u8; 16], Logger, Logger);
+/// A way to load and save small bits of text between launches, keyed by name: a
+/// loading function and a saving function.
+pub type Storage = Option<(fn(&str) -> Option<String>, fn(&str, &str))>;
+
+/// What the platform passes to the game when it starts.
+pub struct StateParams {
+    pub seed: [u8; 16],
+    pub logger: Logger,
+    pub error_logger: Logger,
+    pub storage: Storage,
+    /// The seed string the player asked for when launching the game, if any.
+    pub requested_seed: Option<String>,
+    /// The current day, counted from 1970-01-01 in UTC.
+    pub today: u32,
+}
 
 pub trait State {
     fn frame(&mut self) -> (&[Command], &[SFX]);
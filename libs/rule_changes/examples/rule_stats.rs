@@ -0,0 +1,45 @@
+// Prints the distribution of the random parts of the rules that the cpu players make.
+//
+// Usage: cargo run -p rule_changes --example rule_stats -- [weights file] [sample count]
+//
+// If no weights file is given, the bundled `weights.txt` is used.
+use common::xs;
+use game_state::{
+    weights::{Weights, BUNDLED_WEIGHTS},
+    RULE_TYPES,
+};
+use rule_changes::generation::Stats;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let text = match args.next() {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("could not read {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => BUNDLED_WEIGHTS.to_owned(),
+    };
+
+    let weights = match Weights::parse(&text) {
+        Ok(weights) => weights,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let samples = args.next().and_then(|s| s.parse().ok()).unwrap_or(10_000);
+
+    let mut rng = xs::from_seed([
+        0xb0, 0xa7, 0x70, 0x6b, 0x2a, 0x11, 0x5e, 0xed, 0x42, 0x13, 0x37, 0x99, 0x10, 0x20, 0x30,
+        0x40,
+    ]);
+
+    let enabled = [true; RULE_TYPES.len()];
+
+    print!("{}", Stats::sample(&mut rng, &weights, &enabled, samples));
+}
@@ -0,0 +1,324 @@
+//! Weighted random generation of the parts of the rules that the cpu players make.
+//! The weights come from `GameState::weights`, which are described in
+//! `game_state::weights`. `cargo run -p rule_changes --example rule_stats` prints the
+//! distribution that a given weights file produces.
+use common::{xs::Xs, *};
+use game_state::{
+    in_game::{self, CardMovement, RelativePlayer, RelativePlayerSet},
+    weights::{
+        allowed_hand_kinds, hand_kind, turn_changes_allowed, Weights, AFFECTED_KEYS, CHANGE_KEYS,
+        HAND_KIND_KEYS, RULE_TYPE_KEYS, SELECTION_KEYS, SET_SIZE_KEYS,
+    },
+    Status, RULE_TYPES,
+};
+use std::{fmt, num::NonZeroU8};
+
+// The inclusive bounds on the size of the card sets in each of the set size buckets, for
+// a deck with the faces in `composition`.
+fn set_size_bounds(composition: DeckComposition) -> [(u32, u32); 6] {
+    let face_count = composition.face_count() as u32;
+
+    [
+        (1, 1),
+        (2, 4),
+        (5, face_count / 4),
+        (face_count / 4 + 1, face_count / 2),
+        (face_count / 2 + 1, face_count - 1),
+        (face_count, face_count),
+    ]
+}
+
+/// Returns an index into `weights`, chosen with probability proportional to its weight.
+/// Returns 0 if all the weights are 0.
+pub fn pick(rng: &mut Xs, weights: &[u32]) -> usize {
+    let total: u32 = weights.iter().sum();
+    if total == 0 {
+        return 0;
+    }
+
+    let mut roll = xs::range(rng, 0..total);
+    for (i, &weight) in weights.iter().enumerate() {
+        if roll < weight {
+            return i;
+        }
+        roll -= weight;
+    }
+
+    invariant_violation!({ 0 }, "pick roll was larger than the total weight")
+}
+
+/// Picks one of the rule types that are `enabled`, which is indexed the same way as
+/// `RULE_TYPES`. If all of those have no weight, they are all equally likely.
+pub fn rule_type(rng: &mut Xs, weights: &Weights, enabled: &[bool; RULE_TYPES.len()]) -> Status {
+    let mut rule_type = [0; RULE_TYPES.len()];
+    for (i, weight) in rule_type.iter_mut().enumerate() {
+        if enabled[i] {
+            *weight = weights.rule_type[i];
+        }
+    }
+
+    if rule_type.iter().all(|&weight| weight == 0) {
+        for (i, weight) in rule_type.iter_mut().enumerate() {
+            *weight = enabled[i] as u32;
+        }
+    }
+
+    RULE_TYPES[pick(rng, &rule_type)]
+}
+
+/// Picks a set of cards from the faces in `composition`.
+pub fn card_flags(rng: &mut Xs, weights: &Weights, composition: DeckComposition) -> CardFlags {
+    let bucket = pick(rng, &weights.set_size);
+    let (min, max) = set_size_bounds(composition)[bucket];
+
+    if pick(rng, &weights.set_kind) == 0 {
+        let structured: Vec<u64> = SPECIAL_FLAGS
+            .iter()
+            .cloned()
+            .filter(|&bits| {
+                let len = bits.count_ones();
+                min <= len && len <= max
+            })
+            .collect();
+
+        if !structured.is_empty() {
+            let index = xs::range(rng, 0..structured.len() as u32) as usize;
+            return CardFlags::new(structured[index]);
+        }
+    }
+
+    let size = xs::range(rng, min..max + 1) as usize;
+
+    let mut deck = fresh_deck(DeckComposition {
+        decks: 1,
+        ..composition
+    });
+    xs::shuffle(rng, &mut deck);
+    deck.truncate(size);
+
+    CardFlags::from_cards(deck)
+}
+
+pub fn change(rng: &mut Xs, weights: &Weights) -> in_game::Change {
+    let change_weights = [
+        if turn_changes_allowed() {
+            weights.change[0]
+        } else {
+            0
+        },
+        weights.change[1],
+    ];
+
+    if pick(rng, &change_weights) == 0 && turn_changes_allowed() {
+        let players = RelativePlayer::all_values();
+        let index = xs::range(rng, 0..players.len() as u32) as usize;
+        return in_game::Change::CurrentPlayer(players[index]);
+    }
+
+    in_game::Change::CardLocation(movement(rng, weights))
+}
+
+fn movement(rng: &mut Xs, weights: &Weights) -> CardMovement {
+    let kinds = allowed_hand_kinds();
+    let kind_weights: Vec<u32> = kinds.iter().map(|&(s, t)| weights.movement[s][t]).collect();
+    let kind = kinds[pick(rng, &kind_weights)];
+
+    let pairs: Vec<_> = CardMovement::hand_pairs()
+        .into_iter()
+        .filter(|&(s, t)| (hand_kind(s), hand_kind(t)) == kind)
+        .collect();
+    let (source, target) = pairs[xs::range(rng, 0..pairs.len() as u32) as usize];
+
+    let affected_count = pick(rng, &weights.affected) as u32 + 1;
+    let sets: Vec<_> = RelativePlayerSet::all_non_empty_values()
+        .into_iter()
+        .filter(|set| set.count() as u32 == affected_count)
+        .collect();
+    let affected = sets[xs::range(rng, 0..sets.len() as u32) as usize];
+
+    let nth = match pick(rng, &weights.selection) {
+        i @ 0..=2 => i as u8 + 1,
+        _ => xs::range(rng, 4..DECK_SIZE as u32 + 1) as u8,
+    };
+
+    CardMovement {
+        affected,
+        source,
+        target,
+        selection: CardSelection::NthModuloCount(NonZeroU8::new(nth).unwrap_or(nu8!(1))),
+    }
+}
+
+/// Counts of how often each option came up, over a number of generated rules.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub samples: u32,
+    pub rule_type: [u32; RULE_TYPES.len()],
+    pub set_size: [u32; 6],
+    pub change: [u32; 2],
+    pub movement: [[u32; 3]; 3],
+    pub affected: [u32; 4],
+    pub selection: [u32; 4],
+}
+
+impl Stats {
+    /// Generates `samples` of each kind of thing, and counts the results. Only the rule
+    /// types that are `enabled`, which is indexed the same way as `RULE_TYPES`, come up.
+    pub fn sample(
+        rng: &mut Xs,
+        weights: &Weights,
+        enabled: &[bool; RULE_TYPES.len()],
+        samples: u32,
+    ) -> Stats {
+        let mut stats = Stats { samples, ..d!() };
+
+        for _ in 0..samples {
+            let status = rule_type(rng, weights, enabled);
+            if let Some(i) = RULE_TYPES.iter().position(|&s| s == status) {
+                stats.rule_type[i] += 1;
+            }
+
+            let composition = d!();
+            let len = card_flags(rng, weights, composition).len();
+            let size_index = set_size_bounds(composition)
+                .iter()
+                .position(|&(min, max)| min <= len && len <= max);
+            if let Some(i) = size_index {
+                stats.set_size[i] += 1;
+            } else {
+                invariant_violation!({}, "a set of {} cards is in no set size bucket", len)
+            }
+
+            match change(rng, weights) {
+                in_game::Change::CurrentPlayer(_) => stats.change[0] += 1,
+                in_game::Change::CardLocation(m) => {
+                    stats.change[1] += 1;
+                    stats.movement[hand_kind(m.source)][hand_kind(m.target)] += 1;
+                    stats.affected[m.affected.count() - 1] += 1;
+                    let CardSelection::NthModuloCount(n) = m.selection;
+                    stats.selection[(n.get() as usize - 1).min(3)] += 1;
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn group(
+            f: &mut fmt::Formatter<'_>,
+            name: &str,
+            labels: &[&str],
+            counts: &[u32],
+        ) -> fmt::Result {
+            let total: u32 = counts.iter().sum();
+            writeln!(f, "{} ({} samples)", name, total)?;
+            for (label, &count) in labels.iter().zip(counts.iter()) {
+                let percent = if total == 0 {
+                    0.0
+                } else {
+                    count as f32 * 100.0 / total as f32
+                };
+                writeln!(
+                    f,
+                    "  {:<18} {:>6.2}% {}",
+                    label,
+                    percent,
+                    "#".repeat((percent / 2.0).round() as usize)
+                )?;
+            }
+            writeln!(f)
+        }
+
+        group(f, "rule_type", &RULE_TYPE_KEYS, &self.rule_type)?;
+        group(f, "set_size", &SET_SIZE_KEYS, &self.set_size)?;
+        group(f, "change", &CHANGE_KEYS, &self.change)?;
+
+        let mut labels = Vec::with_capacity(9);
+        let mut counts = Vec::with_capacity(9);
+        for &(s, t) in allowed_hand_kinds() {
+            labels.push(format!("{}_to_{}", HAND_KIND_KEYS[s], HAND_KIND_KEYS[t]));
+            counts.push(self.movement[s][t]);
+        }
+        let labels: Vec<&str> = labels.iter().map(|s| s.as_str()).collect();
+        group(f, "movement", &labels, &counts)?;
+
+        group(f, "affected", &AFFECTED_KEYS, &self.affected)?;
+        group(f, "selection", &SELECTION_KEYS, &self.selection)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_only_count_the_enabled_rule_types() {
+        let mut rng = xs::from_seed([0x57; 16]);
+        let enabled = [false, true, false, true];
+
+        let stats = Stats::sample(&mut rng, &Weights::default(), &enabled, 100);
+
+        assert_eq!(stats.rule_type[0] + stats.rule_type[2], 0);
+        assert_eq!(stats.rule_type[1] + stats.rule_type[3], 100);
+    }
+
+    #[test]
+    fn test_only_the_weighted_set_size_is_generated() {
+        let weights = Weights {
+            set_size: [0, 1, 0, 0, 0, 0],
+            ..d!()
+        };
+
+        let mut rng = xs::from_seed([0x5e, 0x7, 0x51, 0x2e, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+        for _ in 0..100 {
+            let len = card_flags(&mut rng, &weights, d!()).len();
+            assert!((2..=4).contains(&len), "{}", len);
+        }
+    }
+
+    #[test]
+    fn test_card_flags_only_pick_jokers_when_the_deck_has_them() {
+        let weights = Weights {
+            set_size: [0, 0, 0, 0, 0, 1],
+            set_kind: [0, 1],
+            ..d!()
+        };
+
+        let picks_a_joker = |composition: DeckComposition| {
+            let mut rng = xs::from_seed([0x10; 16]);
+            (0..16).any(|_| {
+                let flags = card_flags(&mut rng, &weights, composition);
+                flags.cards().into_iter().any(is_joker)
+            })
+        };
+
+        assert!(!picks_a_joker(d!()));
+        assert!(picks_a_joker(DeckComposition {
+            jokers: true,
+            ..d!()
+        }));
+    }
+
+    #[test]
+    fn test_the_all_set_size_has_every_face_in_the_deck() {
+        let weights = Weights {
+            set_size: [0, 0, 0, 0, 0, 1],
+            ..d!()
+        };
+        let composition = DeckComposition {
+            jokers: true,
+            ..d!()
+        };
+
+        let mut rng = xs::from_seed([0xa1; 16]);
+
+        for _ in 0..16 {
+            let len = card_flags(&mut rng, &weights, composition).len();
+            assert_eq!(len, composition.face_count() as u32);
+        }
+    }
+}
@@ -4,5 +4,7 @@
 
 
 
+pub mod generation;
+
 mod rule_changes;
 pub use self::rule_changes::*;
@@ -1,5 +1,11 @@
 use common::*;
-use game_state::{can_play, event_push, in_game, GameState, Status, RULE_TYPES};
+use game_state::{
+    can_play,
+    diff::{get_edits, Edit},
+    event_push, in_game, seed_from_number, seed_string, Config, GameMode, GameState, PendingRule,
+    Provenance, RuleEdit, SecretRule, Status, RULE_TYPES,
+};
+use crate::generation;
 
 struct CardFlagsDelta {
     pub additions: CardFlags,
@@ -11,7 +17,7 @@ impl CardFlagsDelta {
         let mut additions = CardFlags::new(0);
         let mut removals = CardFlags::new(0);
 
-        for card in 0..DECK_SIZE {
+        for card in 0..FACE_COUNT {
             let mask = 1 << card as usize;
             let p_edge = mask & previous_flags.get_bits() != 0;
             let n_edge = mask & new_flags.get_bits() != 0;
@@ -30,33 +36,107 @@ impl CardFlagsDelta {
     }
 }
 
-pub fn reset(state: &mut GameState) {
-    let status = {
-        let mut status = Status::InGame;
+/// Starts a new game with the settings in `state.config`, including any rules the game
+/// mode starts with.
+pub fn start(state: &mut GameState) {
+    let chosen_config = state.config;
+    let config = if chosen_config.mode == GameMode::Daily {
+        chosen_config.daily()
+    } else {
+        chosen_config
+    };
 
-        for id in state.winners().clone() {
-            if is_player(id) {
-                status = Status::RuleSelection;
-                continue;
-            }
+    let seed_number = match config.seed {
+        Some(number) => number,
+        // The old game's generators are about to be replaced, so it does not matter which
+        // one this comes from.
+        None => xs::range(&mut state.rngs.rules, 0..u32::MAX),
+    };
+    let weights = state.weights.clone();
 
-            add_cpu_rule(state, id);
-        }
+    *state = GameState::new(seed_from_number(seed_number), config);
+    state.seed_number = seed_number;
+    state.weights = weights;
+    if config != chosen_config {
+        state.chosen_config = Some(chosen_config);
+    }
+    state
+        .event_log
+        .push(format!("seed: {}.", seed_string(seed_number)).as_bytes());
+
+    let starting_rule_count = if config.rules_change() {
+        config.mode.starting_rule_count()
+    } else {
+        0
+    };
+
+    for i in 0..starting_rule_count {
+        let player = i % config.cpu_count.max(1);
 
-        status
+        let rule = random_cpu_rule(state);
+        apply_rule(state, rule, player);
+    }
+}
+
+pub fn reset(state: &mut GameState) {
+    // When the rules never change, winning a round just leads to the next one.
+    let winners = if state.config.rules_change() {
+        state.winners().clone()
+    } else {
+        Vec::new()
+    };
+
+    let rule_makers: Vec<PlayerID> = winners
+        .iter()
+        .cloned()
+        .filter(|&id| is_player(state.in_game.human_seats, id))
+        .collect();
+
+    let status = if rule_makers.is_empty() {
+        Status::InGame
+    } else {
+        Status::RuleSelection
     };
 
     let old_log = state.event_log.take();
     let old_rules = state.rules.take();
+    let scores = state.current_scores();
+    let seed_number = state.seed_number;
+    let points = state.current_points();
+    let chosen_config = state.chosen_config;
+    let watch = state.watch;
+    let weights = state.weights.clone();
 
     *state = GameState::new_with_previous(
-        xs::new_seed(&mut state.rng),
+        state.rngs.clone(),
         status,
         old_rules,
         old_log,
         state.round_count,
         state.show_rules,
+        state.config,
     );
+    state.scores = scores;
+    state.seed_number = seed_number;
+    state.points = points;
+    state.chosen_config = chosen_config;
+    state.watch = watch;
+    state.weights = weights;
+    state.rule_makers = rule_makers;
+
+    // The next round has already been dealt, so the cpu players can make rules that suit
+    // their new hands.
+    let mut turns_left = MAX_SIMULATED_TURNS_PER_RESET;
+    for id in winners {
+        if is_player(state.in_game.human_seats, id) {
+            continue;
+        }
+
+        for _ in 0..state.config.rule_budget {
+            let rule = choose_cpu_rule(state, id, &mut turns_left);
+            add_cpu_rule(state, id, rule);
+        }
+    }
 
     //aka if the player did not win.
     if let Status::InGame = status {
@@ -65,24 +145,196 @@ pub fn reset(state: &mut GameState) {
     //`start_new_round` should also be called after the player makes a rule
 }
 
-fn add_cpu_rule(state: &mut GameState, player: PlayerID) {
-    let rule_type = {
-        let index = xs::range(&mut state.rng, 0..RULE_TYPES.len() as _) as usize;
-        RULE_TYPES[index]
-    };
+fn add_cpu_rule(state: &mut GameState, player: PlayerID, rule: PendingRule) {
+    if state.config.hidden_rules {
+        add_secret_cpu_rule(state, player, rule);
+    } else {
+        apply_rule(state, rule, player);
+    }
+}
+
+fn add_secret_cpu_rule(state: &mut GameState, player: PlayerID, rule: PendingRule) {
+    // Log the rule change into a separate log so we can show it when the rules are revealed.
+    let public_log = state.event_log.take();
+
+    let provenance = apply_rule(state, rule, player);
+
+    let secret_log = std::mem::replace(&mut state.event_log, public_log);
+
+    let lines = secret_log
+        .iter()
+        .filter(|line| !line.iter().all(|&c| c == b'-'))
+        .map(|line| line.to_vec())
+        .collect();
+
+    state.rules.secrets.rules.push(SecretRule { provenance, lines });
+
+    state.event_log.push_hr();
+    event_push!(
+        state.event_log,
+        player_name(state.in_game.human_seats, player).as_bytes(),
+        b" made a secret rule: rule #",
+        provenance.number.to_string().as_bytes(),
+        b".",
+    );
+}
+
+/// Applies `rule`, made by `player`, logging it and recording it in the rules' history.
+/// Returns where the rule came from.
+pub fn apply_rule(state: &mut GameState, rule: PendingRule, player: PlayerID) -> Provenance {
+    let provenance = state.rules.provenance.begin_rule(player, state.round_count);
+
+    match rule {
+        PendingRule::CanPlay(changes) => apply_can_play_graph_changes(state, changes, player),
+        PendingRule::Wild(wild) => apply_wild_change(state, wild, player),
+        PendingRule::WhenPlayed(card_flags, changes) => {
+            apply_when_played_changes(state, card_flags, changes, player)
+        }
+        PendingRule::Deal(deal) => apply_deal_change(state, deal, player),
+    }
+
+    state.rules.provenance.end_rule();
+
+    provenance
+}
+
+// How many random rules a cpu player who is looking out for themselves considers.
+const CANDIDATE_COUNT: usize = 4;
+// How many rounds are simulated to judge each of those rules.
+const SIMULATED_ROUNDS: u32 = 3;
+// The most turns that judging the rules for one choice can take.
+const MAX_TURNS_PER_CHOICE: u32 =
+    (CANDIDATE_COUNT as u32 + 1) * SIMULATED_ROUNDS * simulation::MAX_SIMULATED_TURNS;
+// The most turns simulated while the cpu players choose their rules between two rounds,
+// so the next round does not wait long however many rules they make. This is enough for
+// a few choices at worst, and usually several times that.
+const MAX_SIMULATED_TURNS_PER_RESET: u32 = MAX_TURNS_PER_CHOICE * 4;
+
+// Depending on how chaotic they are, cpu players either make a random rule, or pick the
+// rule that does the most for them in simulated rounds, out of a few random ones and one
+// that is meant to help them. Once there are not `MAX_TURNS_PER_CHOICE` of `turns_left`
+// to simulate, they make a random rule too.
+fn choose_cpu_rule(state: &mut GameState, player: PlayerID, turns_left: &mut u32) -> PendingRule {
+    let chaos = state
+        .config
+        .cpu_chaos
+        .get(player as usize)
+        .cloned()
+        .unwrap_or(Config::MAX_CHAOS);
+
+    if xs::range(&mut state.rngs.rules, 0..Config::MAX_CHAOS as u32) < chaos as u32
+        || *turns_left < MAX_TURNS_PER_CHOICE
+    {
+        return random_cpu_rule(state);
+    }
+
+    let mut candidates = Vec::with_capacity(CANDIDATE_COUNT + 1);
+    for _ in 0..CANDIDATE_COUNT {
+        candidates.push(random_cpu_rule(state));
+    }
+    if rule_type_enabled(state, Status::RuleSelectionWild) {
+        candidates.push(self_serving_wild_rule(state, player));
+    }
+
+    // Judging every rule on the same simulated deals makes the comparison fairer.
+    let seed = xs::new_seed(&mut state.rngs.rules);
+
+    let mut best: Option<(i32, PendingRule)> = None;
+    for candidate in candidates {
+        let mut rules = state.rules.for_simulation();
+        candidate.apply_to(&mut rules);
+
+        // A new deal takes effect straight away, so it is judged on new hands.
+        let redealt;
+        let in_game = match candidate {
+            PendingRule::Deal(ref deal) => {
+                redealt = in_game::State::new(
+                    &mut xs::from_seed(seed),
+                    deal,
+                    state.round_count + 1,
+                    state.in_game.human_seats,
+                    state.config.cpu_count,
+                );
+                &redealt
+            }
+            _ => &state.in_game,
+        };
+
+        let (score, turns) =
+            simulation::evaluate_rules(in_game, &rules, player, seed, SIMULATED_ROUNDS);
+        *turns_left = turns_left.saturating_sub(turns);
+
+        match best {
+            Some((best_score, _)) if best_score >= score => {}
+            _ => best = Some((score, candidate)),
+        }
+    }
+
+    match best {
+        Some((_, rule)) => rule,
+        None => random_cpu_rule(state),
+    }
+}
+
+/// `count` random rules of the types the game allows, for a player to pick between when
+/// they cannot use the rule editing screens, like someone playing over a network.
+pub fn rule_candidates(state: &mut GameState, count: usize) -> Vec<PendingRule> {
+    (0..count).map(|_| random_cpu_rule(state)).collect()
+}
+
+fn random_cpu_rule(state: &mut GameState) -> PendingRule {
+    let rule_type = generation::rule_type(
+        &mut state.rngs.rules,
+        &state.weights,
+        &state.config.rule_types,
+    );
 
     match rule_type {
-        Status::RuleSelectionCanPlay => add_cpu_can_play_graph_change(state, player),
-        Status::RuleSelectionWild => add_cpu_wild_change(state, player),
-        Status::RuleSelectionWhenPlayed => add_cpu_when_played_change(state, player),
-        Status::RuleSelection | Status::InGame => {
-            invariant_violation!("add_cpu_rule generated a non-rule type status");
+        Status::RuleSelectionCanPlay => random_cpu_can_play_graph_change(state),
+        Status::RuleSelectionWild => random_cpu_wild_change(state),
+        Status::RuleSelectionWhenPlayed => random_cpu_when_played_change(state),
+        Status::RuleSelectionDeal => random_cpu_deal_change(state),
+        Status::RuleSelection | Status::RuleSelectionConfirm | Status::InGame => {
+            invariant_violation!(
+                { random_cpu_wild_change(state) },
+                "random_cpu_rule generated a non-rule type status"
+            )
         }
     }
 }
 
-fn add_cpu_when_played_change(state: &mut GameState, player: PlayerID) {
-    let card_flags: CardFlags = CardFlags::from_rng(&mut state.rng);
+/// Whether the rule type that is made in `status` can be made in the current game.
+pub fn rule_type_enabled(state: &GameState, status: Status) -> bool {
+    RULE_TYPES
+        .iter()
+        .zip(state.config.rule_types.iter())
+        .any(|(&rule_type, &enabled)| enabled && rule_type == status)
+}
+
+// Wild cards can be played on anything, so making the rank a player has the most of wild
+// should help them get rid of their hand.
+fn self_serving_wild_rule(state: &GameState, player: PlayerID) -> PendingRule {
+    let mut rank_counts = [0u8; RANK_COUNT as usize];
+    for &card in state.in_game.get_hand(player).iter().filter(|&&c| !is_joker(c)) {
+        rank_counts[get_rank(card) as usize] += 1;
+    }
+
+    let rank = rank_counts
+        .iter()
+        .enumerate()
+        .max_by_key(|&(_, &count)| count)
+        .map(|(rank, _)| rank)
+        .unwrap_or_default();
+
+    PendingRule::Wild(state.rules.wild | RANK_FLAGS[rank])
+}
+
+fn random_cpu_when_played_change(state: &mut GameState) -> PendingRule {
+    let card_flags = generation::card_flags(
+        &mut state.rngs.rules,
+        &state.weights,
+        state.rules.deal.composition,
+    );
 
     let mut previous_changes: Vec<_> = state
         .rules
@@ -90,7 +342,7 @@ fn add_cpu_when_played_change(state: &mut GameState, player: PlayerID) {
         .get_card_flags_changes(card_flags)
         .collect();
 
-    let remove_count = xs::range(&mut state.rng, 0..5);
+    let remove_count = xs::range(&mut state.rngs.rules, 0..5);
 
     for _ in 0..remove_count {
         let len = previous_changes.len();
@@ -98,69 +350,21 @@ fn add_cpu_when_played_change(state: &mut GameState, player: PlayerID) {
             break;
         }
         previous_changes.remove(
-            xs::range(&mut state.rng, 0..len as _) as usize
+            xs::range(&mut state.rngs.rules, 0..len as _) as usize
         );
     }
 
-    let add_count = xs::range(&mut state.rng, 1..3) as usize;
+    let add_count = xs::range(&mut state.rngs.rules, 1..3) as usize;
     let mut new_card_changes: Vec<in_game::Change> = previous_changes;
     new_card_changes.reserve(add_count);
     for _ in 0..add_count {
-        new_card_changes.push(in_game::Change::from_rng(&mut state.rng));
-    }
-
-    apply_when_played_changes(state, card_flags, new_card_changes, player);
-}
-
-#[allow(dead_code)]
-enum Edit<T> {
-    Same(T),
-    Add(T),
-    Remove(T),
-}
-
-fn get_edits<T: Eq + Copy>(old_changes: &[T], new_changes: &[T]) -> Vec<Edit<T>> {
-    // TODO use an actual diffing algorithm instead of punting like this.
-    // see https://blog.jcoglan.com/2017/02/12/the-myers-diff-algorithm-part-1/
-    let mut additions: Vec<T> = Vec::new();
-    let mut removals: Vec<T> = Vec::new();
-
-    let mut tighter_start = None;
-    for (i, (old_c, new_c)) in old_changes.iter().zip(new_changes.iter()).enumerate() {
-        if old_c != new_c {
-            tighter_start = Some(i);
-            break;
-        }
+        new_card_changes.push(generation::change(&mut state.rngs.rules, &state.weights));
     }
 
-    let old = if let Some(i) = tighter_start {
-        &old_changes[i..]
-    } else {
-        old_changes
-    };
-
-    for &c in old.iter() {
-        removals.push(c);
-    }
-
-    let new = if let Some(i) = tighter_start {
-        &new_changes[i..]
-    } else {
-        new_changes
-    };
-
-    for &c in new.iter() {
-        additions.push(c);
-    }
-
-    removals
-        .into_iter()
-        .map(Edit::Remove)
-        .chain(additions.into_iter().map(Edit::Add))
-        .collect()
+    PendingRule::WhenPlayed(card_flags, new_card_changes)
 }
 
-pub fn apply_when_played_changes(
+fn apply_when_played_changes(
     state: &mut GameState,
     card_flags: CardFlags,
     new_changes: Vec<in_game::Change>,
@@ -169,17 +373,13 @@ pub fn apply_when_played_changes(
     //logging
     add_rule_change_log_header(state, player);
 
-    let pronoun = get_pronoun(player);
+    let pronoun = get_pronoun(state.in_game.human_seats, player);
 
     let rules = &mut state.rules;
 
-    let edits = {
-        let changes = rules.when_played.get_card_flags_changes(card_flags);
-
-        let vec: Vec<_> = changes.collect();
+    let old_changes: Vec<_> = rules.when_played.get_card_flags_changes(card_flags).collect();
 
-        get_edits(&vec, &new_changes)
-    };
+    let edits = get_edits(&old_changes, &new_changes);
 
     event_push!(
         state.event_log,
@@ -199,16 +399,32 @@ pub fn apply_when_played_changes(
         event_push!(state.event_log, prefix, c_string.as_bytes());
     }
 
+    if let Some(provenance) = rules.provenance.current {
+        rules.history.record(
+            provenance,
+            RuleEdit::WhenPlayed {
+                card_flags,
+                before: old_changes,
+                after: new_changes.clone(),
+            },
+        );
+    }
+
     rules.when_played.set_changes(card_flags, new_changes);
+    rules.provenance.mark_when_played(card_flags);
 }
 
-fn add_cpu_wild_change(state: &mut GameState, player: PlayerID) {
-    let new_wild: CardFlags = CardFlags::from_rng(&mut state.rng);
+fn random_cpu_wild_change(state: &mut GameState) -> PendingRule {
+    let new_wild = generation::card_flags(
+        &mut state.rngs.rules,
+        &state.weights,
+        state.rules.deal.composition,
+    );
 
-    apply_wild_change(state, new_wild, player);
+    PendingRule::Wild(new_wild)
 }
 
-pub fn apply_wild_change(state: &mut GameState, new_wild: CardFlags, player: PlayerID) {
+fn apply_wild_change(state: &mut GameState, new_wild: CardFlags, player: PlayerID) {
     //logging
     add_rule_change_log_header(state, player);
 
@@ -217,7 +433,7 @@ pub fn apply_wild_change(state: &mut GameState, new_wild: CardFlags, player: Pla
         removals,
     } = CardFlagsDelta::new(state.rules.wild, new_wild);
 
-    let pronoun = get_pronoun(player);
+    let pronoun = get_pronoun(state.in_game.human_seats, player);
 
     match (additions.is_empty(), removals.is_empty()) {
         (true, true) => {}
@@ -263,33 +479,101 @@ pub fn apply_wild_change(state: &mut GameState, new_wild: CardFlags, player: Pla
 
     /////////
 
+    if let Some(provenance) = state.rules.provenance.current {
+        state.rules.history.record(
+            provenance,
+            RuleEdit::Wild {
+                before: state.rules.wild,
+                after: new_wild,
+            },
+        );
+    }
+
     state.rules.wild = new_wild;
+    state.rules.provenance.mark_wild();
 }
 
-fn add_cpu_can_play_graph_change(state: &mut GameState, player: PlayerID) {
-    //TODO add single-strongly connected component checking and start
-    //generating non-additive changes;
-    let cards: CardFlags = CardFlags::from_rng(&mut state.rng);
+fn random_cpu_deal_change(state: &mut GameState) -> PendingRule {
+    let rng = &mut state.rngs.rules;
+    let mut deal = state.rules.deal;
 
-    let mut changes = Vec::with_capacity(cards.size() as usize);
-    for card in cards {
-        let old_edges = state.rules.can_play_graph.get_edges(card);
-        let edges = CardFlags::from_rng(&mut state.rng) | old_edges;
+    let sizes = in_game::Deal::MIN_HAND_SIZE as u32..in_game::Deal::MAX_HAND_SIZE as u32 + 1;
 
-        changes.push(can_play::Change::new(edges, card));
+    match xs::range(rng, 0..6) {
+        0 => deal.flip_starting_card = !deal.flip_starting_card,
+        1 => deal.rotate_dealer = !deal.rotate_dealer,
+        2 => deal.composition.jokers = !deal.composition.jokers,
+        3 => {
+            let decks = 1..DeckComposition::MAX_DECKS as u32 + 1;
+            deal.composition.decks = xs::range(rng, decks) as u8;
+        }
+        4 => deal.hand_sizes = [xs::range(rng, sizes) as u8; PLAYER_ID_COUNT],
+        _ => {
+            let player = xs::range(rng, 0..PLAYER_ID_COUNT as u32) as usize;
+            deal.hand_sizes[player] = xs::range(rng, sizes) as u8;
+        }
     }
 
-    apply_can_play_graph_changes(state, changes, player);
+    PendingRule::Deal(deal)
+}
+
+fn apply_deal_change(state: &mut GameState, new_deal: in_game::Deal, player: PlayerID) {
+    //logging
+    add_rule_change_log_header(state, player);
+
+    let pronoun = get_pronoun(state.in_game.human_seats, player);
+
+    event_push!(
+        state.event_log,
+        pronoun.as_bytes(),
+        b" changed how the cards are dealt:",
+    );
+
+    let seats = state.in_game.human_seats;
+    for line in in_game::Deal::describe_changes(&state.rules.deal, &new_deal, seats) {
+        state.event_log.push(line.as_bytes());
+    }
+
+    if let Some(provenance) = state.rules.provenance.current {
+        state.rules.history.record(
+            provenance,
+            RuleEdit::Deal {
+                before: state.rules.deal,
+                after: new_deal,
+            },
+        );
+    }
+
+    state.rules.deal = new_deal;
+    state.rules.provenance.mark_deal();
+
+    // The next round has already been dealt, so it is dealt again the new way.
+    state.redeal();
 }
 
-pub fn apply_can_play_graph_changes(
+fn random_cpu_can_play_graph_change(state: &mut GameState) -> PendingRule {
+    //TODO add single-strongly connected component checking and start
+    //generating non-additive changes;
+    let composition = state.rules.deal.composition;
+    let rng = &mut state.rngs.rules;
+    let set_change = can_play::SetChange {
+        cards: generation::card_flags(rng, &state.weights, composition),
+        operation: can_play::SetOperation::Add,
+        edges: generation::card_flags(rng, &state.weights, composition),
+    };
+
+    let graph = &state.rules.can_play_graph;
+    PendingRule::CanPlay(set_change.expand(|card| graph.get_edges(card)))
+}
+
+fn apply_can_play_graph_changes(
     state: &mut GameState,
     changes: Vec<can_play::Change>,
     player: PlayerID,
 ) {
     //TODO enforce a single strongly connected component in the graph
 
-    let mut unflattened_changes = [None; DECK_SIZE as usize];
+    let mut unflattened_changes = [None; FACE_COUNT as usize];
 
     for &change in changes.iter() {
         let index = change.card() as usize;
@@ -310,7 +594,7 @@ pub fn apply_can_play_graph_changes(
             removals,
         } = CardFlagsDelta::new(previous_edges, new_edges);
 
-        let pronoun = get_pronoun(player);
+        let pronoun = get_pronoun(state.in_game.human_seats, player);
         let card_string = get_card_string(new_card);
 
         match (additions.is_empty(), removals.is_empty()) {
@@ -356,18 +640,171 @@ pub fn apply_can_play_graph_changes(
 
         /////////
 
+        if let Some(provenance) = state.rules.provenance.current {
+            state.rules.history.record(
+                provenance,
+                RuleEdit::CanPlay {
+                    card: new_card,
+                    before: previous_edges,
+                    after: new_edges,
+                },
+            );
+        }
+
         state.rules.can_play_graph.set_edges(new_card, new_edges);
+        state.rules.provenance.mark_can_play(new_card);
     }
 }
 
+// Names the rule `apply_rule` is in the middle of applying.
 fn add_rule_change_log_header(state: &mut GameState, player: PlayerID) {
     state.event_log.push_hr();
 
-    let player_name = player_name(player);
+    let player_name = player_name(state.in_game.human_seats, player);
+
+    match state.rules.provenance.current {
+        Some(provenance) => event_push!(
+            state.event_log,
+            player_name.as_bytes(),
+            b" made rule #",
+            provenance.number.to_string().as_bytes(),
+            b", changing the rules as follows:"
+        ),
+        None => event_push!(
+            state.event_log,
+            player_name.as_bytes(),
+            b" changed the rules as follows:"
+        ),
+    }
+}
 
-    event_push!(
-        state.event_log,
-        player_name.as_bytes(),
-        b" changed the rules as follows:"
-    );
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_state::weights::Weights;
+
+    #[test]
+    fn test_no_one_changes_the_rules_in_crazy_eights() {
+        let config = Config {
+            mode: GameMode::CrazyEights,
+            ..d!()
+        };
+        let mut state = GameState::new([42; 16], config);
+        state.in_game.winners = all_player_ids().to_vec();
+
+        reset(&mut state);
+
+        assert!(matches!(state.status, Status::InGame));
+        assert_eq!(state.config.mode, GameMode::CrazyEights);
+        assert!(state.rules.provenance.current.is_none());
+    }
+
+    #[test]
+    fn test_the_weights_are_kept_for_the_next_game_and_round() {
+        let weights = Weights {
+            set_size: [0, 1, 0, 0, 0, 0],
+            ..d!()
+        };
+        let mut state = GameState::new([42; 16], d!());
+        state.weights = weights.clone();
+
+        start(&mut state);
+        assert_eq!(state.weights, weights);
+
+        state.in_game.winners = all_player_ids().to_vec();
+        reset(&mut state);
+        assert_eq!(state.weights, weights);
+    }
+
+    #[test]
+    fn test_bartog_with_random_rules_starts_with_rules_made() {
+        let mut state = GameState::new([42; 16], d!());
+        state.config.set_mode(GameMode::BartogWithRandomRules(3));
+
+        start(&mut state);
+
+        assert_eq!(state.config.mode, GameMode::BartogWithRandomRules(3));
+        let numbers: Vec<_> = state
+            .rules
+            .history
+            .entries()
+            .iter()
+            .map(|entry| entry.provenance.number)
+            .collect();
+        assert!(!numbers.is_empty());
+        assert!(numbers.iter().all(|&number| (1..=3).contains(&number)));
+        // Nothing that happens later can be attributed to the last of those rules.
+        assert!(state.rules.provenance.current.is_none());
+        assert_ne!(
+            state.rules.get_reference(d!()),
+            game_state::Rules::default().get_reference(d!())
+        );
+    }
+
+    #[test]
+    fn test_cpu_players_stop_simulating_rules_once_the_turns_between_rounds_run_out() {
+        let config = Config {
+            cpu_chaos: [0; PLAYER_ID_COUNT],
+            ..d!()
+        };
+        let mut state = GameState::new([42; 16], config);
+
+        let mut turns_left = MAX_TURNS_PER_CHOICE;
+        choose_cpu_rule(&mut state, 0, &mut turns_left);
+        assert!(turns_left < MAX_TURNS_PER_CHOICE);
+
+        // There are not enough turns left to judge another choice, so none are simulated.
+        let before = turns_left;
+        choose_cpu_rule(&mut state, 0, &mut turns_left);
+        assert_eq!(turns_left, before);
+    }
+
+    #[test]
+    fn test_everyone_gets_the_same_daily_challenge_whatever_their_settings() {
+        let daily = |config: Config| {
+            let mut state = GameState::new([42; 16], config);
+            start(&mut state);
+            state
+        };
+
+        let today = Config {
+            mode: GameMode::Daily,
+            today: 20745,
+            ..d!()
+        };
+        let changed = Config {
+            cpu_count: 1,
+            hand_size: 3,
+            seed: Some(7),
+            rule_types: [false; RULE_TYPES.len()],
+            cpu_chaos: [0; PLAYER_ID_COUNT],
+            rule_budget: Config::MAX_RULE_BUDGET,
+            ..today
+        };
+        let tomorrow = Config {
+            today: today.today + 1,
+            ..today
+        };
+
+        let a = daily(today);
+        let b = daily(changed);
+        let c = daily(tomorrow);
+
+        assert_eq!(a.seed_number, b.seed_number);
+        let hands = |state: &GameState| {
+            all_player_ids()
+                .iter()
+                .map(|&player| state.in_game.get_hand(player).iter().cloned().collect())
+                .collect::<Vec<Vec<Card>>>()
+        };
+        assert_eq!(hands(&a), hands(&b));
+        assert_eq!(a.rules.get_reference(d!()), b.rules.get_reference(d!()));
+        assert_eq!(a.config.cpu_chaos, b.config.cpu_chaos);
+        assert_eq!(a.config.rule_budget, b.config.rule_budget);
+        assert_ne!(a.seed_number, c.seed_number);
+
+        // The settings that were chosen come back once the challenge is over.
+        assert_eq!(b.chosen_config, Some(changed));
+        assert_eq!(b.config, changed.daily());
+    }
 }
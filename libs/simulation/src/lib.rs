@@ -0,0 +1,8 @@
+extern crate animations;
+
+extern crate common;
+
+extern crate game_state;
+
+mod simulation;
+pub use self::simulation::*;
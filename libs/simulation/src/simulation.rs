@@ -0,0 +1,562 @@
+use animations::{
+    add_discard_animation, draw_on_turn, log_wild_selection_parts, play_to_discard_parts,
+};
+use common::{xs::Xs, *};
+use game_state::{in_game, EventLog, GameState, PendingRule, RulePreview, Rules, SimulationStats};
+
+/// The most turns a simulated round can last. Some rules make rounds that never end.
+pub const MAX_SIMULATED_TURNS: u32 = 256;
+
+//Since this uses rng, calling this in response to repeatable user input allows rng manipulation.
+//So pass it a generator that only cpu decisions use, like `Rngs::cpu`, so the deals stay fixed.
+pub fn cpu_would_play(
+    state: &mut in_game::State,
+    rng: &mut Xs,
+    rules: &Rules,
+    player_id: PlayerID,
+) -> Option<u8> {
+    let playable: Vec<(usize, Card)> = {
+        let hand = state.get_hand(player_id);
+        hand.iter()
+            .cloned()
+            .enumerate()
+            .filter(|&(_, card)| rules.can_play(state, card))
+            .collect()
+    };
+
+    if let in_game::Difficulty::Easy = state.difficulty {
+        if playable.is_empty() {
+            return None;
+        }
+        let index = xs::range(rng, 0..playable.len() as u32) as usize;
+        return Some(playable[index].0 as u8);
+    }
+
+    let sim_state = get_sim_state(state, rng, player_id);
+
+    let mut indexes_and_hand_deltas = Vec::with_capacity(playable.len());
+
+    for (i, card) in playable {
+        let delta = get_hand_delta(&sim_state, rules, rng, player_id, card);
+
+        indexes_and_hand_deltas.push((i, delta));
+    }
+
+    //if we make repeated decisions with equal weight, sometimes choose differently.
+    xs::shuffle(rng, &mut indexes_and_hand_deltas);
+
+    if let in_game::Difficulty::Hard = state.difficulty {
+        // Among equally good plays, save the wild cards for when they are needed.
+        let hand = state.get_hand(player_id);
+        indexes_and_hand_deltas.sort_by_key(|&(i, delta)| {
+            let is_wild = hand.get(i as u8).is_some_and(|&card| rules.is_wild(card));
+            (-delta, !is_wild)
+        });
+    } else {
+        indexes_and_hand_deltas.sort_by_key(
+            |&(_, delta)| -delta, //highest negative delta to end
+        );
+    }
+
+    indexes_and_hand_deltas.pop().map(|(i, _)| i as u8)
+}
+
+/// Has cpu player `player` take their turn. Returns whether they played or drew a card.
+pub fn take_cpu_turn(game_state: &mut GameState, player: PlayerID) -> bool {
+    let state = &mut game_state.in_game;
+    let rules = &game_state.rules;
+
+    state.current_player = player;
+    if let Some(index) = cpu_would_play(state, &mut game_state.rngs.cpu, rules, player) {
+        add_discard_animation(state, index, &mut game_state.event_log, rules);
+        true
+    } else {
+        draw_on_turn(game_state, player)
+    }
+}
+
+fn get_hand_delta(
+    state: &in_game::State,
+    rules: &Rules,
+    rng: &mut Xs,
+    player_id: PlayerID,
+    card: Card,
+) -> i8 {
+    let original = state.get_hand(player_id).len() as i8;
+    let mut s: in_game::State = (*state).clone();
+
+    play_to_discard_parts(&mut s, rules, rng, &mut None, card);
+
+    let new = s.get_hand(player_id).len() as i8;
+
+    new - original
+}
+
+pub fn get_sim_state(state: &in_game::State, rng: &mut Xs, player_id: PlayerID) -> in_game::State {
+    // We don't want the cpu to cheat, so don't let them see what is really on top of the deck.
+    // or which cards are in each player's hand either. So put all unknown cards in one pile
+    // then shuffle them randomly to each unknown zone, maintaining the original amounts.
+    let mut pile = Vec::with_capacity(state.composition.size() as usize);
+
+    macro_rules! add_to_pile {
+        ($e:expr) => {
+            pile.extend($e.clone().drain());
+        };
+    }
+
+    add_to_pile!(state.deck);
+    add_to_pile!(state.discard);
+
+    match player_id {
+        0 => {
+            add_to_pile!(state.cpu_hands[1]);
+            add_to_pile!(state.cpu_hands[2]);
+            add_to_pile!(state.hand);
+        }
+        1 => {
+            add_to_pile!(state.cpu_hands[0]);
+            add_to_pile!(state.cpu_hands[2]);
+            add_to_pile!(state.hand);
+        }
+        2 => {
+            add_to_pile!(state.cpu_hands[0]);
+            add_to_pile!(state.cpu_hands[1]);
+            add_to_pile!(state.hand);
+        }
+        MAX_PLAYER_ID => {
+            add_to_pile!(state.cpu_hands[0]);
+            add_to_pile!(state.cpu_hands[1]);
+            add_to_pile!(state.cpu_hands[2]);
+        }
+        _ => invariant_violation!({}, "get_sim_state called with bad PlayerID"),
+    }
+
+    xs::shuffle(rng, &mut pile);
+
+    let mut output = in_game::State {
+        current_player: state.current_player,
+        top_wild_declared_as: state.top_wild_declared_as,
+        stalemate_resolution: state.stalemate_resolution,
+        idle_turns: state.idle_turns,
+        composition: state.composition,
+        empty_seats: state.empty_seats,
+        difficulty: state.difficulty,
+        ..d!()
+    };
+
+    macro_rules! deal_into {
+        ($hand:ident) => {
+            let pile_drain = pile.drain(..state.$hand.len() as usize);
+            output.$hand.fill(pile_drain);
+        };
+        ($hand:ident, [$index:expr]) => {
+            let pile_drain = pile.drain(..state.$hand[$index].len() as usize);
+            output.$hand[$index].fill(pile_drain);
+        };
+    }
+
+    deal_into!(deck);
+    deal_into!(discard);
+
+    match player_id {
+        0 => {
+            deal_into!(cpu_hands, [1]);
+            deal_into!(cpu_hands, [2]);
+            deal_into!(hand);
+        }
+        1 => {
+            deal_into!(cpu_hands, [0]);
+            deal_into!(cpu_hands, [2]);
+            deal_into!(hand);
+        }
+        2 => {
+            deal_into!(cpu_hands, [0]);
+            deal_into!(cpu_hands, [1]);
+            deal_into!(hand);
+        }
+        MAX_PLAYER_ID => {
+            deal_into!(cpu_hands, [0]);
+            deal_into!(cpu_hands, [1]);
+            deal_into!(cpu_hands, [2]);
+        }
+        _ => invariant_violation!({}, "get_sim_state called with bad PlayerID"),
+    }
+
+    // The player can see their own hand, of course.
+    if player_id <= MAX_PLAYER_ID {
+        *output.get_hand_mut(player_id) = state.get_hand(player_id).clone();
+    }
+
+    output
+}
+
+/// Completes any card animations immediately, instead of over several frames.
+pub fn settle(state: &mut in_game::State, rules: &Rules, rng: &mut Xs) {
+    settle_until_wild(state, rules, rng, &mut None, |state, player_id| {
+        Some(state.get_hand(player_id).most_common_suit())
+    });
+
+    // Put any cards that are still moving somewhere, so none go missing.
+    for animation in state.card_animations.drain(..) {
+        state.discard.push(animation.card.card);
+    }
+}
+
+/// Completes the card animations immediately, like `settle`, except for the wild cards
+/// that `declare_suit` does not have a suit for yet. `declare_suit` is given the player
+/// who played the wild card, and returns `None` if they have not declared a suit, or else
+/// the suit they declared. Those cards are left animating, along with anything they would
+/// have set off. Returns whether any animations were completed.
+pub fn settle_until_wild(
+    state: &mut in_game::State,
+    rules: &Rules,
+    rng: &mut Xs,
+    event_log: &mut Option<&mut EventLog>,
+    mut declare_suit: impl FnMut(&in_game::State, PlayerID) -> Option<Option<Suit>>,
+) -> bool {
+    let mut settled_any = false;
+
+    // Playing a card can start more animations, so we cap the number of passes in case
+    // the rules would keep doing that forever.
+    for _ in 0..DECK_SIZE {
+        if state.animations_settled() {
+            break;
+        }
+
+        let mut waiting = false;
+        for animation in std::mem::take(&mut state.card_animations) {
+            let card = animation.card.card;
+
+            match animation.completion_action {
+                Action::PlayToDiscard => {
+                    play_to_discard_parts(state, rules, rng, event_log, card);
+                }
+                Action::SelectWild(player_id) => match declare_suit(state, player_id) {
+                    Some(suit) => {
+                        state.top_wild_declared_as = suit;
+                        if let Some(event_log) = event_log.as_mut() {
+                            log_wild_selection_parts(state, event_log, player_id);
+                        }
+                        play_to_discard_parts(state, rules, rng, event_log, card);
+                    }
+                    None => {
+                        state.card_animations.push(animation);
+                        waiting = true;
+                        continue;
+                    }
+                },
+                Action::MoveToDeck => state.deck.push(card),
+                Action::MoveToDiscard => state.discard.push(card),
+                Action::MoveToHand(player_id) => state.get_hand_mut(player_id).push(card),
+            }
+            settled_any = true;
+        }
+
+        if waiting {
+            break;
+        }
+    }
+
+    settled_any
+}
+
+/// Plays the next player's turn the way a cpu player would, without animations or logging.
+/// Returns the card that was played, if any, and the card it was played on, if any.
+pub fn take_simulated_turn(
+    state: &mut in_game::State,
+    rules: &Rules,
+    rng: &mut Xs,
+) -> Option<(Card, Option<Card>)> {
+    let player = state.incremented_current_player();
+    state.current_player = player;
+
+    let onto = state.discard.last().cloned();
+    let mut played = None;
+    let mut acted = true;
+
+    match cpu_would_play(state, rng, rules, player)
+        .and_then(|index| state.remove_positioned_card(player, index))
+    {
+        Some(PositionedCard { card, .. }) => {
+            if rules.is_wild(card) {
+                state.top_wild_declared_as = state.get_hand(player).most_common_suit();
+            }
+            play_to_discard_parts(state, rules, rng, &mut None, card);
+            played = Some((card, onto));
+        }
+        None => {
+            let card = match state.deck.draw() {
+                Some(card) => Some(card),
+                None => state
+                    .reshuffle_discard(rng)
+                    .and_then(|()| state.deck.draw()),
+            };
+
+            match card {
+                Some(card) => state.get_hand_mut(player).push(card),
+                None => acted = false,
+            }
+        }
+    }
+
+    settle(state, rules, rng);
+
+    if state.no_winners_yet() {
+        state.winners = state.empty_handed_players();
+    }
+
+    state.end_turn(acted, rng);
+
+    played
+}
+
+/// Plays out the rest of the round with every player playing like a cpu player, for at
+/// most `MAX_SIMULATED_TURNS`. Returns how many turns were played.
+pub fn play_out_round(state: &mut in_game::State, rules: &Rules, rng: &mut Xs) -> u32 {
+    let mut turns = 0;
+    while turns < MAX_SIMULATED_TURNS && !state.round_has_ended() {
+        take_simulated_turn(state, rules, rng);
+        turns += 1;
+    }
+
+    turns
+}
+
+/// How well `player` does, on average, when the rest of the round is played out under
+/// `rules`, as far as they can tell from what they know about `state`. Higher is better.
+/// Using the same seed for different rules means they are all judged on the same deals.
+/// Also returns how many turns were simulated, which is at most `rounds` times
+/// `MAX_SIMULATED_TURNS`.
+pub fn evaluate_rules(
+    state: &in_game::State,
+    rules: &Rules,
+    player: PlayerID,
+    seed: xs::Seed,
+    rounds: u32,
+) -> (i32, u32) {
+    let mut rng = xs::from_seed(seed);
+    let mut total = 0;
+    let mut turns = 0;
+
+    for _ in 0..rounds {
+        let mut sim_state = get_sim_state(state, &mut rng, player);
+        turns += play_out_round(&mut sim_state, rules, &mut rng);
+
+        total += score(&sim_state, player);
+    }
+
+    (total / rounds.max(1) as i32, turns)
+}
+
+/// How many rounds of a preview to simulate each frame, with and without the rule.
+pub const PREVIEW_ROUNDS_PER_FRAME: u32 = 8;
+
+/// Simulates up to `rounds` more rounds of `preview`, with and without the rule, from the
+/// same deals.
+pub fn advance_preview(preview: &mut RulePreview, rounds: u32) {
+    for _ in 0..rounds {
+        if preview.is_done() {
+            return;
+        }
+
+        let seed = xs::new_seed(&mut preview.rng);
+
+        let seats = preview.human_seats;
+        let cpu_count = preview.cpu_count;
+        simulate_round(
+            seed,
+            seats,
+            cpu_count,
+            &preview.without,
+            None,
+            &mut preview.stats_without,
+        );
+        simulate_round(
+            seed,
+            seats,
+            cpu_count,
+            &preview.with,
+            Some((&preview.rule, &preview.without)),
+            &mut preview.stats_with,
+        );
+    }
+}
+
+// `rule` is the rule to count the firings of, along with the rules from before it.
+fn simulate_round(
+    seed: xs::Seed,
+    human_seats: HumanSeats,
+    cpu_count: u8,
+    rules: &Rules,
+    rule: Option<(&PendingRule, &Rules)>,
+    stats: &mut SimulationStats,
+) {
+    let mut rng = xs::from_seed(seed);
+    let mut state =
+        in_game::State::new(&mut rng, &rules.deal, stats.rounds, human_seats, cpu_count);
+
+    stats.rounds += 1;
+
+    for _ in 0..MAX_SIMULATED_TURNS {
+        if state.round_has_ended() {
+            break;
+        }
+
+        let played = take_simulated_turn(&mut state, rules, &mut rng);
+        stats.turns += 1;
+
+        if let (Some((card, onto)), Some((rule, before))) = (played, rule) {
+            if rule_fires(rule, before, card, onto) {
+                stats.fires += 1;
+            }
+        }
+    }
+
+    if !state.round_has_ended() {
+        stats.unfinished += 1;
+    }
+
+    for &winner in state.winners.iter() {
+        if let Some(wins) = stats.wins.get_mut(winner as usize) {
+            *wins += 1;
+        }
+    }
+}
+
+/// Whether playing `card` onto `onto` went differently because of `rule`, compared to
+/// under `before`.
+pub fn rule_fires(rule: &PendingRule, before: &Rules, card: Card, onto: Option<Card>) -> bool {
+    match rule {
+        PendingRule::Wild(wild) => wild.has_card(card) != before.wild.has_card(card),
+        PendingRule::WhenPlayed(card_flags, _) => card_flags.has_card(card),
+        // The deal is over before any cards are played.
+        PendingRule::Deal(_) => false,
+        PendingRule::CanPlay(changes) => match onto {
+            Some(onto) => changes.iter().any(|change| {
+                change.card() == card
+                    && change.edges().has_card(onto)
+                        != before.can_play_graph.get_edges(card).has_card(onto)
+            }),
+            None => false,
+        },
+    }
+}
+
+const WIN_SCORE: i32 = 60;
+
+// Winning is what matters most, but ending with fewer cards than everyone else is
+// better than nothing.
+fn score(state: &in_game::State, player: PlayerID) -> i32 {
+    let mut output = 0;
+
+    if state.winners.contains(&player) {
+        output += WIN_SCORE / state.winners.len() as i32;
+    }
+
+    let seated = state.seated_player_ids();
+
+    for &other in seated.iter() {
+        let len = state.get_hand(other).len() as i32;
+        if other == player {
+            output -= len * (seated.len() as i32 - 1);
+        } else {
+            output += len;
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use game_state::in_game::StalemateResolution;
+
+    #[test]
+    fn test_a_simulated_round_keeps_every_card() {
+        let mut rng = xs::from_seed([7; 16]);
+        let rules = Rules::default();
+
+        for _ in 0..16 {
+            let mut state = in_game::State::new(&mut rng, &d!(), 1, d!(), CPU_COUNT as u8);
+
+            play_out_round(&mut state, &rules, &mut rng);
+
+            assert_eq!(state.missing_cards(), vec![0; 0]);
+        }
+    }
+
+    #[test]
+    fn test_a_preview_of_making_everything_wild_runs_to_completion_and_sees_it_fire() {
+        let rules = Rules::default();
+        let rule = PendingRule::Wild(CardFlags::new(ALL_FLAGS));
+        let mut preview = RulePreview::new(&rules, rule, [3; 16], d!(), CPU_COUNT as u8);
+
+        let mut frames = 0;
+        while !preview.is_done() {
+            advance_preview(&mut preview, PREVIEW_ROUNDS_PER_FRAME);
+            frames += 1;
+            assert!(frames <= RulePreview::ROUNDS);
+        }
+
+        assert_eq!(preview.stats_with.rounds, RulePreview::ROUNDS);
+        assert_eq!(preview.stats_without.rounds, RulePreview::ROUNDS);
+        assert!(preview.stats_with.fires > 0);
+        assert_eq!(preview.stats_without.fires, 0);
+    }
+
+    #[test]
+    fn test_get_sim_state_keeps_the_players_hand_and_the_hand_sizes() {
+        let mut rng = xs::from_seed([11; 16]);
+        let mut state = in_game::State::new(&mut rng, &d!(), 1, d!(), CPU_COUNT as u8);
+        let card = state.deck.draw().unwrap();
+        state.cpu_hands[2].push(card);
+
+        for player in all_player_ids() {
+            let sim_state = get_sim_state(&state, &mut rng, player);
+
+            assert_eq!(sim_state.missing_cards(), vec![0; 0]);
+            assert_eq!(sim_state.deck.len(), state.deck.len());
+            for other in all_player_ids() {
+                assert_eq!(sim_state.get_hand(other).len(), state.get_hand(other).len());
+            }
+            assert!(sim_state
+                .get_hand(player)
+                .iter()
+                .eq(state.get_hand(player).iter()));
+        }
+    }
+
+    #[test]
+    fn test_a_lap_of_idle_turns_resolves_the_stalemate() {
+        let mut rng = xs::from_seed([13; 16]);
+        let mut state = in_game::State::new(&mut rng, &d!(), 1, d!(), CPU_COUNT as u8);
+        state.cpu_hands[1].fill(state.deck.drain());
+        state.discard.push(state.cpu_hands[1].draw().unwrap());
+
+        for _ in 1..PLAYER_ID_COUNT {
+            assert_eq!(state.end_turn(false, &mut rng), None);
+        }
+
+        let mut stuck = state.clone();
+        assert_eq!(
+            stuck.end_turn(false, &mut rng),
+            Some(StalemateResolution::FewestCards)
+        );
+        assert_eq!(stuck.winners, vec![0, 2, 3]);
+        assert!(stuck.round_has_ended());
+
+        let mut stuck = state.clone();
+        stuck.stalemate_resolution = StalemateResolution::Draw;
+        stuck.end_turn(false, &mut rng);
+        assert!(stuck.no_winners_yet());
+        assert!(stuck.round_has_ended());
+
+        let mut stuck = state;
+        stuck.stalemate_resolution = StalemateResolution::Reshuffle;
+        stuck.end_turn(false, &mut rng);
+        assert!(stuck.discard.is_empty());
+        assert!(!stuck.round_has_ended());
+        assert_eq!(stuck.missing_cards(), vec![0; 0]);
+    }
+}